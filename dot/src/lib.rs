@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub id: String,
+    pub label: Option<String>,
+}
+
+impl Node {
+    pub fn new(id: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            label: None,
+        }
+    }
+
+    pub fn with_label(id: &str, label: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            label: Some(label.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: Option<String>,
+}
+
+impl Edge {
+    pub fn new(from: &str, to: &str) -> Edge {
+        Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label: None,
+        }
+    }
+
+    pub fn with_label(from: &str, to: &str, label: &str) -> Edge {
+        Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label: Some(label.to_string()),
+        }
+    }
+}
+
+//Nodes and edges to draw on top of the plain graph: a path is drawn in red,
+//visited nodes are filled
+#[derive(Debug, Clone, Default)]
+pub struct Highlight {
+    path_nodes: HashSet<String>,
+    path_edges: HashSet<(String, String)>,
+    visited: HashSet<String>,
+}
+
+impl Highlight {
+    pub fn new() -> Highlight {
+        Highlight::default()
+    }
+
+    pub fn path<S: AsRef<str>>(mut self, path: &[S]) -> Highlight {
+        for node in path {
+            self.path_nodes.insert(node.as_ref().to_string());
+        }
+        for pair in path.windows(2) {
+            self.path_edges
+                .insert((pair[0].as_ref().to_string(), pair[1].as_ref().to_string()));
+        }
+        self
+    }
+
+    pub fn visited<S: AsRef<str>>(mut self, nodes: impl IntoIterator<Item = S>) -> Highlight {
+        for node in nodes {
+            self.visited.insert(node.as_ref().to_string());
+        }
+        self
+    }
+
+    fn on_path(&self, edge: &Edge, directed: bool) -> bool {
+        let forward = (edge.from.clone(), edge.to.clone());
+        let backward = (edge.to.clone(), edge.from.clone());
+        self.path_edges.contains(&forward) || (!directed && self.path_edges.contains(&backward))
+    }
+}
+
+fn quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+fn attributes(attributes: &[(&str, String)]) -> String {
+    if attributes.is_empty() {
+        return String::new();
+    }
+    let list = attributes
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(" [{}]", list)
+}
+
+pub trait Dot {
+    fn nodes(&self) -> Vec<Node>;
+
+    fn edges(&self) -> Vec<Edge>;
+
+    fn graph_name(&self) -> String {
+        "G".to_string()
+    }
+
+    //Undirected graphs only draw one edge for each connected pair
+    fn directed(&self) -> bool {
+        true
+    }
+
+    fn to_dot(&self, highlight: &Highlight) -> String {
+        let directed = self.directed();
+        let (keyword, arrow) = if directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+
+        let mut s = format!("{} {} {{\n", keyword, quote(&self.graph_name()));
+
+        for node in self.nodes() {
+            let mut attrs = Vec::new();
+            if let Some(label) = &node.label {
+                attrs.push(("label", quote(label)));
+            }
+            if highlight.visited.contains(&node.id) {
+                attrs.push(("style", quote("filled")));
+                attrs.push(("fillcolor", quote("lightgrey")));
+            }
+            if highlight.path_nodes.contains(&node.id) {
+                attrs.push(("color", quote("red")));
+                attrs.push(("penwidth", "2".to_string()));
+            }
+            s.push_str(&format!("    {}{};\n", quote(&node.id), attributes(&attrs)));
+        }
+
+        let mut seen = HashSet::new();
+        for edge in self.edges() {
+            if !directed {
+                let key = if edge.from <= edge.to {
+                    (edge.from.clone(), edge.to.clone(), edge.label.clone())
+                } else {
+                    (edge.to.clone(), edge.from.clone(), edge.label.clone())
+                };
+                if !seen.insert(key) {
+                    continue;
+                }
+            }
+
+            let mut attrs = Vec::new();
+            if let Some(label) = &edge.label {
+                attrs.push(("label", quote(label)));
+            }
+            if highlight.on_path(&edge, directed) {
+                attrs.push(("color", quote("red")));
+                attrs.push(("penwidth", "2".to_string()));
+            }
+            s.push_str(&format!(
+                "    {} {} {}{};\n",
+                quote(&edge.from),
+                arrow,
+                quote(&edge.to),
+                attributes(&attrs)
+            ));
+        }
+
+        s.push_str("}\n");
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Triangle {
+        directed: bool,
+    }
+
+    impl Dot for Triangle {
+        fn nodes(&self) -> Vec<Node> {
+            vec![
+                Node::with_label("a", "A\n\"start\""),
+                Node::new("b"),
+                Node::new("c"),
+            ]
+        }
+
+        fn edges(&self) -> Vec<Edge> {
+            vec![
+                Edge::with_label("a", "b", "1"),
+                Edge::with_label("b", "a", "1"),
+                Edge::new("b", "c"),
+                Edge::new("c", "a"),
+            ]
+        }
+
+        fn directed(&self) -> bool {
+            self.directed
+        }
+    }
+
+    #[test]
+    fn test_directed() {
+        let dot = Triangle { directed: true }.to_dot(&Highlight::new());
+        assert_eq!(
+            dot,
+            "digraph \"G\" {
+    \"a\" [label=\"A\\n\\\"start\\\"\"];
+    \"b\";
+    \"c\";
+    \"a\" -> \"b\" [label=\"1\"];
+    \"b\" -> \"a\" [label=\"1\"];
+    \"b\" -> \"c\";
+    \"c\" -> \"a\";
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_undirected_dedup() {
+        let dot = Triangle { directed: false }.to_dot(&Highlight::new());
+        assert!(dot.starts_with("graph \"G\" {\n"));
+        assert_eq!(dot.matches(" -- ").count(), 3);
+    }
+
+    #[test]
+    fn test_highlight() {
+        let highlight = Highlight::new().path(&["a", "b"]).visited(["c"]);
+
+        let dot = Triangle { directed: true }.to_dot(&highlight);
+        assert!(dot.contains("\"a\" -> \"b\" [label=\"1\", color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"b\" -> \"a\" [label=\"1\"];"));
+        assert!(dot.contains("\"c\" [style=\"filled\", fillcolor=\"lightgrey\"];"));
+        assert!(dot.contains("\"b\" [color=\"red\", penwidth=2];"));
+
+        let dot = Triangle { directed: false }.to_dot(&Highlight::new().path(&["b", "a"]));
+        assert!(dot.contains("\"a\" -- \"b\" [label=\"1\", color=\"red\", penwidth=2];"));
+    }
+}
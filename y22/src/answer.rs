@@ -0,0 +1,85 @@
+use std::fmt::Display;
+
+//What a puzzle part produces, printing is left to the caller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Number(i128),
+    Text(String),
+    //The part has no answer for this input, with the reason why
+    Unsolved(String),
+}
+
+macro_rules! answer_from_number {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Answer {
+                fn from(n: $t) -> Self {
+                    Answer::Number(n as i128)
+                }
+            }
+        )*
+    };
+}
+
+answer_from_number!(i32, i64, i128, u32, u64, usize);
+
+impl From<String> for Answer {
+    fn from(s: String) -> Self {
+        Answer::Text(s)
+    }
+}
+
+impl From<&str> for Answer {
+    fn from(s: &str) -> Self {
+        Answer::Text(s.to_string())
+    }
+}
+
+impl<T: Into<Answer>> From<Option<T>> for Answer {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Answer::Unsolved("no solution".to_string()), Into::into)
+    }
+}
+
+impl<T: Into<Answer>, E: Display> From<Result<T, E>> for Answer {
+    fn from(value: Result<T, E>) -> Self {
+        match value {
+            Ok(value) => value.into(),
+            Err(e) => Answer::Unsolved(e.to_string()),
+        }
+    }
+}
+
+impl Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Answer::Number(n) => write!(f, "{}", n),
+            //Multi-line answers like the day 10 screen start on their own line
+            Answer::Text(s) if s.contains('\n') => write!(f, "\n{}", s.trim_end()),
+            Answer::Text(s) => write!(f, "{}", s),
+            Answer::Unsolved(reason) => write!(f, "unsolved ({})", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Answer::from(42usize), Answer::Number(42));
+        assert_eq!(Answer::from(-3i64).to_string(), "-3");
+        assert_eq!(Answer::from("CMZ").to_string(), "CMZ");
+        assert_eq!(Answer::from(Some(7u32)), Answer::Number(7));
+        assert_eq!(
+            Answer::from(None::<u32>),
+            Answer::Unsolved("no solution".to_string())
+        );
+        assert_eq!(
+            Answer::from(Err::<u32, _>("stack 3 is empty")).to_string(),
+            "unsolved (stack 3 is empty)"
+        );
+        assert_eq!(Answer::from("#.\n.#\n").to_string(), "\n#.\n.#");
+    }
+}
@@ -1,36 +1,185 @@
-pub fn run() {
-    println!("Hello, day 1");
-
-    //read input file
-    let input = std::fs::read_to_string("input/1").unwrap();
-
-    //split input into lines
-    let lines: Vec<&str> = input.split("\n").collect();
-
-    //Array of the three elves with the highest total calories
-    let mut top_3_elves: [(u32, u32); 3] = [(0, 0), (0, 0), (0, 0)];
-
-    let mut elf_calories = 0;
-    let mut elf_number = 0;
-    for line in lines {
-        if line.len() == 0 {
-            //Check if this elf is in the top 3
-            if elf_calories > top_3_elves[0].1 {
-                top_3_elves[0] = (elf_number, elf_calories);
-                top_3_elves.sort_by(|a, b| a.1.cmp(&b.1));
+use crate::answer::Answer;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::Display,
+    io::{self, BufRead},
+};
+
+#[derive(Debug)]
+enum ReadError {
+    Io(io::Error),
+    InvalidCalories { line: usize, text: String },
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "read error: {}", e),
+            ReadError::InvalidCalories { line, text } => {
+                write!(f, "invalid calories on line {}: {:?}", line, text)
+            }
+        }
+    }
+}
+
+//Sums of the blank line separated groups in a stream, one group at a time.
+//Runs of blank lines count as one separator and the last group doesn't need
+//a trailing blank line.
+struct Groups<R> {
+    reader: R,
+    line: usize,
+    buf: String,
+    done: bool,
+}
+
+impl<R: BufRead> Groups<R> {
+    fn new(reader: R) -> Groups<R> {
+        Groups {
+            reader,
+            line: 0,
+            buf: String::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Groups<R> {
+    type Item = Result<u64, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut total: Option<u64> = None;
+        while !self.done {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    let text = self.buf.trim_end_matches(['\r', '\n']);
+                    if text.trim().is_empty() {
+                        if total.is_some() {
+                            break;
+                        }
+                        continue;
+                    }
+                    match text.trim().parse::<u64>() {
+                        Ok(calories) => *total.get_or_insert(0) += calories,
+                        Err(_) => {
+                            self.done = true;
+                            return Some(Err(ReadError::InvalidCalories {
+                                line: self.line,
+                                text: text.to_string(),
+                            }));
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(ReadError::Io(e)));
+                }
             }
-            elf_number += 1;
-            elf_calories = 0;
-        } else {
-            let calories: u32 = line.parse().unwrap();
-            elf_calories += calories;
         }
+        total.map(Ok)
     }
-    let mut total_calories = 0;
-    println!("Top 3 elves: ");
-    for elf in top_3_elves.iter() {
-        print!("Nr. {}, Calories: {} \n", elf.0, elf.1);
-        total_calories += elf.1;
+}
+
+//The n largest items with their index, largest first. Ties keep input order.
+fn top_n<T: Ord>(items: impl IntoIterator<Item = T>, n: usize) -> Vec<(usize, T)> {
+    //Min-heap of the best n so far, a later index counts as smaller on ties
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    for (index, item) in items.into_iter().enumerate() {
+        heap.push(Reverse((item, Reverse(index))));
+        if heap.len() > n {
+            heap.pop();
+        }
+    }
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((item, Reverse(index)))| (index, item))
+        .collect()
+}
+
+fn top_groups<R: BufRead>(reader: R, n: usize) -> Result<Vec<(usize, u64)>, ReadError> {
+    //Groups stops after its first error, so only the totals before it are ranked
+    let mut error = None;
+    let totals = Groups::new(reader).map_while(|total| total.map_err(|e| error = Some(e)).ok());
+    let top = top_n(totals, n);
+    error.map_or(Ok(top), Err)
+}
+
+fn top_calories(input: &str, n: usize) -> Result<u64, ReadError> {
+    let top = top_groups(input.as_bytes(), n)?;
+    Ok(top.iter().map(|&(_, total)| total).sum())
+}
+
+pub fn part1(input: &str) -> Answer {
+    top_calories(input, 1).into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    top_calories(input, 3).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n";
+
+    fn groups(input: &str) -> Vec<u64> {
+        Groups::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parts() {
+        assert_eq!(part1(EXAMPLE), Answer::from(24000));
+        assert_eq!(part2(EXAMPLE), Answer::from(45000));
+    }
+
+    #[test]
+    fn test_groups() {
+        assert_eq!(groups(EXAMPLE), vec![6000, 4000, 11000, 24000, 10000]);
+        assert_eq!(groups(""), Vec::<u64>::new());
+        assert_eq!(groups("\n\n1\n\n\n\n2\n\n"), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_missing_trailing_newline() {
+        assert_eq!(groups("1\n2\n\n3"), vec![3, 3]);
+        assert_eq!(groups("1\n2\n\n3\n"), vec![3, 3]);
+        assert_eq!(part1(EXAMPLE.trim_end()), Answer::from(24000));
+    }
+
+    #[test]
+    fn test_crlf() {
+        assert_eq!(groups("1\r\n2\r\n\r\n3\r\n"), vec![3, 3]);
+        assert_eq!(part2(&EXAMPLE.replace('\n', "\r\n")), Answer::from(45000));
+    }
+
+    #[test]
+    fn test_invalid_calories() {
+        let result = Groups::new("1\n\n2x\n".as_bytes()).collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid calories on line 3: \"2x\""
+        );
+        assert_eq!(
+            part1("1\nabc"),
+            Answer::Unsolved("invalid calories on line 2: \"abc\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_top_n() {
+        assert_eq!(
+            top_groups(EXAMPLE.as_bytes(), 3).unwrap(),
+            vec![(3, 24000), (2, 11000), (4, 10000)]
+        );
+        assert_eq!(top_n([5, 1, 5, 3], 2), vec![(0, 5), (2, 5)]);
+        assert_eq!(top_n([2, 1], 5), vec![(0, 2), (1, 1)]);
+        assert_eq!(top_n(["b", "a", "c"], 1), vec![(2, "c")]);
+        assert_eq!(top_n([1, 2, 3], 0), vec![]);
     }
-    println!("Total calories: {}", total_calories);
 }
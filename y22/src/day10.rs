@@ -1,3 +1,4 @@
+use crate::answer::Answer;
 fn signal_strenght(input: &str) -> i32 {
     let mut signal = 0;
     let mut cycle = 1;
@@ -28,7 +29,8 @@ fn signal_strenght(input: &str) -> i32 {
     signal
 }
 
-fn draw_screen(input: &str) {
+fn draw_screen(input: &str) -> String {
+    let mut screen = String::new();
     let mut cycle = 1;
     let mut register = 1;
     for line in input.lines() {
@@ -37,12 +39,12 @@ fn draw_screen(input: &str) {
         match first {
             "noop" => {
                 if register == cycle - 1 || register - 1 == cycle - 1 || register + 1 == cycle - 1 {
-                    print!("#");
+                    screen.push('#');
                 } else {
-                    print!(".");
+                    screen.push('.');
                 }
                 if cycle % 40 == 0 {
-                    println!("");
+                    screen.push('\n');
                     cycle -= 40;
                 }
                 cycle += 1;
@@ -54,12 +56,12 @@ fn draw_screen(input: &str) {
                         || register - 1 == cycle - 1
                         || register + 1 == cycle - 1
                     {
-                        print!("#");
+                        screen.push('#');
                     } else {
-                        print!(".");
+                        screen.push('.');
                     }
                     if cycle % 40 == 0 {
-                        println!("");
+                        screen.push('\n');
                         cycle -= 40;
                     }
                     cycle += 1;
@@ -69,25 +71,15 @@ fn draw_screen(input: &str) {
             _ => panic!("Unknown instruction"),
         }
     }
-    println!("");
+    screen
 }
 
-fn part1() {
-    let input = include_str!("../input/10");
-    let signal = signal_strenght(input);
-    println!("Part 1: {}", signal);
+pub fn part1(input: &str) -> Answer {
+    signal_strenght(input).into()
 }
 
-fn part2() {
-    let input = include_str!("../input/10");
-    draw_screen(input);
-}
-
-pub fn run() {
-    println!("Day 10");
-
-    part1();
-    part2();
+pub fn part2(input: &str) -> Answer {
+    draw_screen(input).into()
 }
 
 #[cfg(test)]
@@ -104,7 +96,16 @@ mod tests {
     #[test]
     fn test_part2() {
         let input = include_str!("../input/test10");
-        draw_screen(input);
-        // assert!(false);
+        let screen = draw_screen(input);
+        assert_eq!(
+            screen,
+            "##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....
+"
+        );
     }
 }
@@ -1,230 +1,420 @@
-#[derive(Debug)]
-struct Monkey {
-    id: i64,
-    items: Vec<i64>,
-    operation: Operation,
-    divisor: i64,
-    if_true: Option<i64>,
-    if_false: Option<i64>,
-    num_inspected_items: i64,
+use crate::answer::Answer;
+use std::{fmt::Display, str::FromStr};
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    InvalidExpression(String),
+    InvalidLine(String),
+    MissingField { monkey: usize, field: &'static str },
+    MissingId,
+    //Monkeys have to be listed in order, starting at 0
+    IdMismatch { expected: usize, found: usize },
+    UnknownTarget { monkey: usize, target: usize },
 }
-impl Monkey {
-    fn new(id: i64) -> Monkey {
-        Monkey {
-            id,
-            items: Vec::new(),
-            operation: Operation::Empty,
-            divisor: 0,
-            if_true: None,
-            if_false: None,
-            num_inspected_items: 0,
-        }
-    }
-    fn process_items(&mut self, part1: bool, modulus: i64) -> Vec<(i64, i64)> {
-        let mut result: Vec<(i64, i64)> = Vec::new();
-
-        //Inspect items
-        for item in self.items.iter_mut() {
-            match self.operation {
-                Operation::Add(rhs) => {
-                    *item += rhs;
-                }
-                Operation::MultiplyBy(rhs) => {
-                    *item *= rhs;
-                }
-                Operation::MultiplyBySelf => {
-                    *item *= *item;
-                }
-                _ => {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidExpression(s) => write!(f, "invalid operation: {}", s),
+            ParseError::InvalidLine(line) => write!(f, "unexpected line: {}", line),
+            ParseError::MissingField { monkey, field } => {
+                write!(f, "monkey {} has no {}", monkey, field)
             }
-            self.num_inspected_items += 1;
-            //Decrease items worry after inspection
-            if part1 {
-                *item /= 3;
-            } else {
-                *item %= modulus;
+            ParseError::MissingId => write!(f, "monkey without an id"),
+            ParseError::IdMismatch { expected, found } => {
+                write!(f, "expected monkey {}, found monkey {}", expected, found)
+            }
+            ParseError::UnknownTarget { monkey, target } => {
+                write!(f, "monkey {} throws to unknown monkey {}", monkey, target)
             }
+        }
+    }
+}
 
-            if *item % self.divisor == 0 {
-                result.push((*item, self.if_true.unwrap()));
-            } else {
-                result.push((*item, self.if_false.unwrap()));
+#[derive(Debug, PartialEq, Eq)]
+enum SimulationError {
+    //The operation overflowed or divided by zero
+    Arithmetic { monkey: usize, worry: i64 },
+    //Dividing by or taking the remainder of a number below 1
+    InvalidRelief(i64),
+    //Relief::Modulo changes the result of a division
+    DividingOperation { monkey: usize },
+    //The divisors have no common multiple that fits in an i64
+    ModulusOverflow,
+}
+
+impl Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::Arithmetic { monkey, worry } => write!(
+                f,
+                "monkey {} cannot apply its operation to worry level {}",
+                monkey, worry
+            ),
+            SimulationError::InvalidRelief(n) => {
+                write!(f, "worry levels can't be relieved by {}", n)
+            }
+            SimulationError::DividingOperation { monkey } => write!(
+                f,
+                "monkey {} divides, worry levels can't be kept modulo a number",
+                monkey
+            ),
+            SimulationError::ModulusOverflow => {
+                write!(
+                    f,
+                    "the divisors have no common multiple that fits in an i64"
+                )
             }
         }
+    }
+}
 
-        //Clear items
-        self.items.clear();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Old,
+    Constant(i64),
+}
 
-        result
+impl Operand {
+    fn value(self, old: i64) -> i64 {
+        match self {
+            Operand::Old => old,
+            Operand::Constant(c) => c,
+        }
     }
 }
 
-#[derive(Debug)]
-enum Operation {
-    Add(i64),
-    MultiplyBy(i64),
-    MultiplyBySelf,
-    Empty,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
 }
 
-impl Operation {
-    fn new(operation: &str, rhs: &str) -> Operation {
-        match (operation, rhs) {
-            ("+", rhs) => Operation::Add(rhs.parse::<i64>().unwrap()),
-            ("*", "old") => Operation::MultiplyBySelf,
+//The right hand side of "new = <lhs> <op> <rhs>"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Expression {
+    lhs: Operand,
+    op: Operator,
+    rhs: Operand,
+}
 
-            ("*", rhs) => Operation::MultiplyBy(rhs.parse::<i64>().unwrap()),
-            _ => Operation::Empty,
+impl Expression {
+    fn eval(&self, old: i64) -> Option<i64> {
+        let (a, b) = (self.lhs.value(old), self.rhs.value(old));
+        match self.op {
+            Operator::Add => a.checked_add(b),
+            Operator::Sub => a.checked_sub(b),
+            Operator::Mul => a.checked_mul(b),
+            Operator::Div => a.checked_div(b),
         }
     }
 }
 
-fn parse_input(input: &str) -> Vec<Monkey> {
-    let mut monkeys: Vec<Monkey> = Vec::new();
-
-    input.lines().for_each(|line| {
-        let mut words = line.split_whitespace();
-        match words.next() {
-            Some("Monkey") => {
-                let id = words
-                    .next()
-                    .unwrap()
-                    .split(':')
-                    .nth(0)
-                    .unwrap()
-                    .parse::<i64>()
-                    .unwrap();
-                monkeys.push(Monkey::new(id));
-            }
-            Some("Starting") => {
-                let _ = words.next().unwrap();
-                while let Some(item) = words.next() {
-                    let item = item.split(',').nth(0).unwrap().parse::<i64>().unwrap();
-                    monkeys.last_mut().unwrap().items.push(item);
-                }
-            }
-            Some("Operation:") => {
-                let operation = words.clone().skip(3).next().unwrap();
-                let rhs = words.skip(4).next().unwrap();
-                let operation = Operation::new(operation, rhs);
-                monkeys.last_mut().unwrap().operation = operation;
-            }
-            Some("Test:") => {
-                monkeys.last_mut().unwrap().divisor =
-                    words.skip(2).next().unwrap().parse::<i64>().unwrap();
-            }
-            Some("If") => match words.next() {
-                Some("true:") => {
-                    let if_true_id = words.last().unwrap().parse::<i64>().unwrap();
-                    monkeys.last_mut().unwrap().if_true = Some(if_true_id);
-                }
-                Some("false:") => {
-                    let if_false_id = words.last().unwrap().parse::<i64>().unwrap();
-                    monkeys.last_mut().unwrap().if_false = Some(if_false_id);
-                }
-                _ => {}
-            },
-            _ => {}
-        }
-    });
-    monkeys
+impl FromStr for Expression {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::InvalidExpression(s.to_string());
+        let operand = |word: &str| match word {
+            "old" => Ok(Operand::Old),
+            c => c.parse().map(Operand::Constant).map_err(|_| invalid()),
+        };
+        let expression = s.trim().strip_prefix("new =").unwrap_or(s);
+        let words = expression.split_whitespace().collect::<Vec<_>>();
+        let [lhs, op, rhs] = words[..] else {
+            return Err(invalid());
+        };
+        let op = match op {
+            "+" => Operator::Add,
+            "-" => Operator::Sub,
+            "*" => Operator::Mul,
+            "/" => Operator::Div,
+            _ => return Err(invalid()),
+        };
+        Ok(Expression {
+            lhs: operand(lhs)?,
+            op,
+            rhs: operand(rhs)?,
+        })
+    }
 }
-fn part() {
-    println!("Part 1");
-    let input = include_str!("../input/11");
-    let mut monkeys = parse_input(input);
-
-    for _ in 0..20 {
-        let mut throws: Vec<(i64, i64)> = Vec::new();
-        for monkey in monkeys.iter_mut() {
-            //Check if there are any throws to this monkey in this round
-            for throw in throws.iter() {
-                if throw.1 == monkey.id {
-                    monkey.items.push(throw.0);
-                }
-            }
-            throws.retain(|t| t.1 != monkey.id);
 
-            let result = monkey.process_items(true, 0);
-            for throw in result {
-                throws.push(throw);
-            }
+//How worry levels drop after a monkey inspected an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Relief {
+    None,
+    DivideBy(i64),
+    //Keeps the numbers small without changing any divisibility test, as long as
+    //the modulus is a multiple of every divisor and no operation divides
+    Modulo(i64),
+}
+
+impl Relief {
+    fn apply(self, worry: i64) -> i64 {
+        match self {
+            Relief::None => worry,
+            Relief::DivideBy(d) => worry / d,
+            Relief::Modulo(m) => worry % m,
         }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Monkey {
+    id: usize,
+    items: Vec<i64>,
+    operation: Expression,
+    divisor: i64,
+    if_true: usize,
+    if_false: usize,
+    inspected: u64,
+}
 
-        //Add throws to monkeys
-        for throw in throws {
-            let monkey = monkeys.iter_mut().find(|m| m.id == throw.1).unwrap();
-            monkey.items.push(throw.0);
+impl Monkey {
+    //Inspect all items and return where each of them is thrown
+    fn process_items(&mut self, relief: Relief) -> Result<Vec<(i64, usize)>, SimulationError> {
+        let mut result = Vec::new();
+        for worry in self.items.drain(..) {
+            let worry = self
+                .operation
+                .eval(worry)
+                .ok_or(SimulationError::Arithmetic {
+                    monkey: self.id,
+                    worry,
+                })?;
+            let worry = relief.apply(worry);
+            self.inspected += 1;
+            let target = if worry % self.divisor == 0 {
+                self.if_true
+            } else {
+                self.if_false
+            };
+            result.push((worry, target));
         }
+        Ok(result)
     }
+}
 
-    let mut num_inspected_items = monkeys
-        .iter()
-        .map(|m| m.num_inspected_items)
-        .collect::<Vec<i64>>();
+impl FromStr for Monkey {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut id = None;
+        let mut items = None;
+        let mut operation = None;
+        let mut divisor = None;
+        let mut if_true = None;
+        let mut if_false = None;
+
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let invalid = || ParseError::InvalidLine(line.to_string());
+            let last_number = || {
+                line.split_whitespace()
+                    .last()
+                    .and_then(|word| word.parse::<usize>().ok())
+                    .ok_or_else(invalid)
+            };
+            if let Some(rest) = line.strip_prefix("Monkey ") {
+                id = Some(rest.trim_end_matches(':').parse().map_err(|_| invalid())?);
+            } else if let Some(rest) = line.strip_prefix("Starting items:") {
+                items = Some(
+                    rest.split(',')
+                        .map(|item| item.trim())
+                        .filter(|item| !item.is_empty())
+                        .map(|item| item.parse().map_err(|_| invalid()))
+                        .collect::<Result<Vec<i64>, _>>()?,
+                );
+            } else if let Some(rest) = line.strip_prefix("Operation:") {
+                operation = Some(rest.parse()?);
+            } else if line.starts_with("Test: divisible by") {
+                divisor = Some(last_number()? as i64);
+            } else if line.starts_with("If true:") {
+                if_true = Some(last_number()?);
+            } else if line.starts_with("If false:") {
+                if_false = Some(last_number()?);
+            } else {
+                return Err(invalid());
+            }
+        }
 
-    num_inspected_items.sort();
+        let id = id.ok_or(ParseError::MissingId)?;
+        let missing = |field| ParseError::MissingField { monkey: id, field };
+        Ok(Monkey {
+            id,
+            items: items.ok_or(missing("starting items"))?,
+            operation: operation.ok_or(missing("operation"))?,
+            divisor: divisor.filter(|&d| d != 0).ok_or(missing("test"))?,
+            if_true: if_true.ok_or(missing("true target"))?,
+            if_false: if_false.ok_or(missing("false target"))?,
+            inspected: 0,
+        })
+    }
+}
 
-    let first = num_inspected_items.pop().unwrap();
-    let second = num_inspected_items.pop().unwrap();
-    println!("Max num inspected items: {}", first);
-    println!("2nd Max num inspected items: {}", second);
-    println!("Monkey business: {}", first * second);
+//State of all monkeys at the end of a round
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    round: usize,
+    items: Vec<Vec<i64>>,
+    inspected: Vec<u64>,
 }
 
-fn part2() {
-    println!("Part 2");
-    let input = include_str!("../input/11");
-    let mut monkeys = parse_input(input);
+struct Troop {
+    monkeys: Vec<Monkey>,
+    round: usize,
+}
 
-    let mut modulo = 1;
-    for monkey in &monkeys {
-        modulo *= monkey.divisor;
+impl Troop {
+    //Least common multiple of all divisors, the smallest valid modulus for Relief::Modulo
+    fn common_modulus(&self) -> Result<i64, SimulationError> {
+        fn gcd(a: i64, b: i64) -> i64 {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+        self.monkeys.iter().try_fold(1, |lcm, m| {
+            (lcm / gcd(lcm, m.divisor))
+                .checked_mul(m.divisor)
+                .ok_or(SimulationError::ModulusOverflow)
+        })
     }
-    println!("Modulo: {}", modulo);
-
-    for _ in 0..10000 {
-        let mut throws: Vec<(i64, i64)> = Vec::new();
-        for monkey in monkeys.iter_mut() {
-            //Check if there are any throws to this monkey in this round
-            for throw in throws.iter() {
-                if throw.1 == monkey.id {
-                    monkey.items.push(throw.0);
-                }
+
+    fn check_relief(&self, relief: Relief) -> Result<(), SimulationError> {
+        match relief {
+            Relief::None => Ok(()),
+            Relief::DivideBy(n) | Relief::Modulo(n) if n < 1 => {
+                Err(SimulationError::InvalidRelief(n))
             }
-            throws.retain(|t| t.1 != monkey.id);
+            Relief::DivideBy(_) => Ok(()),
+            Relief::Modulo(_) => match self
+                .monkeys
+                .iter()
+                .find(|m| m.operation.op == Operator::Div)
+            {
+                Some(monkey) => Err(SimulationError::DividingOperation { monkey: monkey.id }),
+                None => Ok(()),
+            },
+        }
+    }
 
-            let result = monkey.process_items(false, modulo);
-            for throw in result {
-                throws.push(throw);
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            round: self.round,
+            items: self.monkeys.iter().map(|m| m.items.clone()).collect(),
+            inspected: self.monkeys.iter().map(|m| m.inspected).collect(),
+        }
+    }
+
+    //Items thrown to a monkey later in the turn order are handled in the same round
+    fn round(&mut self, relief: Relief) -> Result<(), SimulationError> {
+        for i in 0..self.monkeys.len() {
+            for (worry, target) in self.monkeys[i].process_items(relief)? {
+                self.monkeys[target].items.push(worry);
             }
         }
+        self.round += 1;
+        Ok(())
+    }
+
+    fn simulate<F>(
+        &mut self,
+        rounds: usize,
+        relief: Relief,
+        mut on_round: F,
+    ) -> Result<(), SimulationError>
+    where
+        F: FnMut(Snapshot),
+    {
+        self.check_relief(relief)?;
+        for _ in 0..rounds {
+            self.round(relief)?;
+            on_round(self.snapshot());
+        }
+        Ok(())
+    }
+
+    fn snapshots(
+        &mut self,
+        rounds: usize,
+        relief: Relief,
+    ) -> Result<Vec<Snapshot>, SimulationError> {
+        let mut snapshots = Vec::new();
+        self.simulate(rounds, relief, |snapshot| snapshots.push(snapshot))?;
+        Ok(snapshots)
+    }
+
+    fn monkey_business(&self) -> u64 {
+        let mut inspected = self.monkeys.iter().map(|m| m.inspected).collect::<Vec<_>>();
+        inspected.sort_unstable_by(|a, b| b.cmp(a));
+        inspected.iter().take(2).product()
+    }
+}
 
-        //Add throws to monkeys
-        for throw in throws {
-            let monkey = monkeys.iter_mut().find(|m| m.id == throw.1).unwrap();
-            monkey.items.push(throw.0);
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "After round {}:", self.round)?;
+        for (id, (items, inspected)) in self.items.iter().zip(&self.inspected).enumerate() {
+            let items = items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "Monkey {} ({} inspected): {}", id, inspected, items)?;
         }
+        Ok(())
     }
+}
 
-    let mut num_inspected_items = monkeys
-        .iter()
-        .map(|m| m.num_inspected_items)
-        .collect::<Vec<i64>>();
+fn parse_input(input: &str) -> Result<Troop, ParseError> {
+    let input = input.replace("\r\n", "\n");
+    let monkeys = input
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| block.parse())
+        .collect::<Result<Vec<Monkey>, _>>()?;
+
+    for (i, monkey) in monkeys.iter().enumerate() {
+        if monkey.id != i {
+            return Err(ParseError::IdMismatch {
+                expected: i,
+                found: monkey.id,
+            });
+        }
+        for target in [monkey.if_true, monkey.if_false] {
+            if target >= monkeys.len() {
+                return Err(ParseError::UnknownTarget { monkey: i, target });
+            }
+        }
+    }
+    Ok(Troop { monkeys, round: 0 })
+}
 
-    num_inspected_items.sort();
+fn monkey_business(mut troop: Troop, rounds: usize, relief: Relief) -> Result<u64, String> {
+    troop
+        .simulate(rounds, relief, |_| {})
+        .map_err(|e| e.to_string())?;
+    Ok(troop.monkey_business())
+}
 
-    let first = num_inspected_items.pop().unwrap();
-    let second = num_inspected_items.pop().unwrap();
-    println!("Max num inspected items: {}", first);
-    println!("2nd Max num inspected items: {}", second);
-    println!("Monkey business: {}", first * second);
+pub fn part1(input: &str) -> Answer {
+    parse_input(input)
+        .map_err(|e| e.to_string())
+        .and_then(|troop| monkey_business(troop, 20, Relief::DivideBy(3)))
+        .into()
 }
 
-pub fn run() {
-    println!("Running day11");
-    part();
-    part2();
+pub fn part2(input: &str) -> Answer {
+    parse_input(input)
+        .map_err(|e| e.to_string())
+        .and_then(|troop| {
+            let modulus = troop.common_modulus().map_err(|e| e.to_string())?;
+            monkey_business(troop, 10000, Relief::Modulo(modulus))
+        })
+        .into()
 }
 
 #[cfg(test)]
@@ -234,44 +424,183 @@ mod tests {
     #[test]
     fn test_parse_input() {
         let input = include_str!("../input/test11");
-        let mut monkeys = parse_input(input);
-
-        for _ in 0..20 {
-            let mut throws: Vec<(i64, i64)> = Vec::new();
-            for monkey in monkeys.iter_mut() {
-                //Check if there are any throws to this monkey in this round
-                for throw in throws.iter() {
-                    if throw.1 == monkey.id {
-                        monkey.items.push(throw.0);
-                    }
-                }
-                throws.retain(|t| t.1 != monkey.id);
-
-                let result = monkey.process_items(true, 0);
-                for throw in result {
-                    throws.push(throw);
-                }
+        let mut troop = parse_input(input).unwrap();
+        assert_eq!(troop.monkeys.len(), 4);
+        assert_eq!(
+            troop.monkeys[2].operation,
+            Expression {
+                lhs: Operand::Old,
+                op: Operator::Mul,
+                rhs: Operand::Old
             }
+        );
+
+        troop.simulate(20, Relief::DivideBy(3), |_| {}).unwrap();
+        let inspected = troop
+            .monkeys
+            .iter()
+            .map(|m| m.inspected)
+            .collect::<Vec<_>>();
+        assert_eq!(inspected, vec![101, 95, 7, 105]);
+        assert_eq!(troop.monkey_business(), 10605);
+    }
+
+    #[test]
+    fn test_part2() {
+        let input = include_str!("../input/test11");
+        assert_eq!(part2(input), Answer::from(2713310158u64));
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let input = include_str!("../input/test11");
+        let mut troop = parse_input(input).unwrap();
+        let snapshots = troop.snapshots(2, Relief::DivideBy(3)).unwrap();
+
+        assert_eq!(snapshots[0].round, 1);
+        assert_eq!(
+            snapshots[0].items,
+            vec![
+                vec![20, 23, 27, 26],
+                vec![2080, 25, 167, 207, 401, 1046],
+                vec![],
+                vec![]
+            ]
+        );
+        assert_eq!(snapshots[0].inspected, vec![2, 4, 3, 5]);
+        assert_eq!(
+            snapshots[1].to_string(),
+            "After round 2:
+Monkey 0 (6 inspected): 695, 10, 71, 135, 350
+Monkey 1 (10 inspected): 43, 49, 58, 55, 362
+Monkey 2 (4 inspected): 
+Monkey 3 (10 inspected): 
+"
+        );
+
+        let mut troop = parse_input(input).unwrap();
+        let modulus = troop.common_modulus().unwrap();
+        assert_eq!(modulus, 23 * 19 * 13 * 17);
+        let snapshots = troop.snapshots(20, Relief::Modulo(modulus)).unwrap();
+        assert_eq!(snapshots[0].inspected, vec![2, 4, 3, 6]);
+        assert_eq!(snapshots[19].inspected, vec![99, 97, 8, 103]);
+    }
+
+    #[test]
+    fn test_expression() {
+        let e: Expression = "new = 7 - old".parse().unwrap();
+        assert_eq!(e.eval(3), Some(4));
+        let e: Expression = "old / 2".parse().unwrap();
+        assert_eq!(e.eval(9), Some(4));
+        let e: Expression = "new = old / old".parse().unwrap();
+        assert_eq!(e.eval(0), None);
+        let e: Expression = "new = old * old".parse().unwrap();
+        assert_eq!(e.eval(i64::MAX), None);
+        assert_eq!(
+            "new = old ^ 2".parse::<Expression>(),
+            Err(ParseError::InvalidExpression("new = old ^ 2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_overflow_without_relief() {
+        let input = include_str!("../input/test11");
+        let mut troop = parse_input(input).unwrap();
+        let result = troop.simulate(1000, Relief::None, |_| {});
+        assert!(matches!(result, Err(SimulationError::Arithmetic { .. })));
+    }
 
-            //Add throws to monkeys
-            for throw in throws {
-                let monkey = monkeys.iter_mut().find(|m| m.id == throw.1).unwrap();
-                monkey.items.push(throw.0);
+    #[test]
+    fn test_parse_errors() {
+        let monkey = "Monkey 0:\n  Starting items: 1\n  Operation: new = old + 1";
+        assert_eq!(
+            monkey.parse::<Monkey>().unwrap_err(),
+            ParseError::MissingField {
+                monkey: 0,
+                field: "test"
             }
-        }
+        );
+        let input = "Monkey 0:
+  Starting items: 1
+  Operation: new = old + 1
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 3";
+        assert_eq!(
+            parse_input(input).err(),
+            Some(ParseError::UnknownTarget {
+                monkey: 0,
+                target: 3
+            })
+        );
+    }
 
-        let mut num_inspected_items = monkeys
-            .iter()
-            .map(|m| m.num_inspected_items)
-            .collect::<Vec<i64>>();
+    #[test]
+    fn test_ids() {
+        let monkey = "Starting items: 1\n  Operation: new = old + 1";
+        assert_eq!(monkey.parse::<Monkey>().err(), Some(ParseError::MissingId));
+        let input = "Monkey 1:
+  Starting items: 1
+  Operation: new = old + 1
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0";
+        assert_eq!(
+            parse_input(input).err(),
+            Some(ParseError::IdMismatch {
+                expected: 0,
+                found: 1
+            })
+        );
+    }
 
-        num_inspected_items.sort();
+    #[test]
+    fn test_invalid_relief() {
+        let input = include_str!("../input/test11");
+        let troop = parse_input(input).unwrap();
+        assert_eq!(
+            monkey_business(troop, 20, Relief::DivideBy(0)),
+            Err(SimulationError::InvalidRelief(0).to_string())
+        );
+
+        let input = input.replace("new = old + 3", "new = old / 3");
+        let mut troop = parse_input(&input).unwrap();
+        assert_eq!(
+            troop.simulate(20, Relief::Modulo(96577), |_| {}),
+            Err(SimulationError::DividingOperation { monkey: 3 })
+        );
+        assert_eq!(troop.round, 0);
+        assert!(troop.simulate(20, Relief::DivideBy(3), |_| {}).is_ok());
+    }
 
-        let first = num_inspected_items.pop().unwrap();
-        let second = num_inspected_items.pop().unwrap();
-        println!("Max num inspected items: {}", first);
-        println!("2nd Max num inspected items: {}", second);
-        assert_eq!(first, 105);
-        assert_eq!(second, 101);
+    #[test]
+    fn test_common_modulus() {
+        let monkey = |id: usize, divisor: i64| {
+            format!(
+                "Monkey {}:\n  Starting items: 1\n  Operation: new = old + 1\n  \
+                 Test: divisible by {}\n    If true: throw to monkey 0\n    \
+                 If false: throw to monkey 0\n",
+                id, divisor
+            )
+        };
+        let input = [4, 6, 10].iter().enumerate().map(|(i, &d)| monkey(i, d));
+        let troop = parse_input(&input.collect::<Vec<_>>().join("\n")).unwrap();
+        assert_eq!(troop.common_modulus(), Ok(60));
+
+        //Twenty primes above a thousand multiply to far more than an i64 holds
+        let primes = (1000i64..)
+            .filter(|n| (2..*n).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+            .take(20);
+        let input = primes.enumerate().map(|(i, d)| monkey(i, d));
+        let input = input.collect::<Vec<_>>().join("\n");
+        let troop = parse_input(&input).unwrap();
+        assert_eq!(
+            troop.common_modulus(),
+            Err(SimulationError::ModulusOverflow)
+        );
+        assert_eq!(
+            part2(&input).to_string(),
+            "unsolved (the divisors have no common multiple that fits in an i64)"
+        );
     }
 }
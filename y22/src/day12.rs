@@ -1,104 +1,247 @@
-use std::collections::{HashSet, VecDeque};
-#[derive(PartialEq, PartialOrd, Debug)]
-struct Elevation(char);
+use crate::answer::Answer;
+use std::{collections::VecDeque, fmt::Display, str::FromStr};
 
-impl Elevation {
-    fn increment(&self) -> Elevation {
-        Elevation((self.0 as u8 + 1).into())
+type Pos = (usize, usize);
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    NotRectangular,
+    InvalidTile(char),
+    Missing(char),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::NotRectangular => write!(f, "heightmap is not rectangular"),
+            ParseError::InvalidTile(c) => write!(f, "invalid elevation: {}", c),
+            ParseError::Missing(c) => write!(f, "heightmap has no {}", c),
+        }
     }
 }
 
-fn parse_input(input: &str) -> Vec<Vec<Elevation>> {
-    let mut map = Vec::new();
+//How far a single step may go up or down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ClimbingRule {
+    max_up: u8,
+    max_down: u8,
+}
 
-    for line in input.lines() {
-        let mut row = Vec::new();
-        for c in line.chars() {
-            row.push(Elevation(c));
+impl ClimbingRule {
+    fn allows(&self, from: u8, to: u8) -> bool {
+        if to >= from {
+            to - from <= self.max_up
+        } else {
+            from - to <= self.max_down
+        }
+    }
+
+    //The same rule for walking a route backwards
+    fn reversed(&self) -> Self {
+        ClimbingRule {
+            max_up: self.max_down,
+            max_down: self.max_up,
+        }
+    }
+}
+
+impl Default for ClimbingRule {
+    fn default() -> Self {
+        ClimbingRule {
+            max_up: 1,
+            max_down: u8::MAX,
         }
-        map.push(row);
     }
-    map
 }
 
-fn valid_move(from: &Elevation, to: &Elevation) -> bool {
-    if to.0 == 'E' {
-        return valid_move(from, &Elevation('z'));
+//Every square visited in order, from a source to a target
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Path(Vec<Pos>);
+
+impl Path {
+    fn steps(&self) -> usize {
+        self.0.len() - 1
     }
-    if from.0 == 'S' {
-        return true;
+
+    fn start(&self) -> Pos {
+        self.0[0]
     }
-    if to <= from || from == to || &from.increment() == to {
-        return true;
+
+    fn end(&self) -> Pos {
+        *self.0.last().unwrap()
     }
-    false
 }
 
-fn bfs(map: &Vec<Vec<Elevation>>, start: Elevation, goal: Elevation, is_reverse: bool) -> i32 {
-    // Find S in the map
-    let mut start_x = 0;
-    let mut start_y = 0;
-    for (y, row) in map.iter().enumerate() {
-        for (x, elevation) in row.iter().enumerate() {
-            if elevation == &start {
-                start_x = x;
-                start_y = y;
-            }
-        }
+struct HeightMap {
+    width: usize,
+    height: usize,
+    //Elevation 0 for 'a' up to 25 for 'z'
+    elevations: Vec<u8>,
+    start: Pos,
+    end: Pos,
+}
+
+impl HeightMap {
+    fn elevation(&self, (x, y): Pos) -> u8 {
+        self.elevations[y * self.width + x]
     }
 
-    let mut queue = VecDeque::new();
-    let mut visited = HashSet::new();
-    queue.push_back((start_x, start_y, 0));
-    while let Some((x, y, steps)) = queue.pop_front() {
-        if visited.contains(&(x, y)) {
-            continue;
+    fn positions_at(&self, elevation: u8) -> Vec<Pos> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&pos| self.elevation(pos) == elevation)
+            .collect()
+    }
+
+    fn neighbours(&self, (x, y): Pos) -> impl Iterator<Item = Pos> + '_ {
+        [
+            x.checked_sub(1).map(|x| (x, y)),
+            (x + 1 < self.width).then_some((x + 1, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+            (y + 1 < self.height).then_some((x, y + 1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    //Breadth first search from all sources at once, stopping at the first
+    //target reached
+    fn shortest_path(&self, sources: &[Pos], targets: &[Pos], rule: ClimbingRule) -> Option<Path> {
+        let index = |(x, y): Pos| y * self.width + x;
+        let mut is_target = vec![false; self.elevations.len()];
+        for &target in targets {
+            is_target[index(target)] = true;
         }
-        let elevation = &map[y][x];
-        if elevation == &goal {
-            return steps;
+
+        let mut parent: Vec<Option<Pos>> = vec![None; self.elevations.len()];
+        let mut seen = vec![false; self.elevations.len()];
+        let mut queue = VecDeque::new();
+        for &source in sources {
+            if !seen[index(source)] {
+                seen[index(source)] = true;
+                queue.push_back(source);
+            }
         }
-        for (dx, dy) in [(0, 1), (0, -1), (1, 0), (-1, 0)].iter() {
-            let new_x = (x as i32 + dx) as usize;
-            let new_y = (y as i32 + dy) as usize;
-
-            if new_x < map[0].len() && new_y < map.len() {
-                let new_elevation = &map[new_y][new_x];
-                if is_reverse && valid_move(new_elevation, elevation) {
-                    queue.push_back((new_x, new_y, steps + 1));
-                } else if !is_reverse && valid_move(elevation, new_elevation) {
-                    queue.push_back((new_x, new_y, steps + 1));
+
+        while let Some(pos) = queue.pop_front() {
+            if is_target[index(pos)] {
+                let mut path = vec![pos];
+                while let Some(prev) = parent[index(*path.last().unwrap())] {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(Path(path));
+            }
+            for next in self.neighbours(pos) {
+                if !seen[index(next)] && rule.allows(self.elevation(pos), self.elevation(next)) {
+                    seen[index(next)] = true;
+                    parent[index(next)] = Some(pos);
+                    queue.push_back(next);
                 }
             }
         }
-        visited.insert((x, y));
+        None
+    }
+
+    //The route drawn with arrows pointing to the next square and E at its end,
+    //other squares show their elevation or a dot
+    fn render(&self, path: &Path, show_heights: bool) -> String {
+        let mut grid = (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        if show_heights {
+                            (b'a' + self.elevation((x, y))) as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        for pair in path.0.windows(2) {
+            let ((x, y), (nx, ny)) = (pair[0], pair[1]);
+            grid[y][x] = match (nx as i64 - x as i64, ny as i64 - y as i64) {
+                (1, 0) => '>',
+                (-1, 0) => '<',
+                (0, 1) => 'v',
+                _ => '^',
+            };
+        }
+        let (x, y) = path.end();
+        grid[y][x] = 'E';
+
+        grid.into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect()
     }
-    return 0;
 }
 
-fn part1() {
-    println!("Part 1");
+impl FromStr for HeightMap {
+    type Err = ParseError;
 
-    let input = include_str!("../input/12");
-    let map = parse_input(input);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().map(str::trim_end).collect::<Vec<_>>();
+        let width = lines.first().map_or(0, |line| line.len());
+        if lines.iter().any(|line| line.len() != width) {
+            return Err(ParseError::NotRectangular);
+        }
 
-    let steps = bfs(&map, Elevation('S'), Elevation('E'), false);
-    println!("Steps: {}", steps);
+        let mut start = None;
+        let mut end = None;
+        let mut elevations = Vec::with_capacity(width * lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let c = match c {
+                    'S' => {
+                        start = Some((x, y));
+                        'a'
+                    }
+                    'E' => {
+                        end = Some((x, y));
+                        'z'
+                    }
+                    'a'..='z' => c,
+                    c => return Err(ParseError::InvalidTile(c)),
+                };
+                elevations.push(c as u8 - b'a');
+            }
+        }
+        Ok(HeightMap {
+            width,
+            height: lines.len(),
+            elevations,
+            start: start.ok_or(ParseError::Missing('S'))?,
+            end: end.ok_or(ParseError::Missing('E'))?,
+        })
+    }
 }
 
-fn part2() {
-    println!("Part 2");
+fn climb(map: &HeightMap) -> Option<Path> {
+    map.shortest_path(&[map.start], &[map.end], ClimbingRule::default())
+}
 
-    let input = include_str!("../input/12");
-    let map = parse_input(input);
+//Searching from the end finds the closest of all the lowest squares in one go
+fn hike(map: &HeightMap) -> Option<Path> {
+    let rule = ClimbingRule::default().reversed();
+    let mut path = map.shortest_path(&[map.end], &map.positions_at(0), rule)?;
+    path.0.reverse();
+    Some(path)
+}
 
-    let steps = bfs(&map, Elevation('E'), Elevation('a'), true);
-    println!("Steps: {}", steps);
+pub fn part1(input: &str) -> Answer {
+    input
+        .parse::<HeightMap>()
+        .map(|map| climb(&map).map(|path| path.steps()))
+        .into()
 }
 
-pub fn run() {
-    part1();
-    part2();
+pub fn part2(input: &str) -> Answer {
+    input
+        .parse::<HeightMap>()
+        .map(|map| hike(&map).map(|path| path.steps()))
+        .into()
 }
 
 #[cfg(test)]
@@ -106,44 +249,104 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_increment() {
-        let a = Elevation('a');
-        assert_eq!(a.0, 'a');
-        assert_eq!(a.increment().0, 'b');
-    }
-
-    #[test]
-    fn test_valid_move() {
-        let a = Elevation('a');
-        let b = Elevation('b');
-        let c = Elevation('c');
-        assert_eq!(valid_move(&a, &b), true);
-        assert_eq!(valid_move(&a, &a), true);
-        assert_eq!(valid_move(&a, &c), false);
+    fn test_climbing_rule() {
+        let rule = ClimbingRule::default();
+        assert!(rule.allows(0, 1));
+        assert!(rule.allows(0, 0));
+        assert!(!rule.allows(0, 2));
+        assert!(rule.allows(25, 0));
+        assert!(!rule.reversed().allows(25, 0));
+        assert!(rule.reversed().allows(1, 0));
     }
 
     #[test]
     fn test_parse_input() {
         let input = include_str!("../input/test12");
-        let map = parse_input(input);
+        let map: HeightMap = input.parse().unwrap();
 
-        assert_eq!(map[0][0], Elevation('S'));
-        assert_eq!(map[0][3], Elevation('q'));
+        assert_eq!(map.start, (0, 0));
+        assert_eq!(map.end, (5, 2));
+        assert_eq!(map.elevation((0, 0)), 0);
+        assert_eq!(map.elevation((3, 0)), b'q' - b'a');
+        assert_eq!(map.elevation((5, 2)), 25);
+        assert_eq!(
+            "Sab\nbE".parse::<HeightMap>().err(),
+            Some(ParseError::NotRectangular)
+        );
+        assert_eq!(
+            "Sa".parse::<HeightMap>().err(),
+            Some(ParseError::Missing('E'))
+        );
+        assert_eq!(part1("Sa").to_string(), "unsolved (heightmap has no E)");
+        assert_eq!(part2("S!E").to_string(), "unsolved (invalid elevation: !)");
     }
 
     #[test]
     fn test_part1() {
         let input = include_str!("../input/test12");
-        let map = parse_input(input);
-
-        assert_eq!(bfs(&map, Elevation('S'), Elevation('E'), false), 31);
+        let path = climb(&input.parse().unwrap()).unwrap();
+        assert_eq!(path.steps(), 31);
+        assert_eq!(path.start(), (0, 0));
+        assert_eq!(path.end(), (5, 2));
     }
 
     #[test]
     fn test_part2() {
         let input = include_str!("../input/test12");
-        let map = parse_input(input);
+        let path = hike(&input.parse().unwrap()).unwrap();
+        assert_eq!(path.steps(), 29);
+        assert_eq!(path.end(), (5, 2));
+    }
 
-        assert_eq!(bfs(&map, Elevation('E'), Elevation('a'), true), 29);
+    #[test]
+    fn test_no_path() {
+        let map: HeightMap = "Sbz\nddE".parse().unwrap();
+        assert_eq!(
+            map.shortest_path(&[map.start], &[map.end], ClimbingRule::default()),
+            None
+        );
+        let rule = ClimbingRule {
+            max_up: 25,
+            max_down: 0,
+        };
+        let path = map.shortest_path(&[map.start], &[map.end], rule).unwrap();
+        assert_eq!(path.steps(), 3);
+    }
+
+    #[test]
+    fn test_zero_length() {
+        let map: HeightMap = "SaE".parse().unwrap();
+        let path = map
+            .shortest_path(&[map.start, map.end], &[map.end], ClimbingRule::default())
+            .unwrap();
+        assert_eq!(path, Path(vec![(2, 0)]));
+        assert_eq!(path.steps(), 0);
+    }
+
+    #[test]
+    fn test_render() {
+        let input = include_str!("../input/test12");
+        let map: HeightMap = input.parse().unwrap();
+        let path = climb(&map).unwrap();
+        assert_eq!(
+            map.render(&path, false),
+            "\
+>>vv<<<<
+..vvv<<^
+..vv>E^^
+..v>>>^^
+..>>>>>^
+"
+        );
+        assert_eq!(
+            map.render(&path, true),
+            "\
+>>vv<<<<
+abvvv<<^
+acvv>E^^
+acv>>>^^
+ab>>>>>^
+"
+        );
     }
 }
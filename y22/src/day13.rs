@@ -1,3 +1,4 @@
+use crate::answer::Answer;
 use std::{collections::VecDeque, fmt::Display};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -164,14 +165,17 @@ fn check_packet_order(left: &Packet, right: &Packet) -> Order {
                 }
             }
 
-            Order::Correct
+            //Lists that match element for element are equal, not in order
+            if l.len() == r.len() {
+                Order::Equal
+            } else {
+                Order::Correct
+            }
         }
     }
 }
 
-fn part1() {
-    let input = include_str!("../input/13");
-
+pub fn part1(input: &str) -> Answer {
     let packets = parse_input(input);
 
     let packet_pairs = create_pairs(packets);
@@ -189,12 +193,10 @@ fn part1() {
         .iter()
         .sum::<usize>();
 
-    println!("Sum of correct indices: {}", sum_of_correct_indices);
+    sum_of_correct_indices.into()
 }
 
-fn part2() {
-    let input = include_str!("../input/13");
-
+pub fn part2(input: &str) -> Answer {
     let mut packets = parse_input(input);
 
     packets.sort_by(|a, b| {
@@ -222,13 +224,7 @@ fn part2() {
         }
     }
 
-    println!("Part2: {}", indicies.0 * indicies.1);
-}
-
-pub fn run() {
-    println!("Day 13");
-    part1();
-    part2();
+    (indicies.0 * indicies.1).into()
 }
 
 #[cfg(test)]
@@ -478,5 +474,15 @@ mod tests {
         }
 
         assert_eq!(indicies.0 * indicies.1, 140);
+        assert_eq!(part2(input), Answer::from(140));
+    }
+
+    #[test]
+    fn test_equal_lists() {
+        let left = parse_packet("[[1],2]");
+        let right = parse_packet("[[1],3]");
+        assert_eq!(check_packet_order(&left, &left), Order::Equal);
+        assert_eq!(check_packet_order(&left, &right), Order::Correct);
+        assert_eq!(check_packet_order(&right, &left), Order::Incorrect);
     }
 }
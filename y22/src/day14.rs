@@ -1,4 +1,5 @@
-use std::{fmt::Display, str::FromStr};
+use crate::answer::Answer;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum CavePointType {
@@ -106,126 +107,202 @@ impl Iterator for RockPath {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SandEvent {
+    Rested { source: usize, position: (i32, i32) },
+    FellIntoVoid { source: usize },
+    SourceBlocked { source: usize },
+}
+
+#[derive(Debug)]
+struct SandSource {
+    position: (i32, i32),
+    //Trajectory of the previous grain, the next grain follows it as far as it is still free
+    path: Vec<(i32, i32)>,
+    active: bool,
+}
+
 struct CaveMap {
-    map: Vec<Vec<CavePointType>>,
+    map: HashMap<(i32, i32), CavePointType>,
     x_limits: (i32, i32),
     y_limit: i32,
-    sand_entry: (i32, i32),
+    sources: Vec<SandSource>,
+    //Distance of the floor below the lowest rock, the cave is bottomless without it
+    floor_depth: Option<i32>,
 }
 
 impl CaveMap {
     fn new() -> CaveMap {
         CaveMap {
-            map: vec![vec![CavePointType::Air; 1000]; 1000],
-            x_limits: (500, 500),
-            y_limit: (0),
-            sand_entry: (500, 0),
+            map: HashMap::new(),
+            x_limits: (i32::MAX, i32::MIN),
+            y_limit: 0,
+            sources: Vec::new(),
+            floor_depth: None,
         }
     }
+
+    fn from_input(input: &str) -> CaveMap {
+        let mut cave_map = CaveMap::new();
+        input
+            .lines()
+            .map(|l| l.parse::<RockPath>().unwrap())
+            .for_each(|mut r| cave_map.add_rocks(&mut r));
+        cave_map
+    }
+
     fn add_rocks(&mut self, rocks: &mut RockPath) {
         for (x, y) in rocks.by_ref() {
-            if y > self.y_limit {
-                self.y_limit = y;
-            }
-            if x < self.x_limits.0 {
-                self.x_limits.0 = x;
-            }
-            if x > self.x_limits.1 {
-                self.x_limits.1 = x;
-            }
-            self.map[y as usize][x as usize] = CavePointType::Rock;
+            self.y_limit = self.y_limit.max(y);
+            self.x_limits = (self.x_limits.0.min(x), self.x_limits.1.max(x));
+            self.map.insert((x, y), CavePointType::Rock);
         }
     }
 
-    fn drop_one_sand(&mut self, x: &i32, y: &i32) -> Result<(), ()> {
-        let mut y = y.clone();
-        let x = x.clone();
+    fn add_source(&mut self, position: (i32, i32)) {
+        self.sources.push(SandSource {
+            position,
+            path: Vec::new(),
+            active: true,
+        });
+    }
+
+    fn set_floor(&mut self, depth: i32) {
+        self.floor_depth = Some(depth);
+    }
+
+    fn floor(&self) -> Option<i32> {
+        self.floor_depth.map(|depth| self.y_limit + depth)
+    }
+
+    fn is_free(&self, pos: (i32, i32)) -> bool {
+        !self.map.contains_key(&pos) && self.floor() != Some(pos.1)
+    }
 
-        if self.map[y as usize][x as usize] == CavePointType::Sand {
-            return Err(());
+    fn drop_one_sand(&mut self, source: usize) -> SandEvent {
+        let start = self.sources[source].position;
+        if !self.is_free(start) {
+            self.sources[source].active = false;
+            return SandEvent::SourceBlocked { source };
         }
 
-        loop {
-            y += 1;
-            if y > self.y_limit {
-                return Err(()); //Reached bottom
+        let mut path = std::mem::take(&mut self.sources[source].path);
+        while path.last().is_some_and(|&pos| !self.is_free(pos)) {
+            path.pop();
+        }
+        if path.is_empty() {
+            path.push(start);
+        }
+
+        let event = loop {
+            let (x, y) = *path.last().unwrap();
+            if self.floor().is_none() && y > self.y_limit {
+                self.sources[source].active = false;
+                break SandEvent::FellIntoVoid { source };
             }
-            if self.map[y as usize][x as usize] != CavePointType::Air {
-                if self.map[y as usize][x as usize - 1] == CavePointType::Air {
-                    return self.drop_one_sand(&(x - 1), &y);
-                } else if self.map[y as usize][x as usize + 1] == CavePointType::Air {
-                    return self.drop_one_sand(&(x + 1), &y);
-                } else {
-                    self.map[y as usize - 1][x as usize] = CavePointType::Sand;
-                    return Ok(());
+
+            match [(x, y + 1), (x - 1, y + 1), (x + 1, y + 1)]
+                .into_iter()
+                .find(|&pos| self.is_free(pos))
+            {
+                Some(next) => path.push(next),
+                None => {
+                    path.pop();
+                    self.map.insert((x, y), CavePointType::Sand);
+                    break SandEvent::Rested {
+                        source,
+                        position: (x, y),
+                    };
                 }
             }
+        };
+
+        self.sources[source].path = path;
+        event
+    }
+
+    //Drop grains from every active source in turn until they are all blocked or
+    //spilling into the void, returns the number of grains that came to rest
+    fn fill<F>(&mut self, mut on_event: F) -> usize
+    where
+        F: FnMut(&CaveMap, &SandEvent),
+    {
+        let mut rested = 0;
+        while self.sources.iter().any(|s| s.active) {
+            for source in 0..self.sources.len() {
+                if !self.sources[source].active {
+                    continue;
+                }
+                let event = self.drop_one_sand(source);
+                if let SandEvent::Rested { .. } = event {
+                    rested += 1;
+                }
+                on_event(self, &event);
+            }
         }
+        rested
     }
 }
 
 impl Display for CaveMap {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for y in 0..self.y_limit + 1 {
-            for x in self.x_limits.0 - 1..self.x_limits.1 + 2 {
-                if x == self.sand_entry.0 && y == self.sand_entry.1 {
+        let points = self
+            .map
+            .keys()
+            .chain(self.sources.iter().map(|s| &s.position));
+        let (min_x, max_x) = points.fold(self.x_limits, |(min, max), &(x, _)| {
+            (min.min(x), max.max(x))
+        });
+        let min_y = self
+            .sources
+            .iter()
+            .map(|s| s.position.1)
+            .min()
+            .unwrap_or(0)
+            .min(0);
+        let max_y = self.floor().unwrap_or(self.y_limit);
+
+        for y in min_y..=max_y {
+            for x in min_x - 1..=max_x + 1 {
+                if self.sources.iter().any(|s| s.position == (x, y)) && self.is_free((x, y)) {
                     write!(f, "{}", CavePointType::SandEntry)?;
+                } else if self.floor() == Some(y) {
+                    write!(f, "{}", CavePointType::Rock)?;
                 } else {
-                    write!(f, "{}", self.map[y as usize][x as usize])?;
+                    let point = self.map.get(&(x, y)).unwrap_or(&CavePointType::Air);
+                    write!(f, "{}", point)?;
                 }
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
 }
 
-fn part1() -> i32 {
-    let input = include_str!("../input/14");
-    let mut cave_map = CaveMap::new();
-    let mut rocks = input
-        .lines()
-        .map(|l| l.parse::<RockPath>().unwrap())
-        .collect::<Vec<RockPath>>();
-    rocks.iter_mut().for_each(|r| cave_map.add_rocks(r));
-
-    let mut dropped_sand = 0;
-    while let Ok(()) = cave_map.drop_one_sand(&500, &0) {
-        dropped_sand += 1;
+fn sand_map(input: &str, floor: Option<i32>) -> (CaveMap, usize) {
+    let mut cave_map = CaveMap::from_input(input);
+    cave_map.add_source((500, 0));
+    if let Some(floor) = floor {
+        cave_map.set_floor(floor);
     }
-    println!("{}", cave_map);
-    println!("Dropped sand: {}", dropped_sand);
-    dropped_sand
+    let dropped_sand = cave_map.fill(|_, _| {});
+    (cave_map, dropped_sand)
 }
 
-fn part2() -> i32 {
-    let input = include_str!("../input/14");
-    let mut cave_map = CaveMap::new();
-    let mut rocks = input
-        .lines()
-        .map(|l| l.parse::<RockPath>().unwrap())
-        .collect::<Vec<RockPath>>();
-    rocks.iter_mut().for_each(|r| cave_map.add_rocks(r));
-
-    let mut ground_floor = RockPath::new();
-    ground_floor.add_point((1, cave_map.y_limit + 2));
-    ground_floor.add_point((998, cave_map.y_limit + 2));
-
-    cave_map.add_rocks(&mut ground_floor);
-
-    let mut dropped_sand = 0;
-    while let Ok(()) = cave_map.drop_one_sand(&500, &0) {
-        dropped_sand += 1;
-    }
+pub fn part1(input: &str) -> Answer {
+    sand_map(input, None).1.into()
+}
 
-    println!("Dropped sand: {}", dropped_sand);
-    dropped_sand
+pub fn part2(input: &str) -> Answer {
+    sand_map(input, Some(2)).1.into()
 }
 
-pub fn run() {
-    println!("Day 14");
-    println!("Part 1: {}", part1());
-    println!("Part 2: {}", part2());
+pub fn export(input: &str, format: &str) -> Result<Option<String>, String> {
+    Ok(match format {
+        "map" => Some(sand_map(input, None).0.to_string()),
+        "floor" => Some(sand_map(input, Some(2)).0.to_string()),
+        _ => None,
+    })
 }
 
 #[cfg(test)]
@@ -261,46 +338,86 @@ mod tests {
     #[test]
     fn test_part1() {
         let input = include_str!("../input/test14");
-        let mut cave_map = CaveMap::new();
-        let mut rocks = input
-            .lines()
-            .map(|l| l.parse::<RockPath>().unwrap())
-            .collect::<Vec<RockPath>>();
-        rocks.iter_mut().for_each(|r| cave_map.add_rocks(r));
-        println!("{}", cave_map);
-
-        let mut dropped_sand = 0;
-        while let Ok(()) = cave_map.drop_one_sand(&500, &0) {
-            dropped_sand += 1;
-            println!("{}", cave_map);
-        }
-        println!("Dropped sand: {}", dropped_sand);
+        let mut cave_map = CaveMap::from_input(input);
+        cave_map.add_source((500, 0));
+
+        let dropped_sand = cave_map.fill(|_, _| {});
         assert_eq!(dropped_sand, 24);
+
+        let expected = "\
+.......+....
+............
+.......o....
+......ooo...
+.....#ooo##.
+....o#ooo#..
+...###ooo#..
+.....oooo#..
+..o.ooooo#..
+.#########..
+"
+        .replace('o', "O");
+        assert_eq!(cave_map.to_string(), expected);
     }
 
     #[test]
     fn test_part2() {
         let input = include_str!("../input/test14");
-        let mut cave_map = CaveMap::new();
-        let mut rocks = input
-            .lines()
-            .map(|l| l.parse::<RockPath>().unwrap())
-            .collect::<Vec<RockPath>>();
-        rocks.iter_mut().for_each(|r| cave_map.add_rocks(r));
-        println!("{}", cave_map);
+        let mut cave_map = CaveMap::from_input(input);
+        cave_map.add_source((500, 0));
+        cave_map.set_floor(2);
 
-        let mut ground_floor = RockPath::new();
-        ground_floor.add_point((cave_map.x_limits.0 - 15, cave_map.y_limit + 2));
-        ground_floor.add_point((cave_map.x_limits.1 + 15, cave_map.y_limit + 2));
+        assert_eq!(cave_map.fill(|_, _| {}), 93);
+    }
 
-        cave_map.add_rocks(&mut ground_floor);
+    #[test]
+    fn test_events() {
+        let input = include_str!("../input/test14");
+        let mut cave_map = CaveMap::from_input(input);
+        cave_map.add_source((500, 0));
+
+        let mut events = Vec::new();
+        let dropped_sand = cave_map.fill(|_, event| events.push(event.clone()));
+
+        assert_eq!(events.len(), dropped_sand + 1);
+        assert_eq!(
+            events[0],
+            SandEvent::Rested {
+                source: 0,
+                position: (500, 8)
+            }
+        );
+        assert_eq!(
+            events[1],
+            SandEvent::Rested {
+                source: 0,
+                position: (499, 8)
+            }
+        );
+        assert_eq!(events.last(), Some(&SandEvent::FellIntoVoid { source: 0 }));
+    }
 
-        let mut dropped_sand = 0;
-        while let Ok(()) = cave_map.drop_one_sand(&500, &0) {
-            dropped_sand += 1;
-            println!("{}", cave_map);
-        }
-        println!("Dropped sand: {}", dropped_sand);
-        assert_eq!(dropped_sand, 93);
+    #[test]
+    fn test_multiple_sources() {
+        //A single rock only sets the depth, the floor is two below it
+        let mut cave_map = CaveMap::from_input("0,0 -> 0,0");
+        cave_map.set_floor(2);
+        cave_map.add_source((10, 0));
+        cave_map.add_source((20, 0));
+        assert_eq!(cave_map.fill(|_, _| {}), 8);
+
+        let mut cave_map = CaveMap::from_input("0,0 -> 0,0");
+        cave_map.set_floor(2);
+        cave_map.add_source((10, 0));
+        cave_map.add_source((11, 0));
+
+        let mut blocked = Vec::new();
+        let dropped_sand = cave_map.fill(|_, event| {
+            if let SandEvent::SourceBlocked { source } = event {
+                blocked.push(*source);
+            }
+        });
+        assert_eq!(dropped_sand, 6);
+        assert_eq!(blocked, vec![0, 1]);
     }
 }
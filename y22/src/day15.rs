@@ -1,4 +1,5 @@
-use std::{collections::VecDeque, str::FromStr};
+use crate::answer::Answer;
+use std::str::FromStr;
 
 #[derive(Debug)]
 struct Sensor {
@@ -28,171 +29,111 @@ fn manhatten_distance(a: (i32, i32), b: (i32, i32)) -> i32 {
     (a.0 - b.0).abs() + (a.1 - b.1).abs()
 }
 
-fn part1() {
-    let input = include_str!("../input/15");
-    let sensors = input
-        .lines()
-        .map(|s| Sensor::from_str(s).unwrap())
-        .collect::<Vec<Sensor>>();
-
-    let y = 2000000;
-
-    let min_x = sensors
-        .iter()
-        .map(|s| s.position.0 - s.range)
-        .min()
-        .unwrap();
-
-    let max_x = sensors
+//Inclusive x ranges covered by any sensor on the given row, sorted and merged
+fn row_coverage(sensors: &[Sensor], y: i64) -> Vec<(i64, i64)> {
+    let mut ranges = sensors
         .iter()
-        .map(|s| s.position.0 + s.range)
-        .max()
-        .unwrap();
-
-    let mut covered_positions = 0;
-    for x in min_x..max_x {
-        for s in &sensors {
-            if (x, y) == s.position || (x, y) == s.closest_beacon {
-                break;
-            }
-            let dist = manhatten_distance((x, y), s.position);
-            if dist <= s.range {
-                covered_positions += 1;
-                break;
-            }
+        .filter_map(|s| {
+            let half_width = s.range as i64 - (y - s.position.1 as i64).abs();
+            let x = s.position.0 as i64;
+            (half_width >= 0).then_some((x - half_width, x + half_width))
+        })
+        .collect::<Vec<(i64, i64)>>();
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(i64, i64)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
         }
     }
-    println!("Part 1: {}", covered_positions);
+    merged
 }
 
-fn check_if_covered(sensors: &Vec<Sensor>, x: i32, y: i32) -> bool {
-    for s in sensors {
-        if (x, y) == s.position || (x, y) == s.closest_beacon {
-            return true;
-        }
-        let dist = manhatten_distance((x, y), s.position);
-        if dist <= s.range {
-            return true;
-        }
+//Positions on the row that are covered, optionally not counting known beacons
+fn covered_positions(sensors: &[Sensor], y: i64, exclude_beacons: bool) -> i64 {
+    let coverage = row_coverage(sensors, y);
+    let mut covered: i64 = coverage.iter().map(|(start, end)| end - start + 1).sum();
+
+    if exclude_beacons {
+        let mut beacons = sensors
+            .iter()
+            .map(|s| s.closest_beacon)
+            .filter(|b| b.1 as i64 == y)
+            .map(|b| b.0 as i64)
+            .collect::<Vec<i64>>();
+        beacons.sort_unstable();
+        beacons.dedup();
+        covered -= beacons
+            .iter()
+            .filter(|&&x| coverage.iter().any(|&(start, end)| start <= x && x <= end))
+            .count() as i64;
     }
-    false
+    covered
 }
 
-fn find_pos_not_covered(sensors: &Vec<Sensor>, size: i32) -> Option<(i32, i32)> {
-    for s in sensors {
-        println!("{:?}", s);
-
-        //iterate over all positions 1 step away from sensor range
-        //check if any sensor covers that position
-
-        //Start at the top of the sensor range + 1 and go down each side
-        let mut queue = VecDeque::new();
-        queue.push_back((s.position.0, s.position.1 - s.range - 1));
-
-        // println!("From top , going down left");
-        while let Some((x, y)) = queue.pop_front() {
-            if y > s.position.1 {
-                break;
-            }
-
-            queue.push_back((x - 1, y + 1));
-            if x < 0 || y < 0 || x > size || y > size {
-                continue;
-            }
-            // println!("({}, {}) ", x, y);
-
-            let covered = check_if_covered(&sensors, x, y);
-
-            if !covered {
-                return Some((x, y));
-            }
-        }
-
-        queue.clear();
-        queue.push_back((s.position.0, s.position.1 - s.range - 1));
-
-        // println!("From top , going down right");
-        while let Some((x, y)) = queue.pop_front() {
-            if y > s.position.1 {
-                break;
-            }
-
-            queue.push_back((x + 1, y + 1));
-
-            if x < 0 || y < 0 || x > size || y > size {
-                continue;
-            }
-            // println!("({}, {}) ", x, y);
-
-            let covered = check_if_covered(&sensors, x, y);
-
-            if !covered {
-                return Some((x, y));
-            }
-        }
-
-        queue.clear();
-        queue.push_back((s.position.0, s.position.1 + s.range + 1));
-
-        // println!("From bottom , going up left");
-        while let Some((x, y)) = queue.pop_front() {
-            if y < s.position.1 {
-                break;
-            }
-
-            queue.push_back((x - 1, y - 1));
-            if x < 0 || y < 0 || x > size || y > size {
-                continue;
-            }
+fn is_covered(sensors: &[Sensor], x: i64, y: i64) -> bool {
+    sensors.iter().any(|s| {
+        (x - s.position.0 as i64).abs() + (y - s.position.1 as i64).abs() <= s.range as i64
+    })
+}
 
-            let covered = check_if_covered(&sensors, x, y);
+//In rotated coordinates u = x + y and v = x - y every diamond edge is a
+//straight line. A lone uncovered position sits just outside the diamonds,
+//so it lies where those lines meet each other or the border of the area
+fn find_pos_not_covered(sensors: &[Sensor], size: i64) -> Option<(i64, i64)> {
+    let mut u_lines = vec![0, 2 * size];
+    let mut v_lines = vec![-size, size];
+    for s in sensors {
+        let (x, y, r) = (s.position.0 as i64, s.position.1 as i64, s.range as i64);
+        u_lines.extend([x + y - r - 1, x + y + r + 1]);
+        v_lines.extend([x - y - r - 1, x - y + r + 1]);
+    }
+    u_lines.sort_unstable();
+    u_lines.dedup();
+    v_lines.sort_unstable();
+    v_lines.dedup();
 
-            if !covered {
-                return Some((x, y));
+    let mut candidates = Vec::new();
+    for &u in &u_lines {
+        for &v in &v_lines {
+            if (u + v) % 2 == 0 {
+                candidates.push(((u + v) / 2, (u - v) / 2));
             }
         }
+    }
+    for &u in &u_lines {
+        candidates.extend([(0, u), (size, u - size), (u, 0), (u - size, size)]);
+    }
+    for &v in &v_lines {
+        candidates.extend([(0, -v), (size, size - v), (v, 0), (v + size, size)]);
+    }
 
-        queue.clear();
-        queue.push_back((s.position.0, s.position.1 + s.range + 1));
-
-        // println!("From bottom , going up right");
-        while let Some((x, y)) = queue.pop_front() {
-            if y < s.position.1 {
-                break;
-            }
-
-            queue.push_back((x + 1, y - 1));
-
-            if x < 0 || y < 0 || x > size || y > size {
-                continue;
-            }
-
-            let covered = check_if_covered(&sensors, x, y);
+    candidates.into_iter().find(|&(x, y)| {
+        (0..=size).contains(&x) && (0..=size).contains(&y) && !is_covered(sensors, x, y)
+    })
+}
 
-            if !covered {
-                return Some((x, y));
-            }
-        }
-    }
-    None
+fn tuning_frequency((x, y): (i64, i64)) -> i64 {
+    x * 4000000 + y
 }
 
-fn part2_bruteforce() {
-    let input = include_str!("../input/15");
-    let sensors = input
+fn parse_input(input: &str) -> Vec<Sensor> {
+    input
         .lines()
         .map(|s| Sensor::from_str(s).unwrap())
-        .collect::<Vec<Sensor>>();
-
-    let (x, y) = find_pos_not_covered(&sensors, 4000000).unwrap();
+        .collect::<Vec<Sensor>>()
+}
 
-    println!("Part 2: {}", x as i64 * 4000000 + y as i64);
+pub fn part1(input: &str) -> Answer {
+    covered_positions(&parse_input(input), 2000000, true).into()
 }
 
-pub fn run() {
-    println!("Day 15");
-    part1();
-    part2_bruteforce();
+pub fn part2(input: &str) -> Answer {
+    find_pos_not_covered(&parse_input(input), 4000000)
+        .map(tuning_frequency)
+        .into()
 }
 
 #[cfg(test)]
@@ -218,65 +159,35 @@ mod tests {
     }
 
     #[test]
-    fn part1() {
-        println!("Part 1: {}", 0);
-
-        let input = include_str!("../input/test15");
-        let sensors = input
-            .lines()
-            .map(|s| Sensor::from_str(s).unwrap())
-            .collect::<Vec<Sensor>>();
-
-        let y = 10;
-
-        let min_x = sensors
-            .iter()
-            .map(|s| s.position.0 - s.range)
-            .min()
-            .unwrap();
-
-        let max_x = sensors
-            .iter()
-            .map(|s| s.position.0 + s.range)
-            .max()
-            .unwrap();
+    fn test_row_coverage() {
+        let sensors = parse_input(include_str!("../input/test15"));
+        assert_eq!(row_coverage(&sensors, 10), vec![(-2, 24)]);
+        assert_eq!(row_coverage(&sensors, 11), vec![(-3, 13), (15, 25)]);
+        assert_eq!(row_coverage(&sensors, -100), vec![]);
+    }
 
-        let mut covered_positions = 0;
-        for x in min_x..max_x {
-            let mut covered = false;
-            for s in &sensors {
-                if (x, y) == s.position || (x, y) == s.closest_beacon {
-                    break;
-                }
-                let dist = manhatten_distance((x, y), s.position);
-                if dist <= s.range {
-                    covered_positions += 1;
-                    covered = true;
-                    break;
-                }
-            }
-            if covered {
-                print!("#");
-            } else {
-                print!(".");
-            }
-        }
-        println!("Covered positions: {}", covered_positions);
-        assert_eq!(covered_positions, 26);
+    #[test]
+    fn part1() {
+        let sensors = parse_input(include_str!("../input/test15"));
+        assert_eq!(covered_positions(&sensors, 10, true), 26);
+        assert_eq!(covered_positions(&sensors, 10, false), 27);
     }
 
     #[test]
     fn part2() {
         let input = include_str!("../input/test15");
-        let sensors = input
-            .lines()
-            .map(|s| Sensor::from_str(s).unwrap())
-            .collect::<Vec<Sensor>>();
-
-        let (x, y) = find_pos_not_covered(&sensors, 21).unwrap();
+        let sensors = parse_input(input);
 
-        println!("Part 2: {}, {}", x, y);
+        let (x, y) = find_pos_not_covered(&sensors, 20).unwrap();
+        assert_eq!((x, y), (14, 11));
+        assert_eq!(tuning_frequency((x, y)), 56000011);
+    }
 
-        assert_eq!(x * 4000000 + y, 56000011);
+    #[test]
+    fn test_uncovered_on_border() {
+        //One sensor covering everything but the corner of the area
+        let sensors = parse_input("Sensor at x=0, y=0: closest beacon is at x=3, y=2");
+        assert_eq!(find_pos_not_covered(&sensors, 3), Some((3, 3)));
+        assert_eq!(find_pos_not_covered(&sensors, 2), None);
     }
 }
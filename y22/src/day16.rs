@@ -1,7 +1,8 @@
+use crate::answer::Answer;
+use dot::{Dot, Edge, Highlight, Node};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     fmt::Display,
-    time::Instant,
     vec,
 };
 
@@ -107,15 +108,20 @@ impl Graph {
     }
 
     fn optimize_graph(&mut self) -> (usize, Vec<usize>, Vec<Vec<usize>>) {
+        self.compress("AA")
+    }
+
+    //Distance matrix between the start valve and every valve with a flow rate
+    fn compress(&mut self, start: &str) -> (usize, Vec<usize>, Vec<Vec<usize>>) {
         let mut index = HashMap::new();
         let mut v = Vec::new();
         let mut start_index = 0;
         for (_, valve) in self.valves.iter() {
-            if valve.flow_rate != 0 || valve.name == "AA" {
+            if valve.flow_rate != 0 || valve.name == start {
                 v.push(valve.flow_rate as usize);
                 index.insert(valve.name.clone(), v.len() - 1);
             }
-            if valve.name == "AA" {
+            if valve.name == start {
                 start_index = v.len() - 1;
             }
         }
@@ -160,9 +166,6 @@ impl Graph {
         let mut t = vec![vec![0; v.len()]; v.len()];
         for valve in index.keys() {
             for tunnel in tunnels.get(valve).unwrap() {
-                if tunnel.to == "AA" {
-                    continue;
-                }
                 t[index[valve]][index[&tunnel.to]] = tunnel.time as usize;
             }
         }
@@ -217,16 +220,258 @@ impl Graph {
     }
 }
 
-fn part1(input: &str) -> i32 {
+#[derive(Debug, Clone, PartialEq)]
+struct Plan {
+    pressure: i32,
+    //Valves each agent opens in order, with the minute it is opened
+    routes: Vec<Vec<(String, i32)>>,
+}
+
+impl Dot for Graph {
+    fn graph_name(&self) -> String {
+        "valves".to_string()
+    }
+
+    fn directed(&self) -> bool {
+        false
+    }
+
+    fn nodes(&self) -> Vec<Node> {
+        let mut valves: Vec<&Valve> = self.valves.values().collect();
+        valves.sort_by(|a, b| a.name.cmp(&b.name));
+        valves
+            .iter()
+            .map(|v| Node::with_label(&v.name, &format!("{}\nrate={}", v.name, v.flow_rate)))
+            .collect()
+    }
+
+    fn edges(&self) -> Vec<Edge> {
+        let mut from: Vec<&String> = self.tunnels.keys().collect();
+        from.sort();
+        from.iter()
+            .flat_map(|valve| {
+                self.tunnels[*valve]
+                    .iter()
+                    .map(|tunnel| Edge::new(valve, &tunnel.to))
+            })
+            .collect()
+    }
+}
+
+//The distance matrix from optimize_graph with valve names attached
+struct CompressedGraph {
+    names: Vec<String>,
+    flows: Vec<usize>,
+    dist: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn compressed(&mut self, start: &str) -> CompressedGraph {
+        let (_, flows, dist) = self.compress(start);
+        let mut names = vec![String::new(); flows.len()];
+        for (name, &i) in self.valve_index.iter() {
+            names[i] = name.clone();
+        }
+        CompressedGraph { names, flows, dist }
+    }
+}
+
+impl Dot for CompressedGraph {
+    fn graph_name(&self) -> String {
+        "compressed".to_string()
+    }
+
+    fn directed(&self) -> bool {
+        false
+    }
+
+    fn nodes(&self) -> Vec<Node> {
+        let mut nodes: Vec<Node> = self
+            .names
+            .iter()
+            .zip(self.flows.iter())
+            .map(|(name, flow)| Node::with_label(name, &format!("{}\nrate={}", name, flow)))
+            .collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes
+    }
+
+    //Only valves with a flow rate have distances to them in the matrix
+    fn edges(&self) -> Vec<Edge> {
+        let mut edges = Vec::new();
+        for from in 0..self.names.len() {
+            for to in 0..self.names.len() {
+                if from == to || self.flows[to] == 0 {
+                    continue;
+                }
+                let (a, b) = if self.names[from] < self.names[to] {
+                    (from, to)
+                } else {
+                    (to, from)
+                };
+                edges.push(Edge::with_label(
+                    &self.names[a],
+                    &self.names[b],
+                    &self.dist[from][to].to_string(),
+                ));
+            }
+        }
+        edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+        edges
+    }
+}
+
+//Compressed valve index and the minute it is opened
+type Route = Vec<(usize, i32)>;
+
+#[derive(Debug, Clone)]
+struct SubsetTable {
+    //Best pressure and route for opening exactly the valves in each mask
+    best: Vec<Option<(i32, Route)>>,
+    //Valve index in the compressed graph for every bit of the masks
+    valves: Vec<usize>,
+}
+
+struct Planner {
+    agents: usize,
+    time: i32,
+    start: String,
+}
+
+impl Planner {
+    fn new(agents: usize, time: i32, start: &str) -> Planner {
+        Planner {
+            agents,
+            time,
+            start: start.to_string(),
+        }
+    }
+
+    //Walk every route one agent can take and keep the best one for each set of valves
+    fn subset_table(&self, flows: &[usize], dist: &[Vec<usize>], start: usize) -> SubsetTable {
+        let valves: Vec<usize> = (0..flows.len()).filter(|&i| flows[i] > 0).collect();
+        let mut table = SubsetTable {
+            best: vec![None; 1 << valves.len()],
+            valves,
+        };
+
+        let mut stack = vec![(start, self.time, 0usize, 0i32, Vec::new())];
+        while let Some((pos, time_left, mask, pressure, route)) = stack.pop() {
+            if table.best[mask]
+                .as_ref()
+                .is_none_or(|(best, _)| pressure > *best)
+            {
+                table.best[mask] = Some((pressure, route.clone()));
+            }
+
+            for (bit, &valve) in table.valves.iter().enumerate() {
+                if mask & (1 << bit) != 0 {
+                    continue;
+                }
+                let time_left = time_left - dist[pos][valve] as i32 - 1;
+                if time_left <= 0 {
+                    continue;
+                }
+                let mut route = route.clone();
+                route.push((valve, self.time - time_left));
+                stack.push((
+                    valve,
+                    time_left,
+                    mask | 1 << bit,
+                    pressure + flows[valve] as i32 * time_left,
+                    route,
+                ));
+            }
+        }
+        table
+    }
+
+    fn plan(&self, graph: &mut Graph) -> Result<Plan, String> {
+        if !graph.valves.contains_key(&self.start) {
+            return Err(format!("unknown start valve: {}", self.start));
+        }
+        if self.agents == 0 {
+            return Err("at least one agent is needed".to_string());
+        }
+
+        let (start, flows, dist) = graph.compress(&self.start);
+        let table = self.subset_table(&flows, &dist, start);
+        let masks = table.best.len();
+        let full = masks - 1;
+
+        //Best single route within each mask, and which exact subset achieves it
+        let mut within: Vec<(i32, usize)> = (0..masks)
+            .map(|mask| (table.best[mask].as_ref().map_or(0, |b| b.0), mask))
+            .collect();
+        max_over_subsets(&mut within);
+
+        //Each extra agent takes a subset disjoint from what the others take,
+        //only the last agent needs to look at the full set of valves
+        let mut levels = vec![within.clone()];
+        for agent in 1..self.agents {
+            let previous = &levels[agent - 1];
+            let targets: Vec<usize> = if agent + 1 == self.agents {
+                vec![full]
+            } else {
+                (0..masks).collect()
+            };
+            let mut level = vec![(0, 0); masks];
+            for mask in targets {
+                let mut sub = mask;
+                loop {
+                    let pressure = within[sub].0 + previous[mask ^ sub].0;
+                    if pressure >= level[mask].0 {
+                        level[mask] = (pressure, sub);
+                    }
+                    if sub == 0 {
+                        break;
+                    }
+                    sub = (sub - 1) & mask;
+                }
+            }
+            levels.push(level);
+        }
+
+        let names: HashMap<usize, String> = graph
+            .valve_index
+            .iter()
+            .map(|(name, &i)| (i, name.clone()))
+            .collect();
+
+        let pressure = levels[self.agents - 1][full].0;
+        let mut routes = Vec::new();
+        let mut mask = full;
+        for agent in (0..self.agents).rev() {
+            let sub = if agent == 0 {
+                mask
+            } else {
+                levels[agent][mask].1
+            };
+            let route = table.best[within[sub].1]
+                .as_ref()
+                .map_or(vec![], |(_, route)| {
+                    route
+                        .iter()
+                        .map(|&(valve, minute)| (names[&valve].clone(), minute))
+                        .collect()
+                });
+            routes.push(route);
+            mask ^= sub;
+        }
+        routes.reverse();
+
+        Ok(Plan { pressure, routes })
+    }
+}
+
+pub fn part1(input: &str) -> Answer {
     let mut graph = Graph::new();
 
     for line in input.lines() {
         graph.parse_line(line);
     }
 
-    let start = Instant::now();
     let (start_index, valves, neigbour_shortest_dist) = graph.optimize_graph();
-    println!("Time: {}", start.elapsed().as_millis());
 
     let mut cache = HashMap::new();
 
@@ -238,67 +483,105 @@ fn part1(input: &str) -> i32 {
         0,
         &mut cache,
     );
-    println!("Time: {}", start.elapsed().as_millis());
-    max
+    max.into()
 }
 
-fn part2(input: &str) -> i32 {
-    let mut graph = Graph::new();
-
-    for line in input.lines() {
-        graph.parse_line(line);
+//Sum over subsets with max: afterwards every mask holds the best value of any of its subsets
+fn max_over_subsets<T: Copy + Ord>(values: &mut [T]) {
+    let bits = values.len().trailing_zeros();
+    for bit in 0..bits {
+        for mask in 0..values.len() {
+            if mask & (1 << bit) != 0 {
+                values[mask] = values[mask].max(values[mask ^ (1 << bit)]);
+            }
+        }
     }
+}
 
-    let (start_index, valves, neighbours_shortest_dist) = graph.optimize_graph();
+//Phase timings for the split search, printed to stderr when built with the timing feature
+#[cfg(feature = "timing")]
+struct Timer(std::time::Instant);
 
-    //find the maximum number of different bitmasks
-    let num_valves = valves.len();
-    println!("num_valves: {}", num_valves);
+#[cfg(feature = "timing")]
+impl Timer {
+    fn start() -> Timer {
+        Timer(std::time::Instant::now())
+    }
 
-    let num_bitmasks = 2_u32.pow(num_valves as u32);
-    println!("num_bitmasks: {}", num_bitmasks);
+    fn lap(&mut self, phase: &str) {
+        eprintln!("{}: {:?}", phase, self.0.elapsed());
+        self.0 = std::time::Instant::now();
+    }
+}
 
-    let mut max = 0;
+#[cfg(not(feature = "timing"))]
+struct Timer;
 
-    let mut cache = HashMap::new();
-    let mut cache2 = HashMap::new();
-
-    for i in 0..num_bitmasks {
-        let pressure_released = graph.dfs(
-            start_index,
-            &valves,
-            &neighbours_shortest_dist,
-            26,
-            i,
-            &mut cache,
-        ) + graph.dfs(
-            start_index,
-            &valves,
-            &neighbours_shortest_dist,
-            26,
-            !i,
-            &mut cache2,
-        );
-        if pressure_released > max {
-            println!("i: {}, pressure_released: {}", i, pressure_released);
-            max = pressure_released;
-        }
+#[cfg(not(feature = "timing"))]
+impl Timer {
+    fn start() -> Timer {
+        Timer
     }
 
-    max
+    fn lap(&mut self, _phase: &str) {}
 }
 
-pub fn run() {
-    println!("Day 16");
-    let input = include_str!("../input/16");
+pub fn part2(input: &str) -> Answer {
+    let mut timer = Timer::start();
+    let mut graph = Graph::new();
+
+    for line in input.lines() {
+        graph.parse_line(line);
+    }
 
-    let start = Instant::now();
-    println!("Part1: {}", part1(input));
-    println!("Time: {}", start.elapsed().as_millis());
+    let (start_index, valves, neighbours_shortest_dist) = graph.optimize_graph();
+    timer.lap("compress");
+
+    let table =
+        Planner::new(2, 26, "AA").subset_table(&valves, &neighbours_shortest_dist, start_index);
+    let mut best: Vec<i32> = table
+        .best
+        .iter()
+        .map(|best| best.as_ref().map_or(0, |(pressure, _)| *pressure))
+        .collect();
+    timer.lap("mask table");
+
+    max_over_subsets(&mut best);
+    timer.lap("subsets");
+
+    //The elephant takes whatever I leave, each split is looked at once by
+    //keeping the highest valve on the elephant's side
+    let full = best.len() - 1;
+    let max = (0..best.len().div_ceil(2))
+        .map(|mask| best[mask] + best[full ^ mask])
+        .max()
+        .unwrap_or(0);
+    timer.lap("split");
+
+    max.into()
+}
 
-    let start = Instant::now();
-    println!("Part!: {}", part2(input));
-    println!("Time: {}", start.elapsed().as_millis());
+pub fn export(input: &str, format: &str) -> Result<Option<String>, String> {
+    if format != "plan" && format != "dot" {
+        return Ok(None);
+    }
+    let mut graph = Graph::new();
+    for line in input.lines() {
+        graph.parse_line(line);
+    }
+    let plan = Planner::new(2, 26, "AA").plan(&mut graph)?;
+    if format == "plan" {
+        let mut lines = vec![format!("Plan: {}", plan.pressure)];
+        for (agent, route) in plan.routes.iter().enumerate() {
+            lines.push(format!("Agent {}: {:?}", agent + 1, route));
+        }
+        return Ok(Some(lines.join("\n")));
+    }
+    //The compressed graph with the first agent's route, for rendering with dot
+    let mut path = vec!["AA".to_string()];
+    path.extend(plan.routes[0].iter().map(|(valve, _)| valve.clone()));
+    let highlight = Highlight::new().path(&path);
+    Ok(Some(graph.compressed("AA").to_dot(&highlight)))
 }
 
 #[cfg(test)]
@@ -396,13 +679,131 @@ mod tests {
     fn test_part1() {
         let input = include_str!("../input/test16");
 
-        assert_eq!(part1(input), 1651);
+        assert_eq!(part1(input), Answer::from(1651));
     }
 
     #[test]
     fn test_part2() {
         let input = include_str!("../input/test16");
 
-        assert_eq!(part2(input), 1707);
+        assert_eq!(part2(input), Answer::from(1707));
+    }
+
+    #[test]
+    fn test_max_over_subsets() {
+        let mut values = vec![0, 5, 3, 1, 0, 0, 7, 2];
+        max_over_subsets(&mut values);
+        assert_eq!(values, vec![0, 5, 3, 5, 0, 5, 7, 7]);
+    }
+
+    #[test]
+    fn test_part2_matches_planner() {
+        let input = include_str!("../input/16");
+        let mut graph = Graph::new();
+        for line in input.lines() {
+            graph.parse_line(line);
+        }
+        let plan = Planner::new(2, 26, "AA").plan(&mut graph).unwrap();
+        assert_eq!(part2(input), Answer::from(plan.pressure));
+    }
+
+    fn plan(agents: usize, time: i32, start: &str) -> Result<Plan, String> {
+        let mut graph = Graph::new();
+        for line in include_str!("../input/test16").lines() {
+            graph.parse_line(line);
+        }
+        Planner::new(agents, time, start).plan(&mut graph)
+    }
+
+    fn route_pressure(route: &[(String, i32)], time: i32) -> i32 {
+        let flows: HashMap<&str, i32> = include_str!("../input/test16")
+            .lines()
+            .map(|line| {
+                let name = &line[6..8];
+                let rate = line.split('=').nth(1).unwrap().split(';').next().unwrap();
+                (name, rate.parse().unwrap())
+            })
+            .collect();
+        route
+            .iter()
+            .map(|(valve, minute)| flows[valve.as_str()] * (time - minute))
+            .sum()
+    }
+
+    #[test]
+    fn test_planner() {
+        let single = plan(1, 30, "AA").unwrap();
+        assert_eq!(single.pressure, 1651);
+        assert_eq!(single.routes.len(), 1);
+        assert_eq!(route_pressure(&single.routes[0], 30), 1651);
+        assert_eq!(single.routes[0][0], ("DD".to_string(), 2));
+
+        let pair = plan(2, 26, "AA").unwrap();
+        assert_eq!(pair.pressure, 1707);
+        let total: i32 = pair.routes.iter().map(|r| route_pressure(r, 26)).sum();
+        assert_eq!(total, 1707);
+    }
+
+    #[test]
+    fn test_planner_many_agents() {
+        let pair = plan(2, 10, "AA").unwrap();
+        let three = plan(3, 10, "AA").unwrap();
+        assert!(three.pressure >= pair.pressure);
+        assert_eq!(three.routes.len(), 3);
+
+        let total: i32 = three.routes.iter().map(|r| route_pressure(r, 10)).sum();
+        assert_eq!(total, three.pressure);
+
+        let opened: Vec<&String> = three.routes.iter().flatten().map(|(v, _)| v).collect();
+        let unique: HashSet<&String> = opened.iter().copied().collect();
+        assert_eq!(opened.len(), unique.len());
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut graph = Graph::new();
+        for line in include_str!("../input/test16").lines() {
+            graph.parse_line(line);
+        }
+
+        let dot = graph.to_dot(&Highlight::new().visited(["AA"]));
+        assert!(dot.starts_with("graph \"valves\" {\n"));
+        assert!(dot.contains(
+            "\"AA\" [label=\"AA\\nrate=0\", style=\"filled\", fillcolor=\"lightgrey\"];"
+        ));
+        assert!(dot.contains("\"AA\" -- \"DD\";"));
+        assert!(!dot.contains("\"DD\" -- \"AA\";"));
+        assert_eq!(dot.matches(" -- ").count(), 10);
+
+        let route = ["AA", "DD", "BB", "JJ"];
+        let dot = graph
+            .compressed("AA")
+            .to_dot(&Highlight::new().path(&route));
+        assert!(dot.contains("\"AA\" -- \"DD\" [label=\"1\", color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"BB\" -- \"JJ\" [label=\"3\", color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"HH\" -- \"JJ\" [label=\"7\"];"));
+        assert!(!dot.contains("\"FF\""));
+    }
+
+    #[test]
+    fn test_planner_start() {
+        assert!(plan(1, 30, "ZZ").is_err());
+        assert!(plan(0, 30, "AA").is_err());
+
+        //Starting on a valve with a flow rate it can be opened right away
+        let from_jj = plan(1, 3, "JJ").unwrap();
+        assert_eq!(from_jj.pressure, 42);
+        assert_eq!(from_jj.routes[0], vec![("JJ".to_string(), 1)]);
+
+        //Coming back to open the start valve costs the walk back
+        let mut graph = Graph::new();
+        graph.parse_line("Valve AA has flow rate=1; tunnel leads to valve BB");
+        graph.parse_line("Valve BB has flow rate=100; tunnel leads to valve AA");
+        let back = Planner::new(1, 5, "AA").plan(&mut graph).unwrap();
+        assert_eq!(back.pressure, 301);
+        assert_eq!(
+            back.routes[0],
+            vec![("BB".to_string(), 2), ("AA".to_string(), 4)]
+        );
     }
 }
@@ -1,477 +1,463 @@
-use std::{collections::HashMap, fmt::Display};
+use crate::answer::Answer;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
-#[derive(Debug, PartialEq)]
+//The puzzle's rocks, used unless the runner loads other shapes
+pub const ROCKS: &str = include_str!("../input/rocks17");
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 enum Direction {
     Left,
     Right,
-    Down,
 }
 
-impl From<char> for Direction {
-    fn from(c: char) -> Self {
+impl TryFrom<char> for Direction {
+    type Error = ParseError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
         match c {
-            '<' => Direction::Left,
-            '>' => Direction::Right,
-            _ => panic!("Invalid direction: {}", c),
+            '<' => Ok(Direction::Left),
+            '>' => Ok(Direction::Right),
+            _ => Err(ParseError::InvalidJet(c)),
         }
     }
 }
 
-struct JetStream {
-    jet_stream: Vec<Direction>,
-    position: usize,
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    InvalidJet(char),
+    NoJets,
+    InvalidShapeTile(char),
+    EmptyShape(usize),
+    NoShapes,
+    InvalidConfig(String),
+    //A new rock would stick out of the right wall
+    ShapeTooWide { shape: usize, width: usize },
 }
 
-impl JetStream {
-    fn new(input: &str) -> Self {
-        let mut jet_stream = Vec::new();
-
-        for c in input.chars() {
-            jet_stream.push(Direction::from(c));
-        }
-        JetStream {
-            jet_stream,
-            position: 0,
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidJet(c) => write!(f, "invalid jet direction: {}", c),
+            ParseError::NoJets => write!(f, "the jet pattern is empty"),
+            ParseError::InvalidShapeTile(c) => write!(f, "invalid rock tile: {}", c),
+            ParseError::EmptyShape(i) => write!(f, "rock shape {} has no rock in it", i),
+            ParseError::NoShapes => write!(f, "no rock shapes defined"),
+            ParseError::InvalidConfig(s) => {
+                write!(f, "invalid chamber {:?}, expected WIDTH,GAP,OFFSET", s)
+            }
+            ParseError::ShapeTooWide { shape, width } => write!(
+                f,
+                "rock shape {} doesn't fit in a chamber {} wide",
+                shape, width
+            ),
         }
     }
-    fn next(&mut self) -> &Direction {
-        let jet_stream_direction = self.jet_stream.get(self.position).unwrap();
+}
 
-        self.position += 1;
-        if self.position >= self.jet_stream.len() {
-            self.position = 0;
-        }
-        jet_stream_direction
+fn parse_jets(input: &str) -> Result<Vec<Direction>, ParseError> {
+    let jets = input
+        .trim()
+        .chars()
+        .map(Direction::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    if jets.is_empty() {
+        return Err(ParseError::NoJets);
     }
+    Ok(jets)
 }
 
-#[derive(Debug, PartialEq, Hash, Eq, Clone)]
-enum Rock {
-    Horizontal,
-    Plus,
-    ReverseL,
-    Vertical,
-    Square,
+//Cells of a rock as (x, y) with (0, 0) at the bottom left of its bounding box
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Shape {
+    cells: Vec<(usize, usize)>,
+    width: usize,
+    height: usize,
 }
 
-impl Rock {
-    fn height(&self) -> usize {
-        match self {
-            Rock::Horizontal => 1,
-            Rock::Plus => 3,
-            Rock::ReverseL => 3,
-            Rock::Vertical => 4,
-            Rock::Square => 2,
+impl Shape {
+    fn from_drawing(index: usize, drawing: &str) -> Result<Self, ParseError> {
+        let rows = drawing.lines().map(str::trim_end).collect::<Vec<_>>();
+        let height = rows.len();
+        let mut cells = Vec::new();
+        for (row, line) in rows.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => cells.push((x, height - 1 - row)),
+                    '.' | ' ' => {}
+                    c => return Err(ParseError::InvalidShapeTile(c)),
+                }
+            }
         }
-    }
-
-    fn width(&self) -> usize {
-        match self {
-            Rock::Horizontal => 4,
-            Rock::Plus => 3,
-            Rock::ReverseL => 3,
-            Rock::Vertical => 1,
-            Rock::Square => 2,
+        if cells.is_empty() {
+            return Err(ParseError::EmptyShape(index));
         }
+        //Trim empty columns on the left and rows at the bottom
+        let min_x = cells.iter().map(|c| c.0).min().unwrap();
+        let min_y = cells.iter().map(|c| c.1).min().unwrap();
+        let cells = cells
+            .into_iter()
+            .map(|(x, y)| (x - min_x, y - min_y))
+            .collect::<Vec<_>>();
+        Ok(Shape {
+            width: cells.iter().map(|c| c.0).max().unwrap() + 1,
+            height: cells.iter().map(|c| c.1).max().unwrap() + 1,
+            cells,
+        })
     }
+}
 
-    fn get(&self) -> Vec<Vec<usize>> {
-        match self {
-            Rock::Horizontal => {
-                vec![vec![1, 1, 1, 1]]
-            }
-            Rock::Plus => {
-                vec![vec![0, 1, 0], vec![1, 1, 1], vec![0, 1, 0]]
-            }
-            Rock::Vertical => {
-                vec![vec![1], vec![1], vec![1], vec![1]]
-            }
-            Rock::ReverseL => {
-                vec![vec![1, 1, 1], vec![0, 0, 1], vec![0, 0, 1]]
-            }
-            Rock::Square => {
-                vec![vec![1, 1], vec![1, 1]]
-            }
-        }
+//Rock shapes drawn with '#', separated by blank lines, in the order they fall
+fn parse_shapes(input: &str) -> Result<Vec<Shape>, ParseError> {
+    let input = input.replace("\r\n", "\n");
+    let shapes = input
+        .split("\n\n")
+        .filter(|drawing| !drawing.trim().is_empty())
+        .enumerate()
+        .map(|(i, drawing)| Shape::from_drawing(i, drawing))
+        .collect::<Result<Vec<_>, _>>()?;
+    if shapes.is_empty() {
+        return Err(ParseError::NoShapes);
     }
+    Ok(shapes)
 }
 
-struct Rocks {
-    rocks: Vec<Rock>,
-    position: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    width: usize,
+    //Empty rows between the highest rock and a new rock
+    gap: usize,
+    //Distance between the left wall and a new rock
+    offset: usize,
 }
 
-impl Rocks {
-    fn new() -> Self {
-        let rocks = vec![
-            Rock::Horizontal,
-            Rock::Plus,
-            Rock::ReverseL,
-            Rock::Vertical,
-            Rock::Square,
-        ];
-        Rocks { rocks, position: 0 }
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: 7,
+            gap: 3,
+            offset: 2,
+        }
     }
-    fn next(&mut self) -> &Rock {
-        let rocks = self.rocks.get(self.position).unwrap();
+}
 
-        self.position += 1;
-        if self.position >= self.rocks.len() {
-            self.position = 0;
-        }
-        rocks
+//Written as WIDTH,GAP,OFFSET
+impl FromStr for Config {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers = s
+            .split(',')
+            .map(|n| n.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ParseError::InvalidConfig(s.to_string()))?;
+        let [width, gap, offset] = numbers[..] else {
+            return Err(ParseError::InvalidConfig(s.to_string()));
+        };
+        Ok(Config { width, gap, offset })
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Hash, Eq)]
+#[derive(Debug, PartialEq, Clone, Copy, Hash, Eq)]
 enum ChamberPos {
     Air,
     Rock,
 }
 
-#[derive(Eq, PartialEq, Hash)]
-struct Cache(usize, Rock, [Vec<ChamberPos>; 12]);
+//Everything that decides how the next rocks fall, up to a shift in height
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct State {
+    shape: usize,
+    jet: usize,
+    //Depth of the highest rock in every column below the top, capped at the height
+    profile: Vec<usize>,
+}
 
 struct Chamber {
-    height: usize,
-    width: usize,
-    chamber: Vec<Vec<ChamberPos>>,
+    config: Config,
+    shapes: Vec<Shape>,
+    jets: Vec<Direction>,
+    rows: Vec<Vec<ChamberPos>>,
+    next_shape: usize,
+    next_jet: usize,
     rocks_dropped: u64,
-    repeating_sequence: Option<(usize, u64)>,
 }
 
 impl Chamber {
-    fn new(width: usize, height: usize) -> Self {
-        let mut chamber = Vec::new();
-
-        for _ in 0..height {
-            let mut row = Vec::new();
-            for _ in 0..width {
-                row.push(ChamberPos::Air);
-            }
-            chamber.push(row);
+    fn new(config: Config, shapes: Vec<Shape>, jets: Vec<Direction>) -> Result<Self, ParseError> {
+        if let Some(shape) = shapes
+            .iter()
+            .position(|shape| config.offset + shape.width > config.width)
+        {
+            return Err(ParseError::ShapeTooWide {
+                shape,
+                width: config.width,
+            });
         }
-
-        Chamber {
-            height,
-            width,
-            chamber,
-            repeating_sequence: None,
+        Ok(Chamber {
+            config,
+            shapes,
+            jets,
+            rows: Vec::new(),
+            next_shape: 0,
+            next_jet: 0,
             rocks_dropped: 0,
-        }
+        })
     }
 
-    fn increase_height(&mut self, min_height: usize) {
-        let delta_height = min_height as i32 - self.height as i32;
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
 
-        if delta_height <= 0 {
-            return;
-        }
+    fn fits(&self, shape: &Shape, x: usize, y: usize) -> bool {
+        x + shape.width <= self.config.width
+            && shape.cells.iter().all(|&(dx, dy)| {
+                self.rows
+                    .get(y + dy)
+                    .is_none_or(|row| row[x + dx] == ChamberPos::Air)
+            })
+    }
 
-        for _ in 0..delta_height {
-            let mut row = Vec::new();
-            for _ in 0..self.width {
-                row.push(ChamberPos::Air);
+    fn drop_rock(&mut self) {
+        let shape = self.shapes[self.next_shape].clone();
+        self.next_shape = (self.next_shape + 1) % self.shapes.len();
+
+        let mut x = self.config.offset;
+        let mut y = self.height() + self.config.gap;
+        loop {
+            let jet = self.jets[self.next_jet];
+            self.next_jet = (self.next_jet + 1) % self.jets.len();
+            let pushed = match jet {
+                Direction::Left => x.checked_sub(1),
+                Direction::Right => Some(x + 1),
+            };
+            if let Some(pushed) = pushed.filter(|&px| self.fits(&shape, px, y)) {
+                x = pushed;
+            }
+            if y == 0 || !self.fits(&shape, x, y - 1) {
+                break;
             }
-            self.chamber.push(row);
-            self.height += 1;
+            y -= 1;
         }
-    }
-
-    fn valid_move(&self, rock: &Rock, height: usize, pos: usize, direction: &Direction) -> bool {
-        let r = rock.get();
-        // println!("height: {:?}, pos: {:?}", height, pos);
-        // println!("rock: {:?}", r);
-        // println!("direction: {:?}", direction);
-
-        let (dy, dx): (i32, i32) = match direction {
-            Direction::Left => (0, -1),
-            Direction::Right => (0, 1),
-            Direction::Down => (-1, 0),
-        };
 
-        for (i, row) in r.iter().enumerate() {
-            for (j, element) in row.iter().enumerate() {
-                let y = usize::try_from((i + height) as i32 + dy).ok();
-                let x = usize::try_from((j + pos) as i32 + dx).ok();
-
-                // println!("y: {:?}, x: {:?}", y, x);
-
-                match (x, y) {
-                    (Some(x), Some(y)) => {
-                        if x >= self.width || y == 0 {
-                            // println!("OUTSIDE");
-                            return false;
-                        }
-
-                        if y <= self.height
-                            && *element == 1
-                            && self.chamber[y - 1][x] == ChamberPos::Rock
-                        {
-                            // println!("BONK");
-                            return false;
-                        }
-                    }
-                    _ => return false,
-                }
+        for &(dx, dy) in &shape.cells {
+            while self.rows.len() <= y + dy {
+                self.rows.push(vec![ChamberPos::Air; self.config.width]);
             }
+            self.rows[y + dy][x + dx] = ChamberPos::Rock;
         }
-        // println!("Valid move\n");
-        true
+        self.rocks_dropped += 1;
     }
 
-    fn drop_rock(
-        &mut self,
-        rock: &Rock,
-        pos: usize,
-        jetstream: Option<&mut JetStream>,
-        cache: &mut HashMap<Cache, (usize, u64)>,
-    ) {
-        let mut binding = JetStream::new("");
-        let stream = jetstream.unwrap_or(&mut binding);
-
-        let height = self.height;
-        let mut pos = pos;
-
-        for (h, _) in self.chamber.iter().rev().enumerate() {
-            if stream.jet_stream.len() > 0 {
-                let direction = stream.next();
-                if self.valid_move(rock, height - h, pos, direction) {
-                    match direction {
-                        Direction::Left => pos -= 1,
-                        Direction::Right => pos += 1,
-                        _ => {}
-                    }
-                };
-            }
-
-            let valid_move = self.valid_move(rock, height - h, pos, &Direction::Down);
-            if !valid_move {
-                // println!("Hit bottom");
-                self.insert_rock(&rock, pos, height - h - 1);
-                let top_12_rows = self.chamber.last_chunk::<12>();
-
-                match top_12_rows {
-                    None => return,
-                    Some(top_12_rows) => {
-                        let mem = Cache(stream.position, rock.clone(), top_12_rows.clone());
-
-                        if let Some((start_height, start_rocks_dropped)) = cache.get(&mem) {
-                            let rocks = self.rocks_dropped - start_rocks_dropped;
-                            let end_height = self.height;
-                            self.repeating_sequence = Some((end_height - *start_height, rocks));
-                        }
-
-                        cache.insert(mem, (self.height, self.rocks_dropped));
-                    }
-                }
-
-                return;
-            }
+    fn state(&self) -> State {
+        let height = self.height();
+        let profile = (0..self.config.width)
+            .map(|x| {
+                self.rows
+                    .iter()
+                    .rev()
+                    .position(|row| row[x] == ChamberPos::Rock)
+                    .unwrap_or(height)
+            })
+            .collect();
+        State {
+            shape: self.next_shape,
+            jet: self.next_jet,
+            profile,
         }
-        //insert rock at bottom
-        self.insert_rock(&rock, pos, 0);
     }
 
-    fn insert_rock(&mut self, rock: &Rock, pos: usize, height: usize) {
-        // println!("Inserting at height: {:?}, pos:  {:?}", height, pos);
-        match rock {
-            Rock::Horizontal => {
-                self.chamber[height][pos] = ChamberPos::Rock;
-                self.chamber[height][pos + 1] = ChamberPos::Rock;
-                self.chamber[height][pos + 2] = ChamberPos::Rock;
-                self.chamber[height][pos + 3] = ChamberPos::Rock;
-            }
-            Rock::Plus => {
-                self.chamber[height][pos + 1] = ChamberPos::Rock;
-                self.chamber[height + 1][pos] = ChamberPos::Rock;
-                self.chamber[height + 2][pos + 1] = ChamberPos::Rock;
-                self.chamber[height + 1][pos + 1] = ChamberPos::Rock;
-                self.chamber[height + 1][pos + 2] = ChamberPos::Rock;
-            }
-            Rock::ReverseL => {
-                self.chamber[height][pos] = ChamberPos::Rock;
-                self.chamber[height][pos + 1] = ChamberPos::Rock;
-                self.chamber[height][pos + 2] = ChamberPos::Rock;
-                self.chamber[height + 1][pos + 2] = ChamberPos::Rock;
-                self.chamber[height + 2][pos + 2] = ChamberPos::Rock;
-            }
-            Rock::Vertical => {
-                self.chamber[height][pos] = ChamberPos::Rock;
-                self.chamber[height + 1][pos] = ChamberPos::Rock;
-                self.chamber[height + 2][pos] = ChamberPos::Rock;
-                self.chamber[height + 3][pos] = ChamberPos::Rock;
-            }
-            Rock::Square => {
-                self.chamber[height][pos] = ChamberPos::Rock;
-                self.chamber[height][pos + 1] = ChamberPos::Rock;
-                self.chamber[height + 1][pos] = ChamberPos::Rock;
-                self.chamber[height + 1][pos + 1] = ChamberPos::Rock;
+    //Height of the tower after rocks rocks, skipping ahead once the surface
+    //profile repeats
+    fn height_after(&mut self, rocks: u64) -> u64 {
+        let mut heights = vec![self.height() as u64];
+        let mut seen: HashMap<State, u64> = HashMap::new();
+        let start = self.rocks_dropped;
+
+        while self.rocks_dropped - start < rocks {
+            let state = self.state();
+            if let Some(&first) = seen.get(&state) {
+                let dropped = self.rocks_dropped - start;
+                let cycle = dropped - first;
+                let growth = heights[dropped as usize] - heights[first as usize];
+                let remaining = rocks - first;
+                return heights[(first + remaining % cycle) as usize] + remaining / cycle * growth;
             }
+            seen.insert(state, self.rocks_dropped - start);
+            self.drop_rock();
+            heights.push(self.height() as u64);
         }
-
-        self.increase_height(height + rock.height() + 4);
-        self.rocks_dropped += 1;
-        // println!("{}", self);
+        self.height() as u64
     }
 }
 
 impl Display for Chamber {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut output = String::new();
-
-        for row in self.chamber.iter().rev() {
-            for col in row {
-                match col {
-                    ChamberPos::Air => output.push('.'),
-                    ChamberPos::Rock => output.push('#'),
-                }
-            }
-            output.push('\n');
+        for row in self.rows.iter().rev() {
+            let row = row
+                .iter()
+                .map(|col| match col {
+                    ChamberPos::Air => '.',
+                    ChamberPos::Rock => '#',
+                })
+                .collect::<String>();
+            writeln!(f, "|{}|", row)?;
         }
-        write!(f, "{}", output)
+        writeln!(f, "+{}+", "-".repeat(self.config.width))
     }
 }
 
-fn part1(input: &str) -> usize {
-    let mut jet_stream = JetStream::new(input);
-    let mut rocks = Rocks::new();
-
-    let mut chamber = Chamber::new(7, 4);
-
-    let mut cache = HashMap::new();
-    for _ in 0..2022 {
-        chamber.drop_rock(rocks.next(), 2, Some(&mut jet_stream), &mut cache);
-    }
-
-    chamber.height - 4
+fn tower_height(shapes: &str, jets: &str, config: Config, rocks: u64) -> Result<u64, ParseError> {
+    let mut chamber = Chamber::new(config, parse_shapes(shapes)?, parse_jets(jets)?)?;
+    Ok(chamber.height_after(rocks))
 }
 
-fn part2(input: &str) -> u64 {
-    let mut jet_stream = JetStream::new(input);
-    let mut rocks = Rocks::new();
-
-    let mut chamber = Chamber::new(7, 4);
-
-    let mut cache = HashMap::new();
-
-    let mut cycle = None;
-    for _ in 0..3000 {
-        chamber.drop_rock(rocks.next(), 2, Some(&mut jet_stream), &mut cache);
-
-        if let Some(sequence) = chamber.repeating_sequence {
-            println!("Cycle found.");
-            cycle = Some(sequence);
-            break;
-        }
-    }
-    let rocks_dropped = chamber.rocks_dropped;
-    let mut height = 0;
-
-    if let Some((d_height, d_rocks)) = cycle {
-        let num_cycles = (1000000000000 - rocks_dropped) / d_rocks;
-
-        height += (d_height) as u64 * num_cycles;
+//Tower heights for the shapes and chamber given to the runner
+pub fn part1_with(input: &str, shapes: &str, config: Config) -> Answer {
+    tower_height(shapes, input, config, 2022).into()
+}
 
-        chamber.rocks_dropped += d_rocks * num_cycles;
-        while chamber.rocks_dropped < 1000000000000 {
-            chamber.drop_rock(rocks.next(), 2, Some(&mut jet_stream), &mut cache);
-        }
-    }
+pub fn part2_with(input: &str, shapes: &str, config: Config) -> Answer {
+    tower_height(shapes, input, config, 1_000_000_000_000).into()
+}
 
-    chamber.height as u64 - 4 + height
+pub fn part1(input: &str) -> Answer {
+    part1_with(input, ROCKS, Config::default())
 }
 
-pub fn run() {
-    let mut input = include_str!("../input/17");
-    input = input.strip_suffix("\n").unwrap();
-    println!("Part 1: {}", part1(input));
-    println!("Part 2: {}", part2(input));
+pub fn part2(input: &str) -> Answer {
+    part2_with(input, ROCKS, Config::default())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn example() -> Chamber {
+        let shapes = parse_shapes(ROCKS).unwrap();
+        let jets = parse_jets(include_str!("../input/test17")).unwrap();
+        Chamber::new(Config::default(), shapes, jets).unwrap()
+    }
+
     #[test]
-    fn test_rocks() {
-        let mut rocks = Rocks::new();
-
-        assert_eq!(rocks.next(), &Rock::Horizontal);
-        assert_eq!(rocks.next(), &Rock::Plus);
-        assert_eq!(rocks.next(), &Rock::ReverseL);
-        assert_eq!(rocks.next(), &Rock::Vertical);
-        assert_eq!(rocks.next(), &Rock::Square);
-        assert_eq!(rocks.next(), &Rock::Horizontal);
+    fn test_shapes() {
+        let shapes = parse_shapes(ROCKS).unwrap();
+        assert_eq!(shapes.len(), 5);
+        let sizes = shapes
+            .iter()
+            .map(|s| (s.width, s.height, s.cells.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            vec![(4, 1, 4), (3, 3, 5), (3, 3, 5), (1, 4, 4), (2, 2, 4)]
+        );
+        assert!(shapes[2].cells.contains(&(2, 2)));
+        assert!(!shapes[2].cells.contains(&(0, 2)));
     }
 
     #[test]
     fn test_jetstream() {
-        let mut jet_stream = JetStream::new("<<>>");
-
-        assert_eq!(jet_stream.next(), &Direction::Left);
-        assert_eq!(jet_stream.next(), &Direction::Left);
-        assert_eq!(jet_stream.next(), &Direction::Right);
-        assert_eq!(jet_stream.next(), &Direction::Right);
-        assert_eq!(jet_stream.next(), &Direction::Left);
+        assert_eq!(
+            parse_jets("<<>\n"),
+            Ok(vec![Direction::Left, Direction::Left, Direction::Right])
+        );
+        assert_eq!(parse_jets("<x"), Err(ParseError::InvalidJet('x')));
+        assert_eq!(parse_jets("\n"), Err(ParseError::NoJets));
     }
 
     #[test]
     fn test_drop_rock() {
-        let mut chamber = Chamber::new(7, 12);
-        let mut cache = HashMap::new();
-        println!("{}", chamber);
-
-        println!("Drop rock: {:?}", Rock::Horizontal);
-        chamber.drop_rock(&Rock::Horizontal, 2, None, &mut cache);
-        println!("{}", chamber);
-
-        println!("Drop rock: {:?}", Rock::Plus);
-        chamber.drop_rock(&Rock::Plus, 2, None, &mut cache);
-        println!("{}", chamber);
-
-        assert_eq!(chamber.height, 12);
-
-        println!("Drop rock: {:?}", Rock::ReverseL);
-        chamber.drop_rock(&Rock::ReverseL, 2, None, &mut cache);
-        println!("{}", chamber);
-        assert_eq!(chamber.height, 12);
-
-        println!("Drop rock: {:?}", Rock::Vertical);
-        chamber.drop_rock(&Rock::Vertical, 2, None, &mut cache);
-        println!("{}", chamber);
-        assert_eq!(chamber.height, 13);
-
-        println!("Drop rock: {:?}", Rock::Square);
-        chamber.drop_rock(&Rock::Square, 2, None, &mut cache);
-        println!("{}", chamber);
-        assert_eq!(chamber.height, 15);
+        let mut chamber = example();
+        chamber.drop_rock();
+        assert_eq!(chamber.to_string(), "|..####.|\n+-------+\n");
+        chamber.drop_rock();
+        chamber.drop_rock();
+        assert_eq!(
+            chamber.to_string(),
+            "\
+|..#....|
+|..#....|
+|####...|
+|..###..|
+|...#...|
+|..####.|
++-------+
+"
+        );
+        assert_eq!(chamber.height(), 6);
+    }
+
+    #[test]
+    fn test_part1() {
+        let input = include_str!("../input/test17");
+        assert_eq!(part1(input), Answer::from(3068));
     }
+
     #[test]
-    fn test_drop_rock_2() {
-        let mut chamber = Chamber::new(7, 12);
-        let mut cache = HashMap::new();
-        println!("{}", chamber);
+    fn test_part2() {
+        let input = include_str!("../input/test17");
+        assert_eq!(part2(input), Answer::from(1514285714288u64));
+    }
 
-        println!("Drop rock: {:?}", Rock::Vertical);
-        chamber.drop_rock(&Rock::Vertical, 2, None, &mut cache);
-        println!("{}", chamber);
+    #[test]
+    fn test_cycle_matches_simulation() {
+        let mut skipped = example();
+        let mut full = example();
+        for _ in 0..5000 {
+            full.drop_rock();
+        }
+        assert_eq!(skipped.height_after(5000), full.height() as u64);
+    }
 
-        println!("Drop rock: {:?}", Rock::Plus);
-        chamber.drop_rock(&Rock::Plus, 0, None, &mut cache);
-        println!("{}", chamber);
+    #[test]
+    fn test_custom_shapes() {
+        //Single blocks in a one wide chamber simply stack up
+        let shapes = parse_shapes("#").unwrap();
+        let jets = parse_jets("<>").unwrap();
+        let config = Config {
+            width: 1,
+            gap: 5,
+            offset: 0,
+        };
+        let mut chamber = Chamber::new(config, shapes, jets).unwrap();
+        assert_eq!(chamber.height_after(10u64.pow(18)), 10u64.pow(18));
+
+        //Dominoes pushed right in a four wide chamber fill two per row
+        let shapes = parse_shapes("##\n\n").unwrap();
+        let jets = parse_jets(">").unwrap();
+        let config = Config {
+            width: 4,
+            gap: 1,
+            offset: 0,
+        };
+        let mut chamber = Chamber::new(config, shapes.clone(), jets.clone()).unwrap();
+        for _ in 0..3 {
+            chamber.drop_rock();
+        }
+        assert_eq!(chamber.to_string(), "|..##|\n|..##|\n|..##|\n+----+\n");
 
-        assert_eq!(chamber.height, 12);
+        let shapes = parse_shapes(".#.\n###\n\n#").unwrap();
+        assert_eq!(shapes[1].cells, vec![(0, 0)]);
+        assert_eq!(parse_shapes("..\n\n"), Err(ParseError::EmptyShape(0)));
+        assert_eq!(parse_shapes("#o"), Err(ParseError::InvalidShapeTile('o')));
     }
+
     #[test]
-    fn test_part1() {
-        let mut input = include_str!("../input/test17");
-        input = input.strip_suffix("\n").unwrap();
-        assert_eq!(part1(input), 3068);
+    fn test_config() {
+        assert_eq!("7,3,2".parse::<Config>(), Ok(Config::default()));
+        assert_eq!(
+            "7,3".parse::<Config>(),
+            Err(ParseError::InvalidConfig("7,3".to_string()))
+        );
+        let jets = include_str!("../input/test17");
+        assert_eq!(tower_height(ROCKS, jets, Config::default(), 2022), Ok(3068));
+        assert_eq!(
+            tower_height("#\n\n##", "<>", "1,0,0".parse().unwrap(), 5),
+            Err(ParseError::ShapeTooWide { shape: 1, width: 1 })
+        );
+        assert_eq!(
+            tower_height(ROCKS, jets, "7,3,4".parse().unwrap(), 5),
+            Err(ParseError::ShapeTooWide { shape: 0, width: 7 })
+        );
+        assert_eq!(tower_height("###", ">", "3,0,0".parse().unwrap(), 4), Ok(4));
     }
-    //
-    // #[test]
-    // fn test_part2() {
-    //     assert_eq!(part2("3"), 1222153);
-    // }
 }
@@ -1,190 +1,314 @@
-use std::collections::{HashMap, HashSet};
+use crate::answer::Answer;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Write},
+    str::FromStr,
+};
+
+type Voxel = (i32, i32, i32);
+
+//Directions to the six face neighbours, opposite faces are next to each other
+const FACES: [Voxel; 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+fn add(a: Voxel, b: Voxel) -> Voxel {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
 
-fn get_outer_limits(volume: &HashSet<(i32, i32, i32)>) -> ((i32, i32, i32), (i32, i32, i32)) {
-    let mut max = volume.iter().next().unwrap().clone();
-    let mut min = max;
+fn neighbours6(v: Voxel) -> impl Iterator<Item = Voxel> {
+    FACES.into_iter().map(move |d| add(v, d))
+}
 
-    for pos in volume.iter() {
-        if pos.0 > max.0 {
-            max.0 = pos.0
-        }
-        if pos.1 > max.1 {
-            max.1 = pos.1
-        }
-        if pos.2 > max.2 {
-            max.2 = pos.2
-        }
+//Every voxel sharing a face, an edge or a corner
+fn neighbours26(v: Voxel) -> impl Iterator<Item = Voxel> {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| (x, y, z))))
+        .filter(|&d| d != (0, 0, 0))
+        .map(move |d| add(v, d))
+}
 
-        if pos.0 < min.0 {
-            min.0 = pos.0
-        }
-        if pos.1 < min.1 {
-            min.1 = pos.1
-        }
-        if pos.2 < min.2 {
-            min.2 = pos.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Connectivity {
+    Faces,
+    Full,
+}
+
+impl Connectivity {
+    fn neighbours(self, v: Voxel) -> Box<dyn Iterator<Item = Voxel>> {
+        match self {
+            Connectivity::Faces => Box::new(neighbours6(v)),
+            Connectivity::Full => Box::new(neighbours26(v)),
         }
     }
-
-    //Increase limits by 1
-    min = (min.0 - 1, min.1 - 1, min.2 - 1);
-    max = (max.0 + 1, max.1 + 1, max.2 + 1);
-
-    (min, max)
 }
 
-fn is_in_bounds(pos: &(i32, i32, i32), limits: ((i32, i32, i32), (i32, i32, i32))) -> bool {
-    if pos.0 < limits.0 .0
-        || pos.0 > limits.1 .0
-        || pos.1 < limits.0 .1
-        || pos.1 > limits.1 .1
-        || pos.2 < limits.0 .2
-        || pos.2 > limits.1 .2
-    {
-        return false;
+#[derive(Debug, PartialEq, Eq)]
+struct ParseError(String);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid voxel: {}", self.0)
     }
-    return true;
 }
 
-fn get_surface_area(neighbors: &HashMap<(i32, i32, i32), Vec<usize>>) -> i32 {
-    neighbors
-        .values()
-        .map(|v| {
-            let s: usize = v.iter().sum::<usize>();
-            s
-        })
-        .sum::<usize>() as i32
+//One unit square of the surface, the side of voxel facing along normal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Face {
+    voxel: Voxel,
+    normal: Voxel,
 }
 
-fn get_surface(volume: &HashSet<(i32, i32, i32)>) -> HashMap<(i32, i32, i32), Vec<usize>> {
-    let mut store = HashMap::new();
-    let delta = vec![
-        (1, 0, 0),
-        (-1, 0, 0),
-        (0, 1, 0),
-        (0, -1, 0),
-        (0, 0, 1),
-        (0, 0, -1),
-    ];
-    for pos in volume {
-        let x = pos.0;
-        let y = pos.1;
-        let z = pos.2;
-
-        let mut neigbors = vec![1, 1, 1, 1, 1, 1];
-
-        for (i, d) in delta.iter().enumerate() {
-            let mut opposite_index = i + 1;
-
-            if i == 1 || i == 3 || i == 5 {
-                opposite_index = i - 1;
-            }
-
-            let neighbor = (x + d.0, y + d.1, z + d.2);
-
-            if store.contains_key(&neighbor) {
-                let v: &mut Vec<usize> = store.get_mut(&neighbor).unwrap();
-                v[opposite_index] = 0;
-
-                neigbors[i] = 0;
-            }
+impl Face {
+    //Corners counter-clockwise when looking at the face from outside
+    fn corners(&self) -> [Voxel; 4] {
+        let (x, y, z) = self.voxel;
+        let origin = [x, y, z];
+        let normal = [self.normal.0, self.normal.1, self.normal.2];
+        let axis = normal.iter().position(|&n| n != 0).unwrap();
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+        if normal[axis] < 0 {
+            corners.reverse();
         }
-        store.insert((x, y, z), neigbors);
+        corners.map(|(du, dv)| {
+            let mut corner = origin;
+            if normal[axis] > 0 {
+                corner[axis] += 1;
+            }
+            corner[u] += du;
+            corner[v] += dv;
+            (corner[0], corner[1], corner[2])
+        })
     }
-    store
 }
 
-fn part1(input: &str) -> i32 {
-    let mut volume = HashSet::new();
-
-    for line in input.lines() {
-        let pos: Vec<i32> = line.split(',').map(|t| t.parse().unwrap()).collect();
-
-        let x = pos[0];
-        let y = pos[1];
-        let z = pos[2];
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct VoxelGrid {
+    voxels: HashSet<Voxel>,
+}
 
-        volume.insert((x, y, z));
+impl VoxelGrid {
+    fn contains(&self, v: Voxel) -> bool {
+        self.voxels.contains(&v)
     }
 
-    get_surface_area(&get_surface(&volume))
-}
+    fn bounds(&self) -> Option<(Voxel, Voxel)> {
+        let first = *self.voxels.iter().next()?;
+        Some(self.voxels.iter().fold((first, first), |(min, max), &v| {
+            (
+                (min.0.min(v.0), min.1.min(v.1), min.2.min(v.2)),
+                (max.0.max(v.0), max.1.max(v.1), max.2.max(v.2)),
+            )
+        }))
+    }
 
-fn part2(input: &str) -> i32 {
-    let mut lava = HashSet::new();
+    fn sorted(&self) -> Vec<Voxel> {
+        let mut voxels = self.voxels.iter().copied().collect::<Vec<_>>();
+        voxels.sort_unstable();
+        voxels
+    }
 
-    for line in input.lines() {
-        let pos: Vec<i32> = line.split(',').map(|t| t.parse().unwrap()).collect();
+    //Flood fill from start over voxels accepted by open
+    fn flood<F>(start: Voxel, connectivity: Connectivity, open: F) -> HashSet<Voxel>
+    where
+        F: Fn(Voxel) -> bool,
+    {
+        let mut seen = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(v) = stack.pop() {
+            for next in connectivity.neighbours(v) {
+                if open(next) && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
 
-        let x = pos[0];
-        let y = pos[1];
-        let z = pos[2];
+    //Air reachable from outside, inside the bounds grown by one in every direction
+    fn exterior(&self) -> HashSet<Voxel> {
+        let Some((min, max)) = self.bounds() else {
+            return HashSet::new();
+        };
+        let (min, max) = (add(min, (-1, -1, -1)), add(max, (1, 1, 1)));
+        let in_bounds = |v: Voxel| {
+            (min.0..=max.0).contains(&v.0)
+                && (min.1..=max.1).contains(&v.1)
+                && (min.2..=max.2).contains(&v.2)
+        };
+        Self::flood(min, Connectivity::Faces, |v| {
+            in_bounds(v) && !self.contains(v)
+        })
+    }
 
-        lava.insert((x, y, z));
+    //Pockets of air that can not be reached from outside
+    fn cavities(&self) -> Vec<Vec<Voxel>> {
+        let Some((min, max)) = self.bounds() else {
+            return Vec::new();
+        };
+        let exterior = self.exterior();
+        let mut seen = HashSet::new();
+        let mut cavities = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    let v = (x, y, z);
+                    if self.contains(v) || exterior.contains(&v) || seen.contains(&v) {
+                        continue;
+                    }
+                    let cavity = Self::flood(v, Connectivity::Faces, |n| !self.contains(n));
+                    let mut cavity = cavity.into_iter().collect::<Vec<_>>();
+                    cavity.sort_unstable();
+                    seen.extend(cavity.iter().copied());
+                    cavities.push(cavity);
+                }
+            }
+        }
+        cavities
     }
 
-    let limits = get_outer_limits(&lava);
-    let lava_surface = get_surface(&lava);
+    //Connected pieces of the droplet, each sorted and ordered by their first voxel
+    fn components(&self, connectivity: Connectivity) -> Vec<Vec<Voxel>> {
+        let mut seen = HashSet::new();
+        let mut components = Vec::new();
+        for v in self.sorted() {
+            if seen.contains(&v) {
+                continue;
+            }
+            let component = Self::flood(v, connectivity, |n| self.contains(n));
+            let mut component = component.into_iter().collect::<Vec<_>>();
+            component.sort_unstable();
+            seen.extend(component.iter().copied());
+            components.push(component);
+        }
+        components
+    }
 
-    //Breadth first search to find all reachable air position from outside the lava
-    //droplet
-    //
-    let delta = vec![
-        (1, 0, 0),
-        (-1, 0, 0),
-        (0, 1, 0),
-        (0, -1, 0),
-        (0, 0, 1),
-        (0, 0, -1),
-    ];
+    fn faces(&self, exterior_only: bool) -> Vec<Face> {
+        let exterior = if exterior_only {
+            Some(self.exterior())
+        } else {
+            None
+        };
+        self.sorted()
+            .into_iter()
+            .flat_map(|voxel| FACES.map(|normal| Face { voxel, normal }))
+            .filter(|face| {
+                let outside = add(face.voxel, face.normal);
+                match &exterior {
+                    Some(exterior) => exterior.contains(&outside),
+                    None => !self.contains(outside),
+                }
+            })
+            .collect()
+    }
 
-    let mut air_outside = HashSet::new();
-    let mut stack = Vec::new();
+    fn surface_area(&self) -> usize {
+        self.faces(false).len()
+    }
 
-    // stack.push((limits.0 .0 - 1, limits.0 .1 - 1, limits.0 .2 - 1));
-    stack.push(limits.0);
+    fn exterior_surface_area(&self) -> usize {
+        self.faces(true).len()
+    }
 
-    while let Some(pos) = stack.pop() {
-        if lava.get(&pos).is_none() {
-            air_outside.insert(pos);
-        } else {
-            continue;
+    //Wavefront OBJ with shared vertices and one quad per face
+    fn to_obj(&self, exterior_only: bool) -> String {
+        let mut vertices: HashMap<Voxel, usize> = HashMap::new();
+        let mut vertex_lines = String::new();
+        let mut face_lines = String::new();
+        for face in self.faces(exterior_only) {
+            let indices = face.corners().map(|corner| {
+                let next = vertices.len() + 1;
+                *vertices.entry(corner).or_insert_with(|| {
+                    writeln!(vertex_lines, "v {} {} {}", corner.0, corner.1, corner.2).unwrap();
+                    next
+                })
+            });
+            writeln!(
+                face_lines,
+                "f {} {} {} {}",
+                indices[0], indices[1], indices[2], indices[3]
+            )
+            .unwrap();
         }
+        format!("o droplet\n{}{}", vertex_lines, face_lines)
+    }
 
-        for d in delta.clone() {
-            let next_pos = (pos.0 + d.0, pos.1 + d.1, pos.2 + d.2);
-
-            if is_in_bounds(&next_pos, limits) && air_outside.get(&next_pos).is_none() {
-                stack.push(next_pos);
+    //ASCII STL, every face split into two triangles
+    fn to_stl(&self, name: &str, exterior_only: bool) -> String {
+        let mut s = format!("solid {}\n", name);
+        for face in self.faces(exterior_only) {
+            let [a, b, c, d] = face.corners();
+            for triangle in [[a, b, c], [a, c, d]] {
+                let n = face.normal;
+                writeln!(s, "  facet normal {} {} {}", n.0, n.1, n.2).unwrap();
+                s.push_str("    outer loop\n");
+                for v in triangle {
+                    writeln!(s, "      vertex {} {} {}", v.0, v.1, v.2).unwrap();
+                }
+                s.push_str("    endloop\n  endfacet\n");
             }
         }
+        writeln!(s, "endsolid {}", name).unwrap();
+        s
     }
+}
 
-    //iterate over all lava position's air neighbors and check if the air pos is
-    //in the HashSet of reacable air from the outside
-    let mut outide_lava_surface = 0;
-    for (lava_pos, neighbors) in lava_surface.iter() {
-        for (i, neighbor) in neighbors.iter().enumerate() {
-            if *neighbor == 1 {
-                let neighbor_pos = (
-                    lava_pos.0 + delta[i].0,
-                    lava_pos.1 + delta[i].1,
-                    lava_pos.2 + delta[i].2,
-                );
-                if air_outside.get(&neighbor_pos).is_some() {
-                    outide_lava_surface += 1;
+impl FromStr for VoxelGrid {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let voxels = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let pos = line
+                    .split(',')
+                    .map(|t| t.trim().parse::<i32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| ParseError(line.to_string()))?;
+                match pos[..] {
+                    [x, y, z] => Ok((x, y, z)),
+                    _ => Err(ParseError(line.to_string())),
                 }
-            }
-        }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(VoxelGrid { voxels })
     }
+}
+
+pub fn part1(input: &str) -> Answer {
+    input
+        .parse::<VoxelGrid>()
+        .map(|grid| grid.surface_area())
+        .into()
+}
 
-    outide_lava_surface
+pub fn part2(input: &str) -> Answer {
+    input
+        .parse::<VoxelGrid>()
+        .map(|grid| grid.exterior_surface_area())
+        .into()
 }
 
-pub fn run() {
-    let input = include_str!("../input/18");
-    println!("Part1: {}", part1(input));
-    println!("Part2: {}", part2(input));
+pub fn export(input: &str, format: &str) -> Result<Option<String>, String> {
+    if format != "obj" && format != "stl" {
+        return Ok(None);
+    }
+    let grid = input.parse::<VoxelGrid>().map_err(|e| e.to_string())?;
+    Ok(Some(if format == "obj" {
+        grid.to_obj(true)
+    } else {
+        grid.to_stl("droplet", true)
+    }))
 }
 
 #[cfg(test)]
@@ -193,12 +317,104 @@ mod tests {
     #[test]
     fn test_part1() {
         let input = include_str!("../input/test18");
-        assert_eq!(part1(input), 64);
+        assert_eq!(part1(input), Answer::from(64));
     }
 
     #[test]
     fn test_part2() {
         let input = include_str!("../input/test18");
-        assert_eq!(part2(input), 58);
+        assert_eq!(part2(input), Answer::from(58));
+    }
+
+    #[test]
+    fn test_real() {
+        let input = include_str!("../input/18");
+        let grid: VoxelGrid = input.parse().unwrap();
+        let cavity_faces = grid
+            .cavities()
+            .iter()
+            .flatten()
+            .flat_map(|&v| neighbours6(v))
+            .filter(|&v| grid.contains(v))
+            .count();
+        assert_eq!(
+            grid.surface_area(),
+            grid.exterior_surface_area() + cavity_faces
+        );
+    }
+
+    #[test]
+    fn test_neighbours() {
+        assert_eq!(neighbours6((0, 0, 0)).count(), 6);
+        let all = neighbours26((1, 1, 1)).collect::<HashSet<_>>();
+        assert_eq!(all.len(), 26);
+        assert!(all.contains(&(0, 0, 0)) && all.contains(&(2, 2, 2)));
+        assert!(!all.contains(&(1, 1, 1)));
+    }
+
+    #[test]
+    fn test_cavities() {
+        let grid: VoxelGrid = include_str!("../input/test18").parse().unwrap();
+        assert_eq!(grid.cavities(), vec![vec![(2, 2, 5)]]);
+        assert_eq!(grid.exterior().len(), 5 * 5 * 8 - 13 - 1);
+    }
+
+    #[test]
+    fn test_components() {
+        let grid: VoxelGrid = "0,0,0\n1,1,1\n1,1,2\n5,5,5".parse().unwrap();
+        assert_eq!(
+            grid.components(Connectivity::Faces),
+            vec![vec![(0, 0, 0)], vec![(1, 1, 1), (1, 1, 2)], vec![(5, 5, 5)]]
+        );
+        assert_eq!(
+            grid.components(Connectivity::Full),
+            vec![vec![(0, 0, 0), (1, 1, 1), (1, 1, 2)], vec![(5, 5, 5)]]
+        );
+    }
+
+    #[test]
+    fn test_face_winding() {
+        //Right hand rule over the corners has to point along the normal
+        let grid: VoxelGrid = include_str!("../input/test18").parse().unwrap();
+        for face in grid.faces(false) {
+            let [a, b, c, _] = face.corners();
+            let (u, v) = (
+                (b.0 - a.0, b.1 - a.1, b.2 - a.2),
+                (c.0 - b.0, c.1 - b.1, c.2 - b.2),
+            );
+            let cross = (
+                u.1 * v.2 - u.2 * v.1,
+                u.2 * v.0 - u.0 * v.2,
+                u.0 * v.1 - u.1 * v.0,
+            );
+            assert_eq!(cross, face.normal);
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let grid: VoxelGrid = "1,1,1\n2,1,1".parse().unwrap();
+        let obj = grid.to_obj(false);
+        assert!(obj.starts_with("o droplet\n"));
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 12);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 10);
+
+        let stl = grid.to_stl("pair", false);
+        assert!(stl.starts_with("solid pair\n"));
+        assert!(stl.ends_with("endsolid pair\n"));
+        assert_eq!(stl.matches("facet normal").count(), 20);
+        assert_eq!(stl.matches("vertex").count(), 60);
+
+        let single: VoxelGrid = "0,0,0".parse().unwrap();
+        assert!(single.to_obj(true).contains("f 1 2 3 4\n"));
+        assert!(single.to_obj(true).contains("v 1 0 0\n"));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            "1,2".parse::<VoxelGrid>(),
+            Err(ParseError("1,2".to_string()))
+        );
     }
 }
@@ -1,7 +1,10 @@
+use crate::answer::Answer;
 use std::{
     ops::AddAssign,
-    ops::{Add, Sub},
+    ops::{Add, Mul, Sub},
     str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
 };
 
 struct Blueprint {
@@ -111,167 +114,379 @@ impl Sub<Inventory> for Inventory {
     }
 }
 
-impl Blueprint {
-    fn max_geode(
-        &self,
-        time: usize,
-        robots: Inventory,
-        inventory: Inventory,
-        after_empty: Option<Vec<usize>>,
-        current_max: usize,
-    ) -> usize {
-        if time == 1 {
-            return inventory.geode + robots.geode;
+impl Mul<usize> for Inventory {
+    type Output = Inventory;
+
+    fn mul(self, factor: usize) -> Inventory {
+        Inventory {
+            ore: self.ore * factor,
+            clay: self.clay * factor,
+            obsidian: self.obsidian * factor,
+            geode: self.geode * factor,
         }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Robot {
+    Ore,
+    Clay,
+    Obsidian,
+    Geode,
+}
 
-        if potential_material(time, inventory.geode, robots.geode) <= current_max {
-            return 0;
+impl Robot {
+    //Geode robots first so good solutions are found early and prune the rest
+    const BUILD_ORDER: [Robot; 4] = [Robot::Geode, Robot::Obsidian, Robot::Clay, Robot::Ore];
+
+    fn unit(&self) -> Inventory {
+        match self {
+            Robot::Ore => Inventory::new(1, 0, 0, 0),
+            Robot::Clay => Inventory::new(0, 1, 0, 0),
+            Robot::Obsidian => Inventory::new(0, 0, 1, 0),
+            Robot::Geode => Inventory::new(0, 0, 0, 1),
         }
+    }
 
-        if potential_material(time, inventory.obsidian, robots.obsidian) <= self.geode_cost.1 {
-            return inventory.geode + robots.geode * time;
+    fn name(&self) -> &str {
+        match self {
+            Robot::Ore => "ore-collecting",
+            Robot::Clay => "clay-collecting",
+            Robot::Obsidian => "obsidian-collecting",
+            Robot::Geode => "geode-cracking",
         }
+    }
+}
 
-        if inventory.ore >= self.geode_cost.0 && inventory.obsidian >= self.geode_cost.1 {
-            return self.max_geode(
-                time - 1,
-                robots + Inventory::new(0, 0, 0, 1),
-                inventory + robots - Inventory::new(self.geode_cost.0, 0, self.geode_cost.1, 0),
-                None,
-                current_max,
-            );
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SearchResult {
+    id: usize,
+    geodes: usize,
+    nodes: usize,
+    //Minute in which each robot is started, in build order
+    schedule: Vec<(usize, Robot)>,
+}
+
+struct Search<'a> {
+    blueprint: &'a Blueprint,
+    time: usize,
+    nodes: usize,
+    best: usize,
+    path: Vec<(usize, Robot)>,
+    best_path: Vec<(usize, Robot)>,
+}
+
+impl Search<'_> {
+    //Instead of stepping minute by minute, pick the next robot to build and
+    //skip ahead to the minute it becomes affordable
+    fn dfs(&mut self, time_left: usize, robots: Inventory, inventory: Inventory) {
+        self.nodes += 1;
+
+        let geodes = inventory.geode + robots.geode * time_left;
+        if geodes > self.best {
+            self.best = geodes;
+            self.best_path = self.path.clone();
         }
 
-        let mut geodes = current_max;
-
-        let after_empty = after_empty.unwrap_or(vec![0; 3]);
-
-        let mut could_build = vec![0; 3];
-        if inventory.ore >= self.obsidian_cost.0
-            && inventory.clay >= self.obsidian_cost.1
-            && robots.obsidian < self.geode_cost.1
-            && after_empty[0] == 0
-        {
-            geodes = geodes.max(self.max_geode(
-                time - 1,
-                robots + Inventory::new(0, 0, 1, 0),
-                inventory + robots
-                    - Inventory::new(self.obsidian_cost.0, self.obsidian_cost.1, 0, 0),
-                None,
-                geodes,
-            ));
-            could_build[0] = 1;
+        //Even building a geode robot every remaining minute can not beat the best
+        if geodes + time_left * time_left.saturating_sub(1) / 2 <= self.best {
+            return;
         }
 
-        if inventory.ore >= self.clay_cost
-            && robots.clay < self.max_clay_robots
-            && after_empty[2] == 0
-        {
-            geodes = geodes.max(self.max_geode(
-                time - 1,
-                robots + Inventory::new(0, 1, 0, 0),
-                inventory + robots - Inventory::new(self.clay_cost, 0, 0, 0),
-                None,
-                geodes,
-            ));
-            could_build[2] = 1;
+        for robot in Robot::BUILD_ORDER {
+            if !self
+                .blueprint
+                .is_useful(robot, &robots, &inventory, time_left)
+            {
+                continue;
+            }
+            let Some(wait) = self.blueprint.wait_for(robot, &robots, &inventory) else {
+                continue;
+            };
+            //The robot needs at least one minute left to collect anything
+            if wait + 1 >= time_left {
+                continue;
+            }
+
+            self.path.push((self.time - time_left + wait + 1, robot));
+            self.dfs(
+                time_left - wait - 1,
+                robots + robot.unit(),
+                inventory + robots * (wait + 1) - self.blueprint.cost(robot),
+            );
+            self.path.pop();
         }
+    }
+}
 
-        if inventory.ore >= self.ore_cost && robots.ore < self.max_ore_robots && after_empty[1] == 0
-        {
-            geodes = geodes.max(self.max_geode(
-                time - 1,
-                robots + Inventory::new(1, 0, 0, 0),
-                inventory + robots - Inventory::new(self.ore_cost, 0, 0, 0),
-                None,
-                geodes,
-            ));
-            could_build[1] = 1;
+impl Blueprint {
+    fn cost(&self, robot: Robot) -> Inventory {
+        match robot {
+            Robot::Ore => Inventory::new(self.ore_cost, 0, 0, 0),
+            Robot::Clay => Inventory::new(self.clay_cost, 0, 0, 0),
+            Robot::Obsidian => Inventory::new(self.obsidian_cost.0, self.obsidian_cost.1, 0, 0),
+            Robot::Geode => Inventory::new(self.geode_cost.0, 0, self.geode_cost.1, 0),
         }
+    }
 
-        geodes = geodes.max(self.max_geode(
-            time - 1,
-            robots,
-            inventory + robots,
-            Some(could_build),
-            geodes,
-        ));
-        return geodes;
+    //A robot is only worth building while the stock and production of its
+    //material can not already pay for the most expensive recipe every minute
+    fn is_useful(
+        &self,
+        robot: Robot,
+        robots: &Inventory,
+        inventory: &Inventory,
+        time_left: usize,
+    ) -> bool {
+        let (count, stock, max) = match robot {
+            Robot::Geode => return true,
+            Robot::Ore => (robots.ore, inventory.ore, self.max_ore_robots),
+            Robot::Clay => (robots.clay, inventory.clay, self.max_clay_robots),
+            Robot::Obsidian => (robots.obsidian, inventory.obsidian, self.geode_cost.1),
+        };
+        count < max && count * time_left + stock < max * time_left
+    }
+
+    //Minutes to wait until the robot is affordable, None if it never will be
+    fn wait_for(&self, robot: Robot, robots: &Inventory, inventory: &Inventory) -> Option<usize> {
+        let cost = self.cost(robot);
+        [
+            (cost.ore, inventory.ore, robots.ore),
+            (cost.clay, inventory.clay, robots.clay),
+            (cost.obsidian, inventory.obsidian, robots.obsidian),
+        ]
+        .iter()
+        .map(|&(cost, stock, rate)| match cost.saturating_sub(stock) {
+            0 => Some(0),
+            _ if rate == 0 => None,
+            missing => Some(missing.div_ceil(rate)),
+        })
+        .try_fold(0, |wait, w| w.map(|w| wait.max(w)))
     }
-}
 
-fn part1(input: &str) -> usize {
-    let mut blueprints = Vec::new();
+    fn max_geode(&self, time: usize) -> SearchResult {
+        let mut search = Search {
+            blueprint: self,
+            time,
+            nodes: 0,
+            best: 0,
+            path: Vec::new(),
+            best_path: Vec::new(),
+        };
+        search.dfs(time, Inventory::new(1, 0, 0, 0), Inventory::new(0, 0, 0, 0));
 
-    for line in input.lines() {
-        let blueprint = Blueprint::from_str(line).unwrap();
-        blueprints.push(blueprint);
+        SearchResult {
+            id: self.id,
+            geodes: search.best,
+            nodes: search.nodes,
+            schedule: search.best_path,
+        }
     }
 
-    let mut max_geodes = Vec::new();
-    let mut sum_quality = 0;
-
-    for blueprint in blueprints {
-        let start = std::time::Instant::now();
-        max_geodes.push(blueprint.max_geode(
-            24,
-            Inventory::new(1, 0, 0, 0),
-            Inventory::new(0, 0, 0, 0),
-            None,
-            0,
-        ));
-        let duration = start.elapsed();
-        println!(
-            "Geodes: id: {}, max: {}, time: {:?}",
-            blueprint.id,
-            max_geodes.last().unwrap(),
-            duration
-        );
-        sum_quality += blueprint.id * max_geodes.last().unwrap();
+    //Replay a build schedule and describe every minute like the puzzle does
+    fn schedule_log(&self, schedule: &[(usize, Robot)], time: usize) -> (usize, String) {
+        let mut log = String::new();
+        let mut robots = Inventory::new(1, 0, 0, 0);
+        let mut inventory = Inventory::new(0, 0, 0, 0);
+        let mut builds = schedule.iter().peekable();
+
+        for minute in 1..=time {
+            log.push_str(&format!("== Minute {} ==\n", minute));
+
+            let building = builds
+                .next_if(|(m, _)| *m == minute)
+                .map(|(_, robot)| *robot);
+            if let Some(robot) = building {
+                let cost = self.cost(robot);
+                inventory = inventory - cost;
+                let spent = [
+                    (cost.ore, "ore"),
+                    (cost.clay, "clay"),
+                    (cost.obsidian, "obsidian"),
+                ]
+                .iter()
+                .filter(|(amount, _)| *amount > 0)
+                .map(|(amount, material)| format!("{} {}", amount, material))
+                .collect::<Vec<_>>()
+                .join(" and ");
+                let article = if robot == Robot::Ore || robot == Robot::Obsidian {
+                    "an"
+                } else {
+                    "a"
+                };
+                log.push_str(&format!(
+                    "Spend {} to start building {} {} robot.\n",
+                    spent,
+                    article,
+                    robot.name()
+                ));
+            }
+
+            inventory += robots;
+            for (count, total, robot) in [
+                (robots.ore, inventory.ore, Robot::Ore),
+                (robots.clay, inventory.clay, Robot::Clay),
+                (robots.obsidian, inventory.obsidian, Robot::Obsidian),
+                (robots.geode, inventory.geode, Robot::Geode),
+            ] {
+                if count == 0 {
+                    continue;
+                }
+                let plural = if count == 1 { "" } else { "s" };
+                let line = match robot {
+                    Robot::Geode => format!(
+                        "{} geode-cracking robot{} crack{} {} geode{}; you now have {} open geode{}.",
+                        count,
+                        plural,
+                        if count == 1 { "s" } else { "" },
+                        count,
+                        plural,
+                        total,
+                        if total == 1 { "" } else { "s" }
+                    ),
+                    robot => {
+                        let material = robot.name().trim_end_matches("-collecting");
+                        format!(
+                            "{} {} robot{} collect{} {} {}; you now have {} {}.",
+                            count,
+                            robot.name(),
+                            plural,
+                            if count == 1 { "s" } else { "" },
+                            count,
+                            material,
+                            total,
+                            material
+                        )
+                    }
+                };
+                log.push_str(&line);
+                log.push('\n');
+            }
+
+            if let Some(robot) = building {
+                robots += robot.unit();
+                let count = match robot {
+                    Robot::Ore => robots.ore,
+                    Robot::Clay => robots.clay,
+                    Robot::Obsidian => robots.obsidian,
+                    Robot::Geode => robots.geode,
+                };
+                log.push_str(&format!(
+                    "The new {} robot is ready; you now have {} of them.\n",
+                    robot.name(),
+                    count
+                ));
+            }
+            log.push('\n');
+        }
+
+        (inventory.geode, log)
     }
+}
 
-    return sum_quality;
+//Hand out blueprints to a fixed number of worker threads, results keep the input order
+fn evaluate(blueprints: &[Blueprint], time: usize, threads: usize) -> Vec<SearchResult> {
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, SearchResult)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(blueprint) = blueprints.get(i) else {
+                            break;
+                        };
+                        done.push((i, blueprint.max_geode(time)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
 }
-fn potential_material(time_left: usize, material_inventory: usize, robots: usize) -> usize {
-    return material_inventory + time_left * robots + time_left * (time_left - 1) / 2;
+
+fn parse_input(input: &str) -> Vec<Blueprint> {
+    input
+        .lines()
+        .map(|line| Blueprint::from_str(line).unwrap())
+        .collect()
 }
 
-fn part2(input: &str) -> usize {
-    let mut blueprints = Vec::new();
+fn quality_level_sum(input: &str, threads: usize) -> usize {
+    let blueprints = parse_input(input);
+    let results = evaluate(&blueprints, 24, threads);
+    results.iter().map(|r| r.id * r.geodes).sum()
+}
 
-    let mut lines = input.lines().take(3);
+fn geode_product(input: &str, threads: usize) -> usize {
+    let blueprints: Vec<Blueprint> = parse_input(input).into_iter().take(3).collect();
+    let results = evaluate(&blueprints, 32, threads);
+    results.iter().map(|r| r.geodes).product()
+}
 
-    for line in lines.by_ref() {
-        let blueprint = Blueprint::from_str(line).unwrap();
+//Answers with the search split over the runner's worker threads
+pub fn part1_with(input: &str, threads: usize) -> Answer {
+    quality_level_sum(input, threads).into()
+}
 
-        blueprints.push(blueprint);
-    }
-    let mut max_geodes = Vec::new();
-    for blueprint in blueprints {
-        //time function
-        let start = std::time::Instant::now();
-        max_geodes.push(blueprint.max_geode(
-            32,
-            Inventory::new(1, 0, 0, 0),
-            Inventory::new(0, 0, 0, 0),
-            None,
-            0,
-        ));
-        let duration = start.elapsed();
-        println!(
-            "Geodes: id: {}, max: {}, time: {:?}",
-            blueprint.id,
-            max_geodes.last().unwrap(),
-            duration
-        );
-    }
-    max_geodes.iter().fold(1, |acc, x| acc * x)
+pub fn part2_with(input: &str, threads: usize) -> Answer {
+    geode_product(input, threads).into()
+}
+
+pub fn part1(input: &str) -> Answer {
+    part1_with(input, 1)
+}
+
+pub fn part2(input: &str) -> Answer {
+    part2_with(input, 1)
+}
+
+fn search_report(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .map(|r| {
+            format!(
+                "Blueprint {}: {} geodes, {} nodes visited",
+                r.id, r.geodes, r.nodes
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-pub fn run() {
-    let input = include_str!("../input/19");
-    println!("Part1: {}", part1(input));
-    println!("Part2: {}", part2(input));
+pub fn export(input: &str, format: &str, threads: usize) -> Result<Option<String>, String> {
+    if format != "search" && format != "schedule" {
+        return Ok(None);
+    }
+    let blueprints = parse_input(input);
+    let results = evaluate(&blueprints, 24, threads);
+    if format == "search" {
+        return Ok(Some(search_report(&results)));
+    }
+    let best = results
+        .iter()
+        .max_by_key(|r| r.id * r.geodes)
+        .ok_or("no blueprints")?;
+    let blueprint = blueprints
+        .iter()
+        .find(|b| b.id == best.id)
+        .ok_or("no blueprints")?;
+    Ok(Some(format!(
+        "Best blueprint {} ({} nodes visited):\n{}",
+        best.id,
+        best.nodes,
+        blueprint.schedule_log(&best.schedule, 24).1
+    )))
 }
 
 #[cfg(test)]
@@ -301,4 +516,49 @@ mod tests {
         assert_eq!(blueprints[1].obsidian_cost, (3, 8));
         assert_eq!(blueprints[1].geode_cost, (3, 12));
     }
+
+    #[test]
+    fn test_max_geode() {
+        let blueprints = parse_input(include_str!("../input/test19"));
+
+        let results = evaluate(&blueprints, 24, 2);
+        assert_eq!(results[0].geodes, 9);
+        assert_eq!(results[1].geodes, 12);
+        assert!(results.iter().all(|r| r.nodes > 0));
+
+        //Each blueprint is searched by one thread, so the counts don't depend on the thread count
+        let single = evaluate(&blueprints, 24, 1);
+        let report = search_report(&results);
+        assert_eq!(report, search_report(&single));
+        assert_eq!(
+            report.lines().next().unwrap(),
+            format!("Blueprint 1: 9 geodes, {} nodes visited", results[0].nodes)
+        );
+
+        assert_eq!(quality_level_sum(include_str!("../input/test19"), 1), 33);
+    }
+
+    #[test]
+    fn test_max_geode_32() {
+        let blueprints = parse_input(include_str!("../input/test19"));
+
+        let results = evaluate(&blueprints, 32, 4);
+        assert_eq!(results[0].geodes, 56);
+        assert_eq!(results[1].geodes, 62);
+    }
+
+    #[test]
+    fn test_schedule_log() {
+        let blueprints = parse_input(include_str!("../input/test19"));
+        let result = blueprints[0].max_geode(24);
+
+        let (geodes, log) = blueprints[0].schedule_log(&result.schedule, 24);
+        assert_eq!(geodes, 9);
+        assert_eq!(log.matches("== Minute").count(), 24);
+        assert!(log.contains(
+            "== Minute 1 ==\n1 ore-collecting robot collects 1 ore; you now have 1 ore.\n"
+        ));
+        assert!(log.contains("to start building a geode-cracking robot."));
+        assert!(log.ends_with("you now have 9 open geodes.\n\n"));
+    }
 }
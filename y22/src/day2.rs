@@ -1,128 +1,476 @@
-#[derive(Debug)]
-enum RockPaperScissors {
-    Rock,
-    Paper,
-    Scissors,
+use crate::answer::Answer;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+#[derive(Debug, PartialEq, Eq)]
+enum GameError {
+    InvalidLine(String),
+    UnknownMove(String),
+    DuplicateMove(String),
+    //Exactly one of every pair of different moves has to beat the other
+    NotATournament(String, String),
+    BeatsItself(String),
+    //Every move needs a move that beats it and one it beats
+    BeatsNothing(String),
+    BeatenByNothing(String),
+    MissingOutcome(Outcome),
+    UnknownSymbol(String),
+    MissingColumn(String),
 }
 
-enum Result {
+impl Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameError::InvalidLine(line) => write!(f, "invalid line: {}", line),
+            GameError::UnknownMove(name) => write!(f, "unknown move: {}", name),
+            GameError::DuplicateMove(name) => write!(f, "move defined twice: {}", name),
+            GameError::NotATournament(a, b) => {
+                write!(f, "exactly one of {} and {} has to win", a, b)
+            }
+            GameError::BeatsItself(name) => write!(f, "{} beats itself", name),
+            GameError::BeatsNothing(name) => write!(f, "{} beats no other move", name),
+            GameError::BeatenByNothing(name) => write!(f, "no move beats {}", name),
+            GameError::MissingOutcome(outcome) => write!(f, "no score for {:?}", outcome),
+            GameError::UnknownSymbol(symbol) => write!(f, "unknown symbol: {}", symbol),
+            GameError::MissingColumn(line) => write!(f, "missing column: {}", line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Outcome {
     Win,
-    Lose,
     Draw,
+    Lose,
 }
 
-impl RockPaperScissors {
-    fn beats(&self, other: &RockPaperScissors) -> bool {
-        match self {
-            RockPaperScissors::Rock => match other {
-                RockPaperScissors::Rock => false,
-                RockPaperScissors::Paper => false,
-                RockPaperScissors::Scissors => true,
-            },
-            RockPaperScissors::Paper => match other {
-                RockPaperScissors::Rock => true,
-                RockPaperScissors::Paper => false,
-                RockPaperScissors::Scissors => false,
-            },
-            RockPaperScissors::Scissors => match other {
-                RockPaperScissors::Rock => false,
-                RockPaperScissors::Paper => true,
-                RockPaperScissors::Scissors => false,
-            },
-        }
+const ROCK_PAPER_SCISSORS: &str = "\
+moves: Rock 1, Paper 2, Scissors 3
+beats: Rock > Scissors, Paper > Rock, Scissors > Paper
+outcomes: win 6, draw 3, lose 0";
+
+const ROCK_PAPER_SCISSORS_LIZARD_SPOCK: &str = "\
+moves: Rock 1, Paper 2, Scissors 3, Lizard 4, Spock 5
+beats: Scissors > Paper, Paper > Rock, Rock > Lizard, Lizard > Spock, Spock > Scissors
+beats: Scissors > Lizard, Lizard > Paper, Paper > Spock, Spock > Rock, Rock > Scissors
+outcomes: win 6, draw 3, lose 0";
+
+//A game defined by its moves, who beats whom and what every result is worth
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Game {
+    moves: Vec<(String, u32)>,
+    beats: Vec<Vec<bool>>,
+    outcome_scores: HashMap<Outcome, u32>,
+}
+
+impl Game {
+    fn move_index(&self, name: &str) -> Result<usize, GameError> {
+        self.moves
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(|| GameError::UnknownMove(name.to_string()))
     }
-    fn score(self, other: &RockPaperScissors) -> u32 {
-        if self.beats(other) {
-            self.self_score() + 6u32
-        } else if other.beats(&self) {
-            self.self_score()
+
+    fn name(&self, m: usize) -> &str {
+        &self.moves[m].0
+    }
+
+    fn outcome(&self, me: usize, opponent: usize) -> Outcome {
+        if self.beats[me][opponent] {
+            Outcome::Win
+        } else if self.beats[opponent][me] {
+            Outcome::Lose
         } else {
-            self.self_score() + 3u32
+            Outcome::Draw
         }
     }
 
-    fn self_score(self) -> u32 {
-        self.into()
-    }
-
-    fn from_strategy(opponent: &RockPaperScissors, wanted_result: &Result) -> RockPaperScissors {
-        match wanted_result {
-            Result::Win => match opponent {
-                RockPaperScissors::Rock => RockPaperScissors::Paper,
-                RockPaperScissors::Paper => RockPaperScissors::Scissors,
-                RockPaperScissors::Scissors => RockPaperScissors::Rock,
-            },
-            Result::Lose => match opponent {
-                RockPaperScissors::Rock => RockPaperScissors::Scissors,
-                RockPaperScissors::Paper => RockPaperScissors::Rock,
-                RockPaperScissors::Scissors => RockPaperScissors::Paper,
-            },
-            Result::Draw => match opponent {
-                RockPaperScissors::Rock => RockPaperScissors::Rock,
-                RockPaperScissors::Paper => RockPaperScissors::Paper,
-                RockPaperScissors::Scissors => RockPaperScissors::Scissors,
-            },
+    //The move giving the wanted outcome, the highest scoring one if there are several.
+    //Validated games have one for every outcome: the opponent's own move draws
+    //and every move beats something and is beaten by something.
+    fn response(&self, opponent: usize, wanted: Outcome) -> usize {
+        (0..self.moves.len())
+            .filter(|&me| self.outcome(me, opponent) == wanted)
+            .max_by_key(|&me| (self.moves[me].1, std::cmp::Reverse(me)))
+            .expect("validated games can reach every outcome")
+    }
+
+    fn validate(&self) -> Result<(), GameError> {
+        for a in 0..self.moves.len() {
+            if self.beats[a][a] {
+                return Err(GameError::BeatsItself(self.name(a).to_string()));
+            }
+            for b in a + 1..self.moves.len() {
+                if self.beats[a][b] == self.beats[b][a] {
+                    return Err(GameError::NotATournament(
+                        self.name(a).to_string(),
+                        self.name(b).to_string(),
+                    ));
+                }
+            }
+        }
+        for m in 0..self.moves.len() {
+            if !self.beats[m].contains(&true) {
+                return Err(GameError::BeatsNothing(self.name(m).to_string()));
+            }
+            if !self.beats.iter().any(|row| row[m]) {
+                return Err(GameError::BeatenByNothing(self.name(m).to_string()));
+            }
         }
+        Ok(())
     }
 }
 
-impl Into<u32> for RockPaperScissors {
-    fn into(self) -> u32 {
-        match self {
-            RockPaperScissors::Rock => 1,
-            RockPaperScissors::Paper => 2,
-            RockPaperScissors::Scissors => 3,
+impl FromStr for Game {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut game = Game {
+            moves: Vec::new(),
+            beats: Vec::new(),
+            outcome_scores: HashMap::new(),
+        };
+        let mut pairs = Vec::new();
+
+        for line in s.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let invalid = || GameError::InvalidLine(line.to_string());
+            let (key, items) = line.split_once(':').ok_or_else(invalid)?;
+            let items = items.split(',').map(str::trim).filter(|i| !i.is_empty());
+            match key.trim() {
+                "moves" => {
+                    for item in items {
+                        let (name, score) = item.rsplit_once(' ').ok_or_else(invalid)?;
+                        let score = score.parse().map_err(|_| invalid())?;
+                        if game.moves.iter().any(|(n, _)| n == name) {
+                            return Err(GameError::DuplicateMove(name.to_string()));
+                        }
+                        game.moves.push((name.to_string(), score));
+                    }
+                }
+                "beats" => {
+                    for item in items {
+                        let (winner, loser) = item.split_once('>').ok_or_else(invalid)?;
+                        pairs.push((winner.trim().to_string(), loser.trim().to_string()));
+                    }
+                }
+                "outcomes" => {
+                    for item in items {
+                        let (outcome, score) = item.split_once(' ').ok_or_else(invalid)?;
+                        let outcome = match outcome {
+                            "win" => Outcome::Win,
+                            "draw" => Outcome::Draw,
+                            "lose" => Outcome::Lose,
+                            _ => return Err(invalid()),
+                        };
+                        let score = score.trim().parse().map_err(|_| invalid())?;
+                        game.outcome_scores.insert(outcome, score);
+                    }
+                }
+                _ => return Err(invalid()),
+            }
         }
+
+        game.beats = vec![vec![false; game.moves.len()]; game.moves.len()];
+        for (winner, loser) in pairs {
+            let (winner, loser) = (game.move_index(&winner)?, game.move_index(&loser)?);
+            game.beats[winner][loser] = true;
+        }
+        game.validate()?;
+        Ok(game)
     }
 }
 
-impl Into<RockPaperScissors> for char {
-    fn into(self) -> RockPaperScissors {
-        match self {
-            'A' => RockPaperScissors::Rock,
-            'B' => RockPaperScissors::Paper,
-            'C' => RockPaperScissors::Scissors,
-            _ => panic!("Invalid input"),
-        }
+//What the second column of a strategy guide means
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Response {
+    Move(HashMap<String, usize>),
+    Outcome(HashMap<String, Outcome>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ColumnMapping {
+    opponent: HashMap<String, usize>,
+    response: Response,
+}
+
+impl ColumnMapping {
+    fn moves(game: &Game, symbols: &[(&str, &str)]) -> Result<HashMap<String, usize>, GameError> {
+        symbols
+            .iter()
+            .map(|&(symbol, name)| Ok((symbol.to_string(), game.move_index(name)?)))
+            .collect()
+    }
+
+    //Both columns name moves
+    fn by_move(
+        game: &Game,
+        opponent: &[(&str, &str)],
+        me: &[(&str, &str)],
+    ) -> Result<Self, GameError> {
+        Ok(ColumnMapping {
+            opponent: Self::moves(game, opponent)?,
+            response: Response::Move(Self::moves(game, me)?),
+        })
+    }
+
+    //The second column names the outcome to aim for
+    fn by_outcome(
+        game: &Game,
+        opponent: &[(&str, &str)],
+        outcomes: &[(&str, Outcome)],
+    ) -> Result<Self, GameError> {
+        Ok(ColumnMapping {
+            opponent: Self::moves(game, opponent)?,
+            response: Response::Outcome(
+                outcomes
+                    .iter()
+                    .map(|&(symbol, outcome)| (symbol.to_string(), outcome))
+                    .collect(),
+            ),
+        })
     }
 }
 
-impl Into<Result> for char {
-    fn into(self) -> Result {
-        match self {
-            'X' => Result::Lose,
-            'Y' => Result::Draw,
-            'Z' => Result::Win,
-            _ => panic!("Invalid input"),
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Score {
+    shape: u32,
+    outcome: u32,
+}
+
+impl Score {
+    fn total(&self) -> u32 {
+        self.shape + self.outcome
     }
 }
 
-pub fn run() {
-    println!("Hello, day 2");
-    let file = std::fs::read_to_string("input/2").unwrap();
-    let lines: Vec<&str> = file.split("\n").collect();
+impl std::ops::Add for Score {
+    type Output = Score;
 
-    let mut total_score = 0;
-    let mut opponent: RockPaperScissors;
-    let mut wanted_result: Result;
-    let mut me: RockPaperScissors;
-    for line in lines {
-        if line.len() == 0 {
-            continue;
+    fn add(self, other: Score) -> Score {
+        Score {
+            shape: self.shape + other.shape,
+            outcome: self.outcome + other.outcome,
         }
-        //parse the line with two chars
-        let chars: Vec<char> = line.chars().collect();
-        println!("Chars: {:?}", chars);
-        opponent = chars[0].into();
-        wanted_result = chars[2].into();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Round {
+    opponent: String,
+    me: String,
+    outcome: Outcome,
+    score: Score,
+    //Score of this and all earlier rounds
+    cumulative: Score,
+}
+
+impl Display for Round {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} vs {}: {:?}, {} + {} = {} (total {})",
+            self.me,
+            self.opponent,
+            self.outcome,
+            self.score.shape,
+            self.score.outcome,
+            self.score.total(),
+            self.cumulative.total()
+        )
+    }
+}
+
+fn play(game: &Game, mapping: &ColumnMapping, guide: &str) -> Result<Vec<Round>, GameError> {
+    let mut cumulative = Score::default();
+    let mut rounds = Vec::new();
+    for line in guide.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let mut columns = line.split_whitespace();
+        let mut next = || {
+            columns
+                .next()
+                .ok_or_else(|| GameError::MissingColumn(line.to_string()))
+        };
+        let (first, second) = (next()?, next()?);
+        let unknown = |symbol: &str| GameError::UnknownSymbol(symbol.to_string());
+
+        let opponent = *mapping.opponent.get(first).ok_or_else(|| unknown(first))?;
+        let me = match &mapping.response {
+            Response::Move(moves) => *moves.get(second).ok_or_else(|| unknown(second))?,
+            Response::Outcome(outcomes) => {
+                let wanted = *outcomes.get(second).ok_or_else(|| unknown(second))?;
+                game.response(opponent, wanted)
+            }
+        };
+
+        let outcome = game.outcome(me, opponent);
+        let score = Score {
+            shape: game.moves[me].1,
+            outcome: *game
+                .outcome_scores
+                .get(&outcome)
+                .ok_or(GameError::MissingOutcome(outcome))?,
+        };
+        cumulative = cumulative + score;
+        rounds.push(Round {
+            opponent: game.name(opponent).to_string(),
+            me: game.name(me).to_string(),
+            outcome,
+            score,
+            cumulative,
+        });
+    }
+    Ok(rounds)
+}
+
+const OPPONENT: [(&str, &str); 3] = [("A", "Rock"), ("B", "Paper"), ("C", "Scissors")];
+const ME: [(&str, &str); 3] = [("X", "Rock"), ("Y", "Paper"), ("Z", "Scissors")];
+const OUTCOMES: [(&str, Outcome); 3] = [
+    ("X", Outcome::Lose),
+    ("Y", Outcome::Draw),
+    ("Z", Outcome::Win),
+];
+
+fn total_score(game: &Game, mapping: &ColumnMapping, guide: &str) -> Result<u32, GameError> {
+    let rounds = play(game, mapping, guide)?;
+    Ok(rounds.last().map_or(0, |round| round.cumulative.total()))
+}
 
-        me = RockPaperScissors::from_strategy(&opponent, &wanted_result);
-        println!("Opponent: {:?}, Me: {:?}", opponent, me);
+pub fn part1(input: &str) -> Answer {
+    ROCK_PAPER_SCISSORS
+        .parse()
+        .and_then(|game: Game| {
+            let mapping = ColumnMapping::by_move(&game, &OPPONENT, &ME)?;
+            total_score(&game, &mapping, input)
+        })
+        .into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    ROCK_PAPER_SCISSORS
+        .parse()
+        .and_then(|game: Game| {
+            let mapping = ColumnMapping::by_outcome(&game, &OPPONENT, &OUTCOMES)?;
+            total_score(&game, &mapping, input)
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "A Y\nB X\nC Z\n";
+
+    #[test]
+    fn test_example() {
+        assert_eq!(part1(EXAMPLE), Answer::from(15));
+        assert_eq!(part2(EXAMPLE), Answer::from(12));
+    }
+
+    #[test]
+    fn test_breakdown() {
+        let game: Game = ROCK_PAPER_SCISSORS.parse().unwrap();
+        let mapping = ColumnMapping::by_outcome(&game, &OPPONENT, &OUTCOMES).unwrap();
+        let rounds = play(&game, &mapping, EXAMPLE).unwrap();
+
+        let lines = rounds.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "Rock vs Rock: Draw, 1 + 3 = 4 (total 4)",
+                "Rock vs Paper: Lose, 1 + 0 = 1 (total 5)",
+                "Rock vs Scissors: Win, 1 + 6 = 7 (total 12)",
+            ]
+        );
+        assert_eq!(
+            rounds[2].cumulative,
+            Score {
+                shape: 3,
+                outcome: 9
+            }
+        );
+    }
+
+    #[test]
+    fn test_lizard_spock() {
+        let game: Game = ROCK_PAPER_SCISSORS_LIZARD_SPOCK.parse().unwrap();
+        let spock = game.move_index("Spock").unwrap();
+        let lizard = game.move_index("Lizard").unwrap();
+        let rock = game.move_index("Rock").unwrap();
+        assert_eq!(game.outcome(lizard, spock), Outcome::Win);
+        assert_eq!(game.outcome(spock, rock), Outcome::Win);
+        assert_eq!(game.outcome(rock, spock), Outcome::Lose);
+
+        //Paper and Spock both beat Rock, Spock scores more
+        assert_eq!(game.response(rock, Outcome::Win), spock);
+        assert_eq!(game.response(rock, Outcome::Draw), rock);
+
+        let opponent = [("A", "Rock"), ("B", "Spock")];
+        let outcomes = [("W", Outcome::Win), ("L", Outcome::Lose)];
+        let mapping = ColumnMapping::by_outcome(&game, &opponent, &outcomes).unwrap();
+        let rounds = play(&game, &mapping, "A W\nB L\nB W").unwrap();
+        let me = rounds.iter().map(|r| r.me.as_str()).collect::<Vec<_>>();
+        assert_eq!(me, vec!["Spock", "Scissors", "Lizard"]);
+        assert_eq!(rounds[2].cumulative.total(), 11 + 3 + 10);
+    }
+
+    #[test]
+    fn test_invalid_games() {
+        let missing = "moves: Rock 1, Paper 2, Scissors 3\nbeats: Rock > Scissors, Paper > Rock";
+        assert_eq!(
+            missing.parse::<Game>(),
+            Err(GameError::NotATournament(
+                "Paper".to_string(),
+                "Scissors".to_string()
+            ))
+        );
+        let both = "moves: A 1, B 2\nbeats: A > B, B > A";
+        assert_eq!(
+            both.parse::<Game>(),
+            Err(GameError::NotATournament("A".to_string(), "B".to_string()))
+        );
+        let unknown = "moves: A 1, B 2\nbeats: A > C";
+        assert_eq!(
+            unknown.parse::<Game>(),
+            Err(GameError::UnknownMove("C".to_string()))
+        );
+        assert_eq!(
+            "moves: A 1, A 2".parse::<Game>(),
+            Err(GameError::DuplicateMove("A".to_string()))
+        );
+        assert_eq!(
+            "moves: A 1\nbeats: A > A".parse::<Game>(),
+            Err(GameError::BeatsItself("A".to_string()))
+        );
+        //Every pair has a winner, but nothing beats A and C beats nothing
+        let transitive = "moves: A 1, B 2, C 3\nbeats: A > B, A > C, B > C";
+        assert_eq!(
+            transitive.parse::<Game>(),
+            Err(GameError::BeatenByNothing("A".to_string()))
+        );
+        assert_eq!(
+            "moves: A 1".parse::<Game>(),
+            Err(GameError::BeatsNothing("A".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_invalid_guide() {
+        assert_eq!(part1("A Q").to_string(), "unsolved (unknown symbol: Q)");
+        assert_eq!(part2("A").to_string(), "unsolved (missing column: A)");
+    }
 
-        let score = me.score(&opponent);
-        println!("Score: {}", score);
-        total_score += score;
+    #[test]
+    fn test_missing_outcome_score() {
+        let game: Game = "moves: Rock 1, Paper 2, Scissors 3
+beats: Rock > Scissors, Paper > Rock, Scissors > Paper
+outcomes: win 6, lose 0"
+            .parse()
+            .unwrap();
+        let mapping = ColumnMapping::by_move(&game, &OPPONENT, &ME).unwrap();
+        assert_eq!(total_score(&game, &mapping, "A Z"), Ok(3));
+        assert_eq!(
+            total_score(&game, &mapping, "A Z\nB Y"),
+            Err(GameError::MissingOutcome(Outcome::Draw))
+        );
     }
-    println!("Total score: {}", total_score);
 }
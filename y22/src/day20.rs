@@ -1,6 +1,7 @@
-use std::{collections::LinkedList, fmt::Display, str::FromStr};
+use crate::answer::Answer;
+use std::{fmt::Display, str::FromStr};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Item {
     index: usize,
     value: i64,
@@ -12,68 +13,153 @@ impl Item {
     }
 }
 
+//Square root decomposition of the list. Every chunk holds the original
+//indices of its items in list order, and each item remembers its chunk, so
+//lookups by position or by original index only touch O(sqrt n) entries
 #[derive(Debug, Clone)]
 struct ItemList {
-    items: LinkedList<Item>,
+    values: Vec<i64>,
+    chunks: Vec<Vec<usize>>,
+    chunk_of: Vec<usize>,
+    chunk_size: usize,
 }
 
 impl ItemList {
-    fn new() -> ItemList {
-        ItemList {
-            items: LinkedList::new(),
+    fn new(values: Vec<i64>) -> ItemList {
+        let chunk_size = ((values.len() as f64).sqrt() as usize).max(1);
+        let mut list = ItemList {
+            chunks: vec![(0..values.len()).collect()],
+            chunk_of: vec![0; values.len()],
+            values,
+            chunk_size,
+        };
+        list.rebuild();
+        list
+    }
+
+    fn rebuild(&mut self) {
+        let order: Vec<usize> = self.chunks.iter().flatten().copied().collect();
+        self.chunks = order
+            .chunks(self.chunk_size)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        for (c, chunk) in self.chunks.iter().enumerate() {
+            for &index in chunk {
+                self.chunk_of[index] = c;
+            }
         }
     }
 
-    fn add(&mut self, index: usize, value: i64) {
-        self.items.push_back(Item::new(index, value));
+    fn len(&self) -> usize {
+        self.values.len()
     }
 
-    fn insert_at(&mut self, index: usize, item: Item) {
-        let mut split = self.items.split_off(index);
-        self.items.push_back(item);
+    fn apply_key(&mut self, key: i64) {
+        for value in self.values.iter_mut() {
+            *value *= key;
+        }
+    }
 
-        self.items.append(&mut split);
+    fn iter(&self) -> impl Iterator<Item = Item> + '_ {
+        self.chunks
+            .iter()
+            .flatten()
+            .map(|&index| Item::new(index, self.values[index]))
     }
 
-    fn len(&self) -> usize {
-        self.items.len()
+    //Chunk and offset inside it for a position in the list
+    fn locate(&self, position: usize) -> (usize, usize) {
+        let mut position = position;
+        for (c, chunk) in self.chunks.iter().enumerate() {
+            if position < chunk.len() {
+                return (c, position);
+            }
+            position -= chunk.len();
+        }
+        (self.chunks.len() - 1, self.chunks.last().unwrap().len())
     }
 
-    fn at(&self, index: usize) -> Option<&Item> {
-        let new_index = index % (self.len());
-        self.items.iter().nth(new_index.try_into().unwrap())
+    fn at(&self, position: usize) -> Option<Item> {
+        if self.len() == 0 {
+            return None;
+        }
+        let (c, offset) = self.locate(position % self.len());
+        let index = self.chunks[c][offset];
+        Some(Item::new(index, self.values[index]))
     }
 
-    fn find(&self, item: &Item) -> Option<usize> {
-        self.items.iter().position(|i| i == item)
+    //Position in the list of the item that started at the given index
+    fn find(&self, index: usize) -> Option<usize> {
+        let c = *self.chunk_of.get(index)?;
+        let offset = self.chunks[c].iter().position(|&i| i == index)?;
+        Some(
+            self.chunks[..c]
+                .iter()
+                .map(|chunk| chunk.len())
+                .sum::<usize>()
+                + offset,
+        )
     }
 
-    fn move_item(&mut self, item: &Item) {
-        let index = self.find(item).unwrap();
-        let mut new_index = index as i64 + item.value;
+    fn position_of_value(&self, value: i64) -> Option<usize> {
+        self.iter().position(|item| item.value == value)
+    }
 
-        new_index = new_index % (self.len() as i64 - 1);
+    fn insert_at(&mut self, position: usize, index: usize) {
+        let (c, offset) = self.locate(position);
+        self.chunks[c].insert(offset, index);
+        self.chunk_of[index] = c;
 
-        if new_index <= 0 {
-            new_index += self.len() as i64 - 1;
+        if self.chunks[c].len() > 2 * self.chunk_size {
+            self.rebuild();
         }
+    }
 
-        let item = self.items.remove(index);
+    fn move_item(&mut self, index: usize) {
+        //Nothing to move around in a list of one item
+        if self.len() <= 1 {
+            return;
+        }
+        let position = self.find(index).unwrap();
+        let c = self.chunk_of[index];
+        let offset = self.chunks[c].iter().position(|&i| i == index).unwrap();
+        self.chunks[c].remove(offset);
+
+        let cycle = self.len() as i64 - 1;
+        let mut new_position = (position as i64 + self.values[index]) % cycle;
+        if new_position <= 0 {
+            new_position += cycle;
+        }
 
-        self.insert_at((new_index).try_into().unwrap(), item);
+        self.insert_at(new_position as usize, index);
+    }
+
+    fn mix(&mut self, rounds: usize) {
+        for _ in 0..rounds {
+            for index in 0..self.len() {
+                self.move_item(index);
+            }
+        }
+    }
+
+    fn grove_coordinates(&self) -> Option<i64> {
+        let index0 = self.position_of_value(0)?;
+        [1000, 2000, 3000]
+            .iter()
+            .map(|offset| self.at(index0 + offset).map(|item| item.value))
+            .sum()
     }
 }
 
 impl FromStr for ItemList {
-    type Err = ();
+    type Err = std::num::ParseIntError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut list = ItemList::new();
-        let _ = s
+        let values = s
             .lines()
-            .enumerate()
-            .for_each(|(i, l)| list.add(i, l.parse().unwrap()));
-        Ok(list)
+            .map(|l| l.trim().parse())
+            .collect::<Result<Vec<i64>, Self::Err>>()?;
+        Ok(ItemList::new(values))
     }
 }
 
@@ -81,7 +167,7 @@ impl Display for ItemList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut s = String::new();
 
-        for item in self.items.iter() {
+        for item in self.iter() {
             s.push_str(&format!("{} ", item.value));
         }
 
@@ -89,60 +175,20 @@ impl Display for ItemList {
     }
 }
 
-fn part1(input: &str) -> i64 {
-    let list = input.parse::<ItemList>().unwrap();
-
-    let mut modified_list = list.clone();
-
-    for i in 0..list.len() {
-        let item = list.at(i).unwrap();
-        modified_list.move_item(item);
-    }
-
-    let index0 = modified_list
-        .items
-        .iter()
-        .position(|i| i.value == 0)
-        .unwrap();
-
-    modified_list.at(index0 + 1000).unwrap().value
-        + modified_list.at(index0 + 2000).unwrap().value
-        + modified_list.at(index0 + 3000).unwrap().value
+fn decrypt(input: &str, key: i64, rounds: usize) -> Result<i64, String> {
+    let mut list = input.parse::<ItemList>().map_err(|e| e.to_string())?;
+    list.apply_key(key);
+    list.mix(rounds);
+    list.grove_coordinates()
+        .ok_or("no item with value 0".to_string())
 }
 
-fn part2(input: &str) -> i64 {
-    let mut list = input.parse::<ItemList>().unwrap();
-
-    let key = 811589153;
-
-    for i in list.items.iter_mut() {
-        i.value *= key;
-    }
-
-    let mut modified_list = list.clone();
-    for _ in 0..10 {
-        for i in 0..list.len() {
-            let item = list.at(i).unwrap();
-            modified_list.move_item(item);
-        }
-    }
-
-    let index0 = modified_list
-        .items
-        .iter()
-        .position(|i| i.value == 0)
-        .unwrap();
-
-    modified_list.at(index0 + 1000).unwrap().value
-        + modified_list.at(index0 + 2000).unwrap().value
-        + modified_list.at(index0 + 3000).unwrap().value
+pub fn part1(input: &str) -> Answer {
+    decrypt(input, 1, 1).into()
 }
 
-pub fn run() {
-    // let input = include_str!("../input/20");
-    let input = include_str!("../input/20");
-    println!("Part 1 result: {}", part1(input));
-    println!("Part 2 result: {}", part2(input));
+pub fn part2(input: &str) -> Answer {
+    decrypt(input, 811589153, 10).into()
 }
 
 #[cfg(test)]
@@ -154,7 +200,6 @@ mod tests {
         let input = include_str!("../input/test20");
 
         let list = input.parse::<ItemList>().unwrap();
-        println!("{}", list);
 
         assert_eq!(list.at(0).unwrap().value, 1);
         assert_eq!(list.at(1).unwrap().value, 2);
@@ -165,22 +210,72 @@ mod tests {
         assert_eq!(list.at(14).unwrap().value, 1);
 
         let mut modified_list = list.clone();
+        modified_list.mix(1);
+        assert_eq!(modified_list.to_string(), "1 2 -3 4 0 3 -2 ");
 
-        for i in 0..list.len() {
-            let item = list.at(i).unwrap();
-            modified_list.move_item(item);
-            println!("{}", modified_list);
-        }
-        println!("{}", modified_list);
-
-        let index0 = modified_list
-            .items
-            .iter()
-            .position(|i| i.value == 0)
-            .unwrap();
+        let index0 = modified_list.position_of_value(0).unwrap();
 
         assert_eq!(4, modified_list.at(index0 + 1000).unwrap().value);
         assert_eq!(-3, modified_list.at(index0 + 2000).unwrap().value);
         assert_eq!(2, modified_list.at(index0 + 3000).unwrap().value);
     }
+
+    #[test]
+    fn test_find() {
+        let mut list = include_str!("../input/test20").parse::<ItemList>().unwrap();
+        list.move_item(0);
+        assert_eq!(list.to_string(), "2 1 -3 3 -2 0 4 ");
+        assert_eq!(list.find(0), Some(1));
+        assert_eq!(list.find(1), Some(0));
+        assert_eq!(list.find(6), Some(6));
+        assert_eq!(list.find(7), None);
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let input = include_str!("../input/test20");
+        assert_eq!(part1(input), Answer::from(3));
+        assert_eq!(part2(input), Answer::from(1623178306));
+    }
+
+    #[test]
+    fn test_small_lists() {
+        let mut list = ItemList::new(vec![5]);
+        list.mix(3);
+        assert_eq!(list.to_string(), "5 ");
+        assert_eq!(decrypt("0\n", 1, 1), Ok(0));
+        assert_eq!(decrypt("", 1, 1), Err("no item with value 0".to_string()));
+        assert_eq!(
+            part1("1\nx\n").to_string(),
+            "unsolved (invalid digit found in string)"
+        );
+    }
+
+    #[test]
+    fn test_matches_naive_mixing() {
+        let input = include_str!("../input/20");
+        let values: Vec<i64> = input
+            .lines()
+            .take(300)
+            .map(|l| l.parse().unwrap())
+            .collect();
+
+        let mut naive: Vec<usize> = (0..values.len()).collect();
+        for (index, value) in values.iter().enumerate() {
+            let position = naive.iter().position(|&i| i == index).unwrap();
+            naive.remove(position);
+            let cycle = naive.len() as i64;
+            let new_position = (position as i64 + value).rem_euclid(cycle);
+            naive.insert(new_position as usize, index);
+        }
+
+        let mut list = ItemList::new(values);
+        list.mix(1);
+
+        //Both orders are the same cycle, only the starting point may differ
+        let start = list.find(naive[0]).unwrap();
+        for (offset, &index) in naive.iter().enumerate() {
+            assert_eq!(list.at(start + offset).unwrap().index, index);
+        }
+    }
 }
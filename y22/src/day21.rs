@@ -1,3 +1,4 @@
+use crate::answer::Answer;
 use std::{collections::HashMap, str::FromStr};
 
 #[derive(Debug)]
@@ -114,9 +115,7 @@ fn path_to_humn(monkeys: &HashMap<String, Monkey>, name: String, path: Vec<u8>)
     }
 }
 
-fn part1() -> i64 {
-    let input = include_str!("../input/21");
-
+pub fn part1(input: &str) -> Answer {
     let mut monkeys = HashMap::new();
 
     for line in input.lines() {
@@ -125,12 +124,10 @@ fn part1() -> i64 {
         monkeys.insert(monkey.name.clone(), monkey);
     }
 
-    number(&monkeys, "root".to_string())
+    number(&monkeys, "root".to_string()).into()
 }
 
-fn part2() -> i64 {
-    let input = include_str!("../input/21");
-
+pub fn part2(input: &str) -> Answer {
     let mut monkeys = HashMap::new();
 
     for line in input.lines() {
@@ -201,14 +198,21 @@ fn part2() -> i64 {
         Action::Op(ref mut op) => op.op = Operation::Sub,
     }
 
-    assert_eq!(0, number(&monkeys, "root".to_string()));
+    if number(&monkeys, "root".to_string()) != 0 {
+        return Answer::Unsolved(format!("humn = {} does not balance root", goal_number));
+    }
 
-    goal_number
+    goal_number.into()
 }
 
-pub fn run() {
-    println!("day21");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("part1: {}", part1());
-    println!("part2: {}", part2());
+    #[test]
+    fn test_parts() {
+        let input = include_str!("../input/test21");
+        assert_eq!(part1(input), Answer::from(152));
+        assert_eq!(part2(input), Answer::from(301));
+    }
 }
@@ -1,6 +1,29 @@
-use std::fmt::Display;
+use crate::answer::Answer;
+use std::{collections::HashMap, fmt::Display};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    InvalidTile(char),
+    InvalidInstruction(char),
+    InvalidSteps(String),
+    MissingMap,
+    //The top row has no open tile to start on
+    NoStart,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidTile(c) => write!(f, "invalid tile: {:?}", c),
+            ParseError::InvalidInstruction(c) => write!(f, "invalid instruction: {:?}", c),
+            ParseError::InvalidSteps(steps) => write!(f, "invalid number of steps: {}", steps),
+            ParseError::MissingMap => write!(f, "no map before the path"),
+            ParseError::NoStart => write!(f, "no open tile in the top row"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
     Up,
     Down,
@@ -17,6 +40,33 @@ impl Direction {
             Direction::Up => 3,
         }
     }
+
+    fn turn_left(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    fn turn_right(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Right => Direction::Down,
+        }
+    }
+
+    fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -27,7 +77,7 @@ enum Pos {
     Direction(Direction),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum Instruction {
     TurnLeft,
     TurnRight,
@@ -79,70 +129,252 @@ impl Grid {
     }
 }
 
-fn parse_input(input: &str) -> (Grid, Vec<Instruction>) {
-    //Parse grid
-    let mut grid = Vec::new();
-    let max_row_len = input.lines().rev().skip(1).map(|x| x.len()).max().unwrap();
-    for line in input.lines() {
-        if line.is_empty() {
-            break;
-        }
-        let mut row = Vec::new();
-        for c in line.chars() {
-            match c {
-                '#' => row.push(Pos::Wall),
-                '.' => row.push(Pos::Empty),
-                ' ' => row.push(Pos::None),
-                _ => panic!("Unknown char: {}", c),
-            }
+type Vec3 = (i32, i32, i32);
+
+fn neg(v: Vec3) -> Vec3 {
+    (-v.0, -v.1, -v.2)
+}
+
+//Orientation of a face once the net is folded: the outward normal and where
+//the net's right and down directions point in 3D
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    normal: Vec3,
+    right: Vec3,
+    down: Vec3,
+}
+
+impl Frame {
+    fn towards(&self, direction: Direction) -> Vec3 {
+        match direction {
+            Direction::Right => self.right,
+            Direction::Down => self.down,
+            Direction::Left => neg(self.right),
+            Direction::Up => neg(self.down),
         }
-        while row.len() < max_row_len {
-            row.push(Pos::None);
+    }
+
+    //Frame of the neighbouring face in the net after folding it over the shared edge
+    fn rolled(&self, direction: Direction) -> Frame {
+        match direction {
+            Direction::Right => Frame {
+                normal: self.right,
+                right: neg(self.normal),
+                down: self.down,
+            },
+            Direction::Left => Frame {
+                normal: neg(self.right),
+                right: self.normal,
+                down: self.down,
+            },
+            Direction::Down => Frame {
+                normal: self.down,
+                right: self.right,
+                down: neg(self.normal),
+            },
+            Direction::Up => Frame {
+                normal: neg(self.down),
+                right: self.right,
+                down: self.normal,
+            },
         }
-        grid.push(row);
     }
+}
 
-    //Parse instructions
-    let mut instructions = Vec::new();
-    for line in input.lines().rev() {
-        if line.is_empty() {
-            break;
-        }
-
-        let mut number_buf = String::new();
-        let mut i = 0;
-        loop {
-            match line.chars().nth(i) {
-                Some(x) if x.is_alphabetic() => {
-                    //Push the previous move instruction
-                    let number = number_buf.parse::<usize>().unwrap();
-                    instructions.push(Instruction::MoveForward(number));
-                    number_buf.clear();
-
-                    //Push the instruction
-                    let instruction = match x {
-                        'L' => Instruction::TurnLeft,
-                        'R' => Instruction::TurnRight,
-                        c => panic!("Unknown char: {}", c),
-                    };
-                    instructions.push(instruction);
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+    Direction::Up,
+];
+
+struct Cube {
+    size: usize,
+    //Tile coordinates (column, row) of each face in the net
+    faces: Vec<(usize, usize)>,
+    //Leaving a face in a direction enters another face heading in a new direction
+    transitions: HashMap<(usize, Direction), (usize, Direction)>,
+}
+
+impl Cube {
+    fn fold(grid: &Grid) -> Result<Cube, String> {
+        let cells = grid
+            .0
+            .iter()
+            .flatten()
+            .filter(|pos| !matches!(pos, Pos::None))
+            .count();
+        let size = (1..=cells)
+            .find(|n| 6 * n * n >= cells)
+            .filter(|n| 6 * n * n == cells)
+            .ok_or(format!("{} cells can not cover a cube", cells))?;
+
+        let mut faces = Vec::new();
+        for row in 0..grid.0.len() / size {
+            for column in 0..grid.0[0].len() / size {
+                if !grid.is_none(&(column * size + 1, row * size + 1)) {
+                    faces.push((column, row));
                 }
-                Some(x) if x.is_numeric() => {
-                    number_buf.push(x);
+            }
+        }
+        if faces.len() != 6 {
+            return Err(format!("net has {} faces of size {}", faces.len(), size));
+        }
+
+        //Fold the net by walking it from the first face
+        let mut frames: Vec<Option<Frame>> = vec![None; 6];
+        frames[0] = Some(Frame {
+            normal: (0, 0, 1),
+            right: (1, 0, 0),
+            down: (0, 1, 0),
+        });
+        let mut queue = vec![0];
+        while let Some(face) = queue.pop() {
+            let frame = frames[face].unwrap();
+            let (column, row) = faces[face];
+            for direction in DIRECTIONS {
+                let neighbour = match direction {
+                    Direction::Right => Some((column + 1, row)),
+                    Direction::Down => Some((column, row + 1)),
+                    Direction::Left => column.checked_sub(1).map(|c| (c, row)),
+                    Direction::Up => row.checked_sub(1).map(|r| (column, r)),
+                };
+                let Some(next) = neighbour.and_then(|n| faces.iter().position(|&f| f == n)) else {
+                    continue;
+                };
+                if frames[next].is_none() {
+                    frames[next] = Some(frame.rolled(direction));
+                    queue.push(next);
                 }
-                _ => break,
             }
-            i += 1;
         }
+        let frames = frames
+            .into_iter()
+            .collect::<Option<Vec<Frame>>>()
+            .ok_or("net is not connected")?;
+
+        let mut transitions = HashMap::new();
+        for (face, frame) in frames.iter().enumerate() {
+            for direction in DIRECTIONS {
+                let target = frames
+                    .iter()
+                    .position(|f| f.normal == frame.towards(direction))
+                    .ok_or("net folds two faces onto each other")?;
+                //Crossing the edge we keep moving away from the face we left
+                let entry = DIRECTIONS
+                    .into_iter()
+                    .find(|&d| frames[target].towards(d) == neg(frame.normal))
+                    .unwrap();
+                transitions.insert((face, direction), (target, entry));
+            }
+        }
+
+        Ok(Cube {
+            size,
+            faces,
+            transitions,
+        })
+    }
 
-        if !number_buf.is_empty() {
-            let number = number_buf.parse::<usize>().unwrap();
-            instructions.push(Instruction::MoveForward(number));
-            number_buf.clear();
+    fn face_at(&self, pos: &Position) -> Option<usize> {
+        let tile = ((pos.0 - 1) / self.size, (pos.1 - 1) / self.size);
+        self.faces.iter().position(|&f| f == tile)
+    }
+
+    //Offset along the edge on the given side, counted clockwise around the face
+    fn edge_offset(&self, side: Direction, local: (usize, usize)) -> usize {
+        let last = self.size - 1;
+        match side {
+            Direction::Up => local.0,
+            Direction::Right => local.1,
+            Direction::Down => last - local.0,
+            Direction::Left => last - local.1,
         }
     }
 
-    (Grid(grid), instructions)
+    fn edge_position(&self, side: Direction, offset: usize) -> (usize, usize) {
+        let last = self.size - 1;
+        match side {
+            Direction::Up => (offset, 0),
+            Direction::Right => (last, offset),
+            Direction::Down => (last - offset, last),
+            Direction::Left => (0, last - offset),
+        }
+    }
+
+    //Both faces see the shared edge clockwise, so the offset is mirrored when crossing
+    fn wrap(&self, pos: Position, direction: Direction) -> (Position, Direction) {
+        let face = self.face_at(&pos).unwrap();
+        let local = ((pos.0 - 1) % self.size, (pos.1 - 1) % self.size);
+        let offset = self.edge_offset(direction, local);
+
+        let (target, entry) = self.transitions[&(face, direction)];
+        let (x, y) = self.edge_position(entry.opposite(), self.size - 1 - offset);
+        let (column, row) = self.faces[target];
+        ((column * self.size + x + 1, row * self.size + y + 1), entry)
+    }
+}
+
+fn parse_steps(steps: &mut String, instructions: &mut Vec<Instruction>) -> Result<(), ParseError> {
+    if !steps.is_empty() {
+        let number = steps
+            .parse()
+            .map_err(|_| ParseError::InvalidSteps(steps.clone()))?;
+        instructions.push(Instruction::MoveForward(number));
+        steps.clear();
+    }
+    Ok(())
+}
+
+//The map, a blank line and the path
+fn parse_input(input: &str) -> Result<(Grid, Vec<Instruction>), ParseError> {
+    let mut lines = input.lines().map(|line| line.trim_end_matches('\r'));
+    let map = lines
+        .by_ref()
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+    let max_row_len = map
+        .iter()
+        .map(|line| line.len())
+        .max()
+        .ok_or(ParseError::MissingMap)?;
+
+    let mut grid = Vec::new();
+    for line in map {
+        let mut row = line
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(Pos::Wall),
+                '.' => Ok(Pos::Empty),
+                ' ' => Ok(Pos::None),
+                c => Err(ParseError::InvalidTile(c)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        row.resize_with(max_row_len, || Pos::None);
+        grid.push(row);
+    }
+
+    //A number of steps, then a turn and more steps, over as many lines as needed
+    let mut instructions = Vec::new();
+    let mut steps = String::new();
+    for c in lines.flat_map(str::chars) {
+        match c {
+            '0'..='9' => steps.push(c),
+            'L' | 'R' => {
+                parse_steps(&mut steps, &mut instructions)?;
+                instructions.push(if c == 'L' {
+                    Instruction::TurnLeft
+                } else {
+                    Instruction::TurnRight
+                });
+            }
+            c if c.is_whitespace() => parse_steps(&mut steps, &mut instructions)?,
+            c => return Err(ParseError::InvalidInstruction(c)),
+        }
+    }
+    parse_steps(&mut steps, &mut instructions)?;
+
+    Ok((Grid(grid), instructions))
 }
 
 impl Display for Grid {
@@ -165,39 +397,29 @@ impl Display for Grid {
     }
 }
 
-fn part1() {
-    let input = include_str!("../input/22");
-    let (mut grid, instructions) = parse_input(input);
-    println!("{}", grid);
+fn start_position(grid: &Grid) -> Result<Position, ParseError> {
+    let x = grid.0[0]
+        .iter()
+        .position(|pos| matches!(pos, Pos::Empty))
+        .ok_or(ParseError::NoStart)?;
+    Ok((x + 1, 1))
+}
 
-    let mut pos = (0, 0);
+fn walk<F>(grid: &mut Grid, instructions: &[Instruction], wrap: F) -> Result<usize, ParseError>
+where
+    F: Fn(&Grid, Position, Direction) -> (Position, Direction),
+{
+    let mut pos = start_position(grid)?;
     let mut direction = Direction::Right;
 
-    for (x, x_pos) in grid.0[0].iter().enumerate() {
-        if let Pos::Empty = x_pos {
-            pos = (x + 1, 1);
-            break;
-        }
-    }
-    println!("Start pos: {:?}", pos);
-
     for instruction in instructions {
         match instruction {
-            Instruction::TurnLeft => match direction {
-                Direction::Up => direction = Direction::Left,
-                Direction::Down => direction = Direction::Right,
-                Direction::Left => direction = Direction::Down,
-                Direction::Right => direction = Direction::Up,
-            },
-            Instruction::TurnRight => match direction {
-                Direction::Up => direction = Direction::Right,
-                Direction::Down => direction = Direction::Left,
-                Direction::Left => direction = Direction::Up,
-                Direction::Right => direction = Direction::Down,
-            },
+            Instruction::TurnLeft => direction = direction.turn_left(),
+            Instruction::TurnRight => direction = direction.turn_right(),
             Instruction::MoveForward(steps) => {
-                for _ in 0..steps {
+                for _ in 0..*steps {
                     let mut new_pos = pos;
+                    let mut new_direction = direction;
                     match direction {
                         Direction::Up => new_pos.1 -= 1,
                         Direction::Down => new_pos.1 += 1,
@@ -205,32 +427,197 @@ fn part1() {
                         Direction::Right => new_pos.0 += 1,
                     }
 
-                    if grid.is_out_of_bounds(&new_pos) {
-                        new_pos = grid.next_empty(pos, &direction);
-                    }
-
-                    if grid.is_none(&new_pos) {
-                        new_pos = grid.next_empty(pos, &direction);
+                    if grid.is_out_of_bounds(&new_pos) || grid.is_none(&new_pos) {
+                        (new_pos, new_direction) = wrap(grid, pos, direction);
                     }
 
                     if grid.is_wall(&new_pos) {
                         break;
                     }
                     pos = new_pos;
-                    grid.0[pos.1 - 1][pos.0 - 1] = Pos::Direction(direction.clone());
+                    direction = new_direction;
+                    grid.0[pos.1 - 1][pos.0 - 1] = Pos::Direction(direction);
                 }
             }
         }
-        // println!("{}", grid);
     }
-    println!("{}", grid);
 
-    println!("Final pos: {:?}", pos);
-    let result = 1000 * pos.1 + 4 * pos.0 + direction.to_int();
-    println!("Part1: {}", result);
+    Ok(1000 * pos.1 + 4 * pos.0 + direction.to_int())
 }
 
-pub fn run() {
-    println!("Day 22");
-    part1();
+pub fn part1(input: &str) -> Answer {
+    parse_input(input)
+        .and_then(|(mut grid, instructions)| {
+            walk(&mut grid, &instructions, |grid, pos, direction| {
+                (grid.next_empty(pos, &direction), direction)
+            })
+        })
+        .into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    parse_input(input)
+        .map_err(|e| e.to_string())
+        .and_then(|(mut grid, instructions)| {
+            let cube = Cube::fold(&grid)?;
+            walk(&mut grid, &instructions, |_, pos, direction| {
+                cube.wrap(pos, direction)
+            })
+            .map_err(|e| e.to_string())
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid_from(net: &str) -> Grid {
+        let width = net.lines().map(|l| l.len()).max().unwrap();
+        Grid(
+            net.lines()
+                .map(|line| {
+                    let mut row: Vec<Pos> = line
+                        .chars()
+                        .map(|c| if c == '.' { Pos::Empty } else { Pos::None })
+                        .collect();
+                    row.resize_with(width, || Pos::None);
+                    row
+                })
+                .collect(),
+        )
+    }
+
+    fn assert_consistent(cube: &Cube) {
+        for face in 0..6 {
+            for direction in DIRECTIONS {
+                let (target, entry) = cube.transitions[&(face, direction)];
+                assert_ne!(target, face);
+                assert_eq!(
+                    cube.transitions[&(target, entry.opposite())],
+                    (face, direction.opposite())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(include_str!("../input/test22")), Answer::from(6032));
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(part2(include_str!("../input/test22")), Answer::from(5031));
+        assert_eq!(
+            part2("..\n..\n\n1\n").to_string(),
+            "unsolved (4 cells can not cover a cube)"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let (grid, instructions) = parse_input("..#\n\n10R5L\n").unwrap();
+        assert_eq!(grid.0.len(), 1);
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::MoveForward(10),
+                Instruction::TurnRight,
+                Instruction::MoveForward(5),
+                Instruction::TurnLeft
+            ]
+        );
+        assert_eq!(
+            parse_input("..x\n\n1").err(),
+            Some(ParseError::InvalidTile('x'))
+        );
+        assert_eq!(
+            parse_input("...\n\n1U2").err(),
+            Some(ParseError::InvalidInstruction('U'))
+        );
+        assert_eq!(parse_input("\n1").err(), Some(ParseError::MissingMap));
+        assert_eq!(
+            part1("..\n\n99999999999999999999999").to_string(),
+            "unsolved (invalid number of steps: 99999999999999999999999)"
+        );
+        assert_eq!(
+            part1("  #\n\n1").to_string(),
+            "unsolved (no open tile in the top row)"
+        );
+        assert_eq!(part2("").to_string(), "unsolved (no map before the path)");
+    }
+
+    #[test]
+    fn test_fold_example() {
+        let (grid, _) = parse_input(include_str!("../input/test22")).unwrap();
+        let cube = Cube::fold(&grid).unwrap();
+        assert_eq!(cube.size, 4);
+        assert_eq!(
+            cube.faces,
+            vec![(2, 0), (0, 1), (1, 1), (2, 1), (2, 2), (3, 2)]
+        );
+        assert_consistent(&cube);
+
+        //The examples from the puzzle text, A to B and C to D
+        assert_eq!(
+            cube.wrap((12, 6), Direction::Right),
+            ((15, 9), Direction::Down)
+        );
+        assert_eq!(
+            cube.wrap((11, 12), Direction::Down),
+            ((2, 8), Direction::Up)
+        );
+        assert_eq!(cube.wrap((7, 5), Direction::Up), ((9, 3), Direction::Right));
+    }
+
+    #[test]
+    fn test_fold_input() {
+        let (grid, _) = parse_input(include_str!("../input/22")).unwrap();
+        let cube = Cube::fold(&grid).unwrap();
+        assert_eq!(cube.size, 50);
+        assert_eq!(
+            cube.faces,
+            vec![(1, 0), (2, 0), (1, 1), (0, 2), (1, 2), (0, 3)]
+        );
+        assert_consistent(&cube);
+
+        //Walking off an edge and turning around leads back to the same cell
+        for face in 0..6 {
+            let (column, row) = cube.faces[face];
+            let pos = (column * 50 + 1, row * 50 + 1);
+            for direction in [Direction::Left, Direction::Up] {
+                let (next, entry) = cube.wrap(pos, direction);
+                assert_eq!(
+                    cube.wrap(next, entry.opposite()),
+                    (pos, direction.opposite())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_nets() {
+        let nets = [
+            ".\n....\n.",
+            ".\n....\n .",
+            ".\n....\n  .",
+            ".\n....\n   .",
+            " .\n....\n .",
+            " .\n....\n  .",
+            "..\n ...\n .",
+            "..\n ...\n  .",
+            "..\n ...\n   .",
+            "..\n ..\n  ..",
+            "...\n  ...",
+        ];
+        for net in nets {
+            let cube = Cube::fold(&grid_from(net)).unwrap();
+            assert_eq!(cube.size, 1);
+            assert_consistent(&cube);
+        }
+
+        assert!(Cube::fold(&grid_from("......")).is_err());
+        assert!(Cube::fold(&grid_from("....\n..")).is_err());
+    }
 }
@@ -1,3 +1,4 @@
+use crate::answer::Answer;
 use itertools::Itertools;
 use itertools::MinMaxResult::MinMax;
 use std::collections::{HashMap, HashSet};
@@ -5,182 +6,376 @@ use std::{fmt::Display, str::FromStr};
 
 type Pos = (i32, i32);
 
-struct Elves(HashSet<Pos>);
-
-impl Elves {
-    fn num_empty_pos(&self) -> usize {
-        let x_limits = self.0.iter().map(|(x, _)| x).minmax();
-        let y_limits = self.0.iter().map(|(_, y)| y).minmax();
+fn add(a: Pos, b: Pos) -> Pos {
+    (a.0 + b.0, a.1 + b.1)
+}
 
-        if let (MinMax(x_min, x_max), MinMax(y_min, y_max)) = (x_limits, y_limits) {
-            let area = (x_max - x_min + 1) * (y_max - y_min + 1);
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    InvalidTile { line: usize, tile: char },
+}
 
-            area as usize - self.0.len()
-        } else {
-            0
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidTile { line, tile } => {
+                write!(f, "invalid tile {:?} on line {}", tile, line)
+            }
         }
     }
 }
 
-impl FromStr for Elves {
-    type Err = ();
+//An elf moves by step if none of the checked offsets holds another elf
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    step: Pos,
+    checks: Vec<Pos>,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut elves = HashSet::new();
-        for (y, line) in s.lines().enumerate() {
-            for (x, c) in line.chars().enumerate() {
-                if c == '#' {
-                    elves.insert((x as i32, y as i32));
-                }
-            }
+impl Rule {
+    fn new(step: Pos, checks: &[Pos]) -> Self {
+        Rule {
+            step,
+            checks: checks.to_vec(),
         }
-        Ok(Elves(elves))
     }
 }
 
-impl Display for Elves {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let x_limits = self.0.iter().map(|(x, _)| x).minmax();
-        let y_limits = self.0.iter().map(|(_, y)| y).minmax();
-
-        if let (MinMax(x_min, x_max), MinMax(y_min, y_max)) = (x_limits, y_limits) {
-            for y in 0..=(y_max - y_min) {
-                for x in 0..=(x_max - x_min) {
-                    if self.0.contains(&(x + x_min, y + y_min)) {
-                        write!(f, "#")?;
-                    } else {
-                        write!(f, ".")?;
-                    }
-                }
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rules {
+    //Elves with none of these offsets occupied stay where they are
+    crowd: Vec<Pos>,
+    order: Vec<Rule>,
+    //Move the first rule to the back after every round
+    rotate: bool,
+}
 
-                writeln!(f)?;
-            }
-        } else {
-            return Err(std::fmt::Error);
+impl Rules {
+    //North, south, west, east with y growing downwards
+    fn elves() -> Self {
+        Rules {
+            crowd: (-1..=1)
+                .cartesian_product(-1..=1)
+                .filter(|&d| d != (0, 0))
+                .collect(),
+            order: vec![
+                Rule::new((0, -1), &[(-1, -1), (0, -1), (1, -1)]),
+                Rule::new((0, 1), &[(-1, 1), (0, 1), (1, 1)]),
+                Rule::new((-1, 0), &[(-1, -1), (-1, 0), (-1, 1)]),
+                Rule::new((1, 0), &[(1, -1), (1, 0), (1, 1)]),
+            ],
+            rotate: true,
         }
-
-        Ok(())
     }
 }
 
-#[derive(Debug, Clone)]
-enum Direction {
-    N,
-    S,
-    W,
-    E,
+//State of the automaton at the end of a round
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Snapshot {
+    round: usize,
+    moved: usize,
+    elves: HashSet<Pos>,
 }
 
-impl Direction {
-    fn next(&self) -> Self {
-        match self {
-            Direction::N => Direction::S,
-            Direction::S => Direction::W,
-            Direction::W => Direction::E,
-            Direction::E => Direction::N,
+struct Elves {
+    elves: HashSet<Pos>,
+    rules: Rules,
+    //How far the rule order has been rotated
+    rotation: usize,
+}
+
+impl Elves {
+    fn with_rules(elves: HashSet<Pos>, rules: Rules) -> Self {
+        Elves {
+            elves,
+            rules,
+            rotation: 0,
         }
     }
-}
 
-fn part1() -> i32 {
-    let input = include_str!("../input/23");
-    let mut elves = Elves::from_str(input).unwrap();
-
-    let mut direction = Direction::N;
-    let mut round = 1;
-    loop {
-        let mut new_pos = HashMap::new();
-        for elf in elves.0.iter() {
-            if !(elves.0.contains(&(elf.0, elf.1 - 1))
-                || elves.0.contains(&(elf.0 - 1, elf.1 - 1))
-                || elves.0.contains(&(elf.0 + 1, elf.1 - 1))
-                || elves.0.contains(&(elf.0, elf.1 + 1))
-                || elves.0.contains(&(elf.0 - 1, elf.1 + 1))
-                || elves.0.contains(&(elf.0 + 1, elf.1 + 1))
-                || elves.0.contains(&(elf.0 - 1, elf.1))
-                || elves.0.contains(&(elf.0 + 1, elf.1)))
-            {
-                continue;
-            }
+    fn proposal(&self, elf: Pos) -> Option<Pos> {
+        let occupied = |d: &Pos| self.elves.contains(&add(elf, *d));
+        if !self.rules.crowd.iter().any(occupied) {
+            return None;
+        }
+        let n = self.rules.order.len();
+        (0..n)
+            .map(|i| &self.rules.order[(self.rotation + i) % n])
+            .find(|rule| !rule.checks.iter().any(occupied))
+            .map(|rule| add(elf, rule.step))
+    }
 
-            let mut current_direction = direction.clone();
-            for _ in 0..4 {
-                match current_direction {
-                    Direction::N => {
-                        if !(elves.0.contains(&(elf.0, elf.1 - 1))
-                            || elves.0.contains(&(elf.0 - 1, elf.1 - 1))
-                            || elves.0.contains(&(elf.0 + 1, elf.1 - 1)))
-                        {
-                            new_pos.insert(*elf, (elf.0, elf.1 - 1));
-                            break;
-                        }
-                    }
-                    Direction::S => {
-                        if !(elves.0.contains(&(elf.0, elf.1 + 1))
-                            || elves.0.contains(&(elf.0 - 1, elf.1 + 1))
-                            || elves.0.contains(&(elf.0 + 1, elf.1 + 1)))
-                        {
-                            new_pos.insert(*elf, (elf.0, elf.1 + 1));
-                            break;
-                        }
-                    }
-                    Direction::W => {
-                        if !(elves.0.contains(&(elf.0 - 1, elf.1))
-                            || elves.0.contains(&(elf.0 - 1, elf.1 - 1))
-                            || elves.0.contains(&(elf.0 - 1, elf.1 + 1)))
-                        {
-                            new_pos.insert(*elf, (elf.0 - 1, elf.1));
-                            break;
-                        }
-                    }
-                    Direction::E => {
-                        if !(elves.0.contains(&(elf.0 + 1, elf.1))
-                            || elves.0.contains(&(elf.0 + 1, elf.1 - 1))
-                            || elves.0.contains(&(elf.0 + 1, elf.1 + 1)))
-                        {
-                            new_pos.insert(*elf, (elf.0 + 1, elf.1));
-                            break;
-                        }
-                    }
-                }
-                current_direction = current_direction.next();
+    //Runs one round and returns how many elves moved. Targets proposed by more
+    //than one elf are left alone, as are targets of an elf that stays put.
+    //Every elf moves at the same time, so the next positions are built from the
+    //current ones.
+    fn step(&mut self) -> usize {
+        let proposals = self
+            .elves
+            .iter()
+            .filter_map(|&elf| self.proposal(elf).map(|target| (elf, target)))
+            .collect::<HashMap<_, _>>();
+
+        let mut counts: HashMap<Pos, usize> = HashMap::new();
+        for target in proposals.values() {
+            *counts.entry(*target).or_default() += 1;
+        }
+        let mut moves = proposals
+            .into_iter()
+            .filter(|(_, target)| counts[target] == 1)
+            .collect::<HashMap<_, _>>();
+
+        //An elf blocked by one that stays put stays put as well, which can
+        //block yet another elf
+        let mut staying = self
+            .elves
+            .iter()
+            .filter(|elf| !moves.contains_key(elf))
+            .copied()
+            .collect::<HashSet<_>>();
+        loop {
+            let blocked = moves
+                .iter()
+                .filter(|(_, target)| staying.contains(target))
+                .map(|(&elf, _)| elf)
+                .collect::<Vec<_>>();
+            if blocked.is_empty() {
+                break;
+            }
+            for elf in blocked {
+                moves.remove(&elf);
+                staying.insert(elf);
             }
         }
-        direction = direction.next();
 
-        if round == 10 {
-            println!("Part1: {}", elves.num_empty_pos());
+        let moved = moves.len();
+        self.elves = staying.into_iter().chain(moves.into_values()).collect();
+
+        if self.rules.rotate {
+            self.rotation += 1;
+        }
+        moved
+    }
+
+    fn snapshot(&self, round: usize, moved: usize) -> Snapshot {
+        Snapshot {
+            round,
+            moved,
+            elves: self.elves.clone(),
         }
+    }
 
-        if new_pos.is_empty() {
-            return round;
+    //Runs at most rounds rounds and stops early once nobody moves, returns
+    //the number of rounds played
+    fn simulate<F>(&mut self, rounds: usize, mut on_round: F) -> usize
+    where
+        F: FnMut(Snapshot),
+    {
+        for round in 1..=rounds {
+            let moved = self.step();
+            on_round(self.snapshot(round, moved));
+            if moved == 0 {
+                return round;
+            }
         }
-        round += 1;
+        rounds
+    }
 
-        let mut non_dup = HashSet::new();
+    fn snapshots(&mut self, rounds: usize) -> Vec<Snapshot> {
+        let mut snapshots = Vec::new();
+        self.simulate(rounds, |snapshot| snapshots.push(snapshot));
+        snapshots
+    }
 
-        new_pos
-            .clone()
-            .into_iter()
-            .map(|(_, pos)| pos)
-            .for_each(|pos| {
-                if non_dup.contains(&pos) {
-                    non_dup.remove(&pos);
+    //First round in which no elf moves
+    fn settle(&mut self) -> usize {
+        self.simulate(usize::MAX, |_| {})
+    }
+
+    fn bounds(&self) -> Option<(Pos, Pos)> {
+        let x_limits = self.elves.iter().map(|(x, _)| *x).minmax();
+        let y_limits = self.elves.iter().map(|(_, y)| *y).minmax();
+        match (x_limits, y_limits) {
+            (MinMax(x_min, x_max), MinMax(y_min, y_max)) => Some(((x_min, y_min), (x_max, y_max))),
+            _ => self.elves.iter().next().map(|&elf| (elf, elf)),
+        }
+    }
+
+    fn num_empty_pos(&self) -> usize {
+        match self.bounds() {
+            Some((min, max)) => {
+                let area = (max.0 - min.0 + 1) * (max.1 - min.1 + 1);
+                area as usize - self.elves.len()
+            }
+            None => 0,
+        }
+    }
+
+    fn render(&self, min: Pos, max: Pos) -> String {
+        let mut s = String::new();
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                s.push(if self.elves.contains(&(x, y)) {
+                    '#'
                 } else {
-                    non_dup.insert(pos);
-                }
-            });
+                    '.'
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
+}
+
+impl FromStr for Elves {
+    type Err = ParseError;
 
-        for (elf, pos) in new_pos {
-            if !non_dup.contains(&pos) {
-                continue;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut elves = HashSet::new();
+        for (y, line) in s.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => {
+                        elves.insert((x as i32, y as i32));
+                    }
+                    '.' | '\r' => {}
+                    tile => return Err(ParseError::InvalidTile { line: y + 1, tile }),
+                }
             }
-            elves.0.remove(&elf);
-            elves.0.insert(pos);
         }
+        Ok(Elves::with_rules(elves, Rules::elves()))
     }
 }
 
-pub fn run() {
-    println!("Part 1: {}", part1());
+impl Display for Elves {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (min, max) = self.bounds().ok_or(std::fmt::Error)?;
+        write!(f, "{}", self.render(min, max))
+    }
+}
+
+pub fn part1(input: &str) -> Answer {
+    Elves::from_str(input)
+        .map(|mut elves| {
+            elves.simulate(10, |_| {});
+            elves.num_empty_pos()
+        })
+        .into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    Elves::from_str(input)
+        .map(|mut elves| elves.settle())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part1() {
+        let input = include_str!("../input/test23");
+        assert_eq!(part1(input), Answer::from(110));
+    }
+
+    #[test]
+    fn test_part2() {
+        let input = include_str!("../input/test23");
+        assert_eq!(part2(input), Answer::from(20));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            Elves::from_str("#.\n.x").err(),
+            Some(ParseError::InvalidTile { line: 2, tile: 'x' })
+        );
+        assert_eq!(
+            part2("#?").to_string(),
+            "unsolved (invalid tile '?' on line 1)"
+        );
+    }
+
+    #[test]
+    fn test_small_example() {
+        let input = include_str!("../input/test23_1");
+        let mut elves = Elves::from_str(input).unwrap();
+        let frame = ((0, 0), (4, 5));
+
+        let snapshots = elves.snapshots(10);
+        assert_eq!(snapshots.len(), 4);
+        assert_eq!(
+            snapshots.iter().map(|s| s.moved).collect_vec(),
+            vec![3, 5, 3, 0]
+        );
+
+        let rendered = snapshots
+            .iter()
+            .map(|s| Elves::with_rules(s.elves.clone(), Rules::elves()).render(frame.0, frame.1))
+            .collect_vec();
+        assert_eq!(rendered[0], "..##.\n.....\n..#..\n...#.\n..#..\n.....\n");
+        assert_eq!(rendered[1], ".....\n..##.\n.#...\n....#\n.....\n..#..\n");
+        assert_eq!(rendered[2], "..#..\n....#\n#....\n....#\n.....\n..#..\n");
+        assert_eq!(rendered[3], rendered[2]);
+        assert_eq!(elves.bounds(), Some(((0, 0), (4, 5))));
+        assert_eq!(elves.num_empty_pos(), 25);
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        //Without rotation and only one rule every crowded elf keeps walking north
+        let rules = Rules {
+            crowd: vec![(1, 0), (-1, 0)],
+            order: vec![Rule::new((0, -1), &[(0, -1)])],
+            rotate: false,
+        };
+        let mut elves = Elves::with_rules(HashSet::from([(0, 0), (1, 0), (5, 5)]), rules);
+        assert_eq!(elves.step(), 2);
+        assert_eq!(elves.step(), 2);
+        assert_eq!(elves.elves, HashSet::from([(0, -2), (1, -2), (5, 5)]));
+    }
+
+    #[test]
+    fn test_three_way_conflict() {
+        //Each elf has its own rule and all three of them aim for (0, 0)
+        let rules = Rules {
+            crowd: Rules::elves().crowd,
+            order: vec![
+                Rule::new((1, 0), &[(-1, 1), (-1, -1)]),
+                Rule::new((-1, 0), &[(1, -1)]),
+                Rule::new((0, -1), &[]),
+            ],
+            rotate: false,
+        };
+        let start = HashSet::from([(-1, 0), (1, 0), (0, 1)]);
+        let mut elves = Elves::with_rules(start.clone(), rules);
+        assert_eq!(elves.proposal((-1, 0)), Some((0, 0)));
+        assert_eq!(elves.proposal((1, 0)), Some((0, 0)));
+        assert_eq!(elves.proposal((0, 1)), Some((0, 0)));
+        assert_eq!(elves.step(), 0);
+        assert_eq!(elves.elves, start);
+    }
+
+    #[test]
+    fn test_simultaneous_moves() {
+        //Both elves step east at once, the second one's old place is free
+        let rules = Rules {
+            crowd: Rules::elves().crowd,
+            order: vec![Rule::new((1, 0), &[])],
+            rotate: false,
+        };
+        let mut elves = Elves::with_rules(HashSet::from([(0, 0), (1, 0)]), rules.clone());
+        assert_eq!(elves.step(), 2);
+        assert_eq!(elves.elves, HashSet::from([(1, 0), (2, 0)]));
+
+        //An elf that can't move blocks the one walking into it
+        let rules = Rules {
+            crowd: vec![(1, 0)],
+            ..rules
+        };
+        let mut elves = Elves::with_rules(HashSet::from([(0, 0), (1, 0)]), rules);
+        assert_eq!(elves.step(), 0);
+        assert_eq!(elves.elves.len(), 2);
+    }
 }
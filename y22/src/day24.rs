@@ -1,11 +1,15 @@
+use crate::answer::Answer;
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, VecDeque},
     fmt::Display,
+    str::FromStr,
 };
 
+//Positions are in map coordinates, walls included, so the valley floor runs
+//from (1, 1) to (width, height)
 type Pos = (usize, usize);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Up,
     Down,
@@ -13,301 +17,319 @@ enum Direction {
     Right,
 }
 
-#[derive(Debug, Clone)]
-struct Blizzard {
-    pos: Pos,
-    direction: Direction,
-    max: usize,
+impl Direction {
+    fn symbol(self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+            Direction::Right => '>',
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    NotRectangular,
+    MissingOpening(&'static str),
+    InvalidTile(char),
+    //A vertical blizzard in the entrance or exit column would leave the valley
+    BlizzardInOpening(Pos),
 }
 
-impl Blizzard {
-    fn new(pos: Pos, direction: Direction, max: usize) -> Self {
-        Self {
-            pos,
-            direction,
-            max,
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::NotRectangular => write!(f, "valley is not rectangular"),
+            ParseError::MissingOpening(side) => write!(f, "no opening in the {} wall", side),
+            ParseError::InvalidTile(c) => write!(f, "invalid tile: {}", c),
+            ParseError::BlizzardInOpening(pos) => {
+                write!(f, "vertical blizzard at {:?} would leave the valley", pos)
+            }
         }
     }
+}
 
-    fn step(&mut self) {
-        match self.direction {
-            Direction::Up => {
-                if self.pos.1 == 0 {
-                    self.pos = (self.pos.0, self.max - 1);
-                } else {
-                    self.pos = (self.pos.0, self.pos.1 - 1);
-                }
-            }
-            Direction::Down => {
-                if self.pos.1 == self.max - 1 {
-                    self.pos = (self.pos.0, 0);
-                } else {
-                    self.pos = (self.pos.0, self.pos.1 + 1);
-                }
-            }
-            Direction::Left => {
-                if self.pos.0 == 0 {
-                    self.pos = (self.max - 1, self.pos.1);
-                } else {
-                    self.pos = (self.pos.0 - 1, self.pos.1);
-                }
-            }
-            Direction::Right => {
-                if self.pos.0 == self.max - 1 {
-                    self.pos = (0, self.pos.1);
-                } else {
-                    self.pos = (self.pos.0 + 1, self.pos.1);
-                }
+#[derive(Debug, PartialEq, Eq)]
+enum PlanError {
+    InvalidWaypoint(Pos),
+    Unreachable { leg: usize, from: Pos, to: Pos },
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanError::InvalidWaypoint(pos) => write!(f, "{:?} is not an open tile", pos),
+            PlanError::Unreachable { leg, from, to } => {
+                write!(f, "leg {}: {:?} can not reach {:?}", leg, from, to)
             }
         }
     }
 }
 
-impl Display for Blizzard {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.pos)
+//One bit per tile of the valley floor
+#[derive(Debug, Clone)]
+struct BitGrid {
+    width: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    fn new(width: usize, height: usize) -> Self {
+        BitGrid {
+            width,
+            words: vec![0; (width * height).div_ceil(64)],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        let i = y * self.width + x;
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        let i = y * self.width + x;
+        self.words[i / 64] & (1 << (i % 64)) != 0
     }
 }
 
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Trip {
+    time: usize,
+    //Position at every minute, starting with the first waypoint at minute 0
+    route: Vec<Pos>,
+}
+
 #[derive(Debug)]
 struct Valley {
-    start_pos: Pos,
-    end_pos: Pos,
     width: usize,
     height: usize,
+    entrance: Pos,
+    exit: Pos,
+    blizzards: Vec<(Pos, Direction)>,
+    //Blizzards repeat after lcm(width, height) minutes
+    period: usize,
+    blocked: Vec<BitGrid>,
 }
 
 impl Valley {
-    fn new(start_pos: Pos, end_pos: Pos, width: usize, height: usize) -> Self {
-        Self {
-            start_pos,
-            end_pos,
+    fn new(
+        width: usize,
+        height: usize,
+        entrance: Pos,
+        exit: Pos,
+        blizzards: Vec<(Pos, Direction)>,
+    ) -> Self {
+        let period = width / gcd(width, height) * height;
+        let mut valley = Valley {
             width,
             height,
-        }
+            entrance,
+            exit,
+            blizzards,
+            period,
+            blocked: Vec::new(),
+        };
+        valley.blocked = (0..period)
+            .map(|t| {
+                let mut grid = BitGrid::new(width, height);
+                for &blizzard in &valley.blizzards {
+                    let (x, y) = valley.blizzard_at(blizzard, t);
+                    grid.set(x - 1, y - 1);
+                }
+                grid
+            })
+            .collect();
+        valley
     }
-}
 
-fn parse_input(str: &str) -> (Valley, Vec<Blizzard>) {
-    let width = str.lines().next().unwrap().len() - 2;
-    let height = str.lines().count() - 2;
-    let start_pos = (str.lines().next().unwrap().find('.').unwrap() - 1, 0);
-    let end_pos = (
-        str.lines().last().unwrap().find('.').unwrap() - 1,
-        height - 1,
-    );
-    let mut blizzards = Vec::new();
-
-    for (y, line) in str.lines().skip(1).enumerate() {
-        if width == 0 {}
-        for (x, c) in line.chars().skip(1).enumerate() {
-            match c {
-                '^' => {
-                    assert!(x != 0);
-                    blizzards.push(Blizzard {
-                        pos: (x, y),
-                        direction: Direction::Up,
-                        max: height,
-                    });
-                }
-                'v' => {
-                    assert!(x != 0);
-                    blizzards.push(Blizzard {
-                        pos: (x, y),
-                        direction: Direction::Down,
-                        max: height,
-                    });
-                }
-                '<' => {
-                    blizzards.push(Blizzard {
-                        pos: (x, y),
-                        direction: Direction::Left,
-                        max: width,
-                    });
-                }
-                '>' => {
-                    blizzards.push(Blizzard {
-                        pos: (x, y),
-                        direction: Direction::Right,
-                        max: width,
-                    });
-                }
-                _ => (),
-            }
-        }
+    fn blizzard_at(&self, ((x, y), direction): (Pos, Direction), t: usize) -> Pos {
+        let (w, h) = (self.width, self.height);
+        let (x, y) = (x - 1, y - 1);
+        let (x, y) = match direction {
+            Direction::Up => (x, (y + h - t % h) % h),
+            Direction::Down => (x, (y + t) % h),
+            Direction::Left => ((x + w - t % w) % w, y),
+            Direction::Right => ((x + t) % w, y),
+        };
+        (x + 1, y + 1)
     }
 
-    (Valley::new(start_pos, end_pos, width, height), blizzards)
-}
+    fn is_floor(&self, (x, y): Pos) -> bool {
+        (1..=self.width).contains(&x) && (1..=self.height).contains(&y)
+    }
 
-fn shortest_path(valley: &Valley, blizzards: &[Blizzard]) -> (usize, Vec<Blizzard>) {
-    let mut steps = 1;
-    let mut blizzards = blizzards.to_vec();
+    fn is_open(&self, pos: Pos) -> bool {
+        pos == self.entrance || pos == self.exit || self.is_floor(pos)
+    }
 
-    let mut queue = VecDeque::new();
-    let mut cache = HashSet::new();
+    fn is_free(&self, pos: Pos, t: usize) -> bool {
+        if !self.is_open(pos) {
+            return false;
+        }
+        !self.is_floor(pos) || !self.blocked[t % self.period].get(pos.0 - 1, pos.1 - 1)
+    }
 
-    for b in blizzards.iter_mut() {
-        b.step();
+    fn moves(&self, (x, y): Pos) -> impl Iterator<Item = Pos> + '_ {
+        [
+            Some((x, y)),
+            y.checked_sub(1).map(|y| (x, y)),
+            Some((x, y + 1)),
+            x.checked_sub(1).map(|x| (x, y)),
+            Some((x + 1, y)),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&pos| self.is_open(pos))
     }
-    while blizzards.iter().any(|b| b.pos == valley.start_pos) {
-        display_valley(&valley.start_pos, &valley, &blizzards);
-        println!("Wait to move into start pos");
-        for b in blizzards.iter_mut() {
-            b.step();
+
+    //Breadth first search over (position, time mod period), so waiting in
+    //place is bounded and a goal that can never be reached is detected
+    fn leg(&self, from: Pos, to: Pos, start: usize) -> Option<Vec<Pos>> {
+        let mut parents: HashMap<(Pos, usize), (Pos, usize)> = HashMap::new();
+        let mut queue = VecDeque::from([(from, start)]);
+        parents.insert((from, start % self.period), (from, start));
+
+        while let Some((pos, t)) = queue.pop_front() {
+            if pos == to {
+                let mut route = vec![pos];
+                let mut state = (pos, t);
+                while state.1 > start {
+                    state = parents[&(state.0, state.1 % self.period)];
+                    route.push(state.0);
+                }
+                route.reverse();
+                return Some(route);
+            }
+            for next in self.moves(pos) {
+                let key = (next, (t + 1) % self.period);
+                if self.is_free(next, t + 1) && !parents.contains_key(&key) {
+                    parents.insert(key, (pos, t));
+                    queue.push_back((next, t + 1));
+                }
+            }
         }
-        steps += 1;
+        None
     }
-    let path: Vec<Pos> = Vec::new();
-    queue.push_back((valley.start_pos, blizzards.clone(), steps, path.clone()));
-    println!("Start pos: {:?}", valley.start_pos);
-
-    // Add the possibility to wait up to 10 minutes before moving into the start pos
-    for _ in 0..10 {
-        for b in blizzards.iter_mut() {
-            b.step();
+
+    //Visits all waypoints in order, each leg starting when the last one ended
+    fn plan(&self, waypoints: &[Pos]) -> Result<Trip, PlanError> {
+        if let Some(&pos) = waypoints.iter().find(|&&pos| !self.is_open(pos)) {
+            return Err(PlanError::InvalidWaypoint(pos));
         }
-        steps += 1;
-        if !blizzards.iter().any(|b| b.pos == valley.start_pos) {
-            queue.push_back((valley.start_pos, blizzards.clone(), steps, path.clone()));
+        let mut route = waypoints.first().map_or(Vec::new(), |&pos| vec![pos]);
+        for (leg, pair) in waypoints.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            let time = route.len() - 1;
+            let steps = self
+                .leg(from, to, time)
+                .ok_or(PlanError::Unreachable { leg, from, to })?;
+            route.extend(&steps[1..]);
         }
+        Ok(Trip {
+            time: route.len().saturating_sub(1),
+            route,
+        })
     }
 
-    while let Some((pos, blizzards, steps, path)) = queue.pop_front() {
-        let mut blizzards = blizzards.clone();
-        let pos = pos.clone();
-
-        if cache.contains(&(pos, steps)) {
-            continue;
+    //The valley at minute t like in the puzzle, with the expedition as E
+    fn render(&self, t: usize, expedition: Option<Pos>) -> String {
+        let mut grid = vec![vec!['#'; self.width + 2]; self.height + 2];
+        for row in grid.iter_mut().take(self.height + 1).skip(1) {
+            row[1..=self.width].fill('.');
         }
-
-        // display_valley(&pos, &valley, &blizzards);
-        for b in blizzards.iter_mut() {
-            b.step();
+        grid[self.entrance.1][self.entrance.0] = '.';
+        grid[self.exit.1][self.exit.0] = '.';
+
+        let mut counts: HashMap<Pos, usize> = HashMap::new();
+        for &blizzard in &self.blizzards {
+            let (x, y) = self.blizzard_at(blizzard, t);
+            let count = counts.entry((x, y)).or_default();
+            *count += 1;
+            grid[y][x] = if *count == 1 {
+                blizzard.1.symbol()
+            } else {
+                char::from_digit(*count as u32, 36).unwrap_or('*')
+            };
         }
-
-        let mut new_pos = vec![
-            (pos.0 as i32, pos.1 as i32 - 1),
-            (pos.0 as i32, pos.1 as i32 + 1),
-            (pos.0 as i32 - 1, pos.1 as i32),
-            (pos.0 as i32 + 1, pos.1 as i32),
-            (pos.0 as i32, pos.1 as i32),
-        ];
-
-        let new_pos = new_pos
-            .iter_mut()
-            .filter(|p| {
-                p.0 >= 0
-                    && p.1 >= 0
-                    && p.0 <= valley.width as i32 - 1
-                    && p.1 <= valley.height as i32 - 1
-                    && !blizzards
-                        .iter()
-                        .any(|b| b.pos == (p.0 as usize, p.1 as usize))
-            })
-            .map(|p| (p.0 as usize, p.1 as usize))
-            .collect::<Vec<(usize, usize)>>();
-
-        if new_pos.contains(&valley.end_pos) {
-            for b in blizzards.iter_mut() {
-                b.step();
-            }
-            display_valley(&pos, valley, &blizzards);
-            println!("Found end");
-            println!("Path: {:?}", path);
-            return (steps + 2, blizzards);
+        if let Some((x, y)) = expedition {
+            grid[y][x] = 'E';
         }
 
-        for pos in new_pos {
-            let mut path = path.clone();
-            path.push(pos);
-            queue.push_back((pos, blizzards.clone(), steps + 1, path));
-        }
-
-        cache.insert((pos, steps));
+        grid.into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect()
     }
-
-    (0, blizzards)
 }
 
-fn display_valley(pos: &Pos, valley: &Valley, blizzards: &[Blizzard]) {
-    let mut valley_grid = vec![vec!['.'; valley.width]; valley.height];
-    let mut blizzard_pos = vec![vec![0; valley.width]; valley.height];
+impl FromStr for Valley {
+    type Err = ParseError;
 
-    valley_grid[pos.1][pos.0] = 'E';
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().collect::<Vec<_>>();
+        let full_width = lines.first().map_or(0, |line| line.len());
+        if lines.len() < 3 || full_width < 3 || lines.iter().any(|l| l.len() != full_width) {
+            return Err(ParseError::NotRectangular);
+        }
+        let (width, height) = (full_width - 2, lines.len() - 2);
 
-    for b in blizzards {
-        blizzard_pos[b.pos.1][b.pos.0] += 1;
-        valley_grid[b.pos.1][b.pos.0] = match b.direction {
-            Direction::Up => '^',
-            Direction::Down => 'v',
-            Direction::Left => '<',
-            Direction::Right => '>',
+        let opening = |line: &str, side| {
+            line.find('.')
+                .filter(|&x| x > 0 && x <= width)
+                .ok_or(ParseError::MissingOpening(side))
         };
-    }
-
-    for (x, line) in blizzard_pos.iter().enumerate() {
-        for (y, c) in line.iter().enumerate() {
-            if *c > 1 {
-                valley_grid[x][y] = blizzard_pos[x][y].to_string().chars().next().unwrap();
+        let entrance = (opening(lines[0], "top")?, 0);
+        let exit = (opening(lines[height + 1], "bottom")?, height + 1);
+
+        let mut blizzards = Vec::new();
+        for (y, line) in lines.iter().enumerate().take(height + 1).skip(1) {
+            for (x, c) in line.chars().enumerate().take(width + 1).skip(1) {
+                let direction = match c {
+                    '.' => continue,
+                    '^' => Direction::Up,
+                    'v' => Direction::Down,
+                    '<' => Direction::Left,
+                    '>' => Direction::Right,
+                    c => return Err(ParseError::InvalidTile(c)),
+                };
+                let vertical = matches!(direction, Direction::Up | Direction::Down);
+                if vertical && (x == entrance.0 || x == exit.0) {
+                    return Err(ParseError::BlizzardInOpening((x, y)));
+                }
+                blizzards.push(((x, y), direction));
             }
         }
+        Ok(Valley::new(width, height, entrance, exit, blizzards))
     }
-
-    for line in valley_grid {
-        println!("{}", line.iter().collect::<String>());
-    }
-    println!("");
 }
 
-fn part1() -> usize {
-    let input = include_str!("../input/24");
-
-    let (valley, blizzards) = parse_input(input);
-
-    println!("{:?}", valley);
-    println!("{:?}", blizzards);
-
-    display_valley(&(0, 0), &valley, &blizzards);
-
-    shortest_path(&valley, &blizzards).0
+//Time to visit the waypoints picked from the valley in order
+fn trip_time(input: &str, waypoints: fn(&Valley) -> Vec<Pos>) -> Result<usize, String> {
+    let valley: Valley = input.parse().map_err(|e: ParseError| e.to_string())?;
+    valley
+        .plan(&waypoints(&valley))
+        .map(|trip| trip.time)
+        .map_err(|e| e.to_string())
 }
 
-fn part2() -> usize {
-    let input = include_str!("../input/24");
-    // let input = include_str!("../input/test24_1");
-
-    let (mut valley, blizzards) = parse_input(input);
-
-    println!("{:?}", valley);
-    println!("{:?}", blizzards);
-
-    let mut total_steps = 0;
-    let start_pos = valley.start_pos;
-    let end_pos = valley.end_pos;
-
-    let (steps_to_goal, blizzards) = shortest_path(&valley, &blizzards);
-    total_steps += steps_to_goal;
-    println!("Steps to goal: {}", steps_to_goal);
-
-    valley.end_pos = start_pos;
-    valley.start_pos = end_pos;
-    let (steps_back, blizzards) = shortest_path(&valley, &blizzards);
-    total_steps += steps_back;
-    println!("Steps back: {}", steps_back);
-
-    valley.end_pos = end_pos;
-    valley.start_pos = start_pos;
-    let (steps_to_goal, _) = shortest_path(&valley, &blizzards);
-    total_steps += steps_to_goal;
-    println!("Steps to goal: {}", steps_to_goal);
-
-    total_steps
+pub fn part1(input: &str) -> Answer {
+    trip_time(input, |valley| vec![valley.entrance, valley.exit]).into()
 }
 
-pub fn run() {
-    println!("Part 1: {}", part1());
-    println!("Part 2: {}", part2());
+pub fn part2(input: &str) -> Answer {
+    trip_time(input, |valley| {
+        let (there, back) = (valley.exit, valley.entrance);
+        vec![back, there, back, there]
+    })
+    .into()
 }
 
 #[cfg(test)]
@@ -315,21 +337,106 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_blizzard() {
+    fn test_example() {
         let input = include_str!("../input/test24_1");
+        assert_eq!(part1(input), Answer::from(18));
+        assert_eq!(part2(input), Answer::from(54));
+    }
 
-        let (valley, mut blizzards) = parse_input(input);
+    #[test]
+    fn test_route() {
+        let valley: Valley = include_str!("../input/test24_1").parse().unwrap();
+        assert_eq!(valley.period, 12);
+
+        let trip = valley.plan(&[valley.entrance, valley.exit]).unwrap();
+        assert_eq!(trip.route.len(), 19);
+        assert_eq!(trip.route[0], (1, 0));
+        assert_eq!(trip.route[18], (6, 5));
+        for (t, pair) in trip.route.windows(2).enumerate() {
+            let (a, b) = (pair[0], pair[1]);
+            assert!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1) <= 1);
+            assert!(valley.is_free(b, t + 1), "minute {} at {:?}", t + 1, b);
+        }
+        assert_eq!(
+            valley.render(18, Some(trip.route[18])),
+            "\
+#.######
+#>2.<.<#
+#.2v^2<#
+#>..>2>#
+#<....>#
+######E#
+"
+        );
+    }
 
-        println!("{:?}", valley);
-        display_valley(&(0, 0), &valley, &blizzards);
-        println!("");
+    #[test]
+    fn test_blizzard() {
+        let valley: Valley = include_str!("../input/test24").parse().unwrap();
+        assert_eq!(valley.period, 5);
+        let frames = (0..=5).map(|t| valley.render(t, None)).collect::<Vec<_>>();
+        assert_eq!(
+            frames[1],
+            "\
+#.#####
+#.....#
+#.>...#
+#.....#
+#.....#
+#...v.#
+#####.#
+"
+        );
+        assert_eq!(
+            frames[3],
+            "\
+#.#####
+#.....#
+#...2.#
+#.....#
+#.....#
+#.....#
+#####.#
+"
+        );
+        assert_eq!(frames[5], frames[0]);
+    }
 
-        for _ in 0..18 {
-            for b in blizzards.iter_mut() {
-                b.step();
-            }
-            display_valley(&(0, 0), &valley, &blizzards);
-            println!("");
-        }
+    #[test]
+    fn test_unreachable() {
+        let valley: Valley = "#.###\n#>>>#\n#...#\n###.#".parse().unwrap();
+        assert_eq!(
+            valley.plan(&[valley.entrance, valley.exit]),
+            Err(PlanError::Unreachable {
+                leg: 0,
+                from: (1, 0),
+                to: (3, 3)
+            })
+        );
+        assert_eq!(
+            valley.plan(&[valley.entrance, (0, 1)]),
+            Err(PlanError::InvalidWaypoint((0, 1)))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("#.###\n#.^.#\n###.#".parse::<Valley>().is_ok());
+        assert_eq!(
+            "#.###\n#^..#\n###.#".parse::<Valley>().err(),
+            Some(ParseError::BlizzardInOpening((1, 1)))
+        );
+        assert_eq!(
+            "#####\n#...#\n###.#".parse::<Valley>().err(),
+            Some(ParseError::MissingOpening("top"))
+        );
+        assert_eq!(
+            "#.###\n#.x.#\n###.#".parse::<Valley>().err(),
+            Some(ParseError::InvalidTile('x'))
+        );
+        assert_eq!(
+            part1("#.###\n#.x.#\n###.#").to_string(),
+            "unsolved (invalid tile: x)"
+        );
     }
 }
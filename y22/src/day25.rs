@@ -1,83 +1,396 @@
-fn snafu_char_to_i64(c: char) -> i64 {
-    match c {
-        '2' => 2,
-        '1' => 1,
-        '0' => 0,
-        '-' => -1,
-        '=' => -2,
-        _ => panic!("Invalid char: {}", c),
+use crate::answer::Answer;
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    iter::Sum,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+//Arbitrary precision number in a balanced odd base, digits run from -BASE/2 to BASE/2
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+struct Balanced<const BASE: u32> {
+    //Least significant digit first, without leading zeros
+    digits: Vec<i8>,
+}
+
+type Snafu = Balanced<5>;
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseBalancedError {
+    Empty,
+    InvalidDigit(char),
+    InvalidSymbols(usize),
+}
+
+impl Display for ParseBalancedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseBalancedError::Empty => write!(f, "empty number"),
+            ParseBalancedError::InvalidDigit(c) => write!(f, "invalid digit: {:?}", c),
+            ParseBalancedError::InvalidSymbols(n) => {
+                write!(f, "expected one symbol per digit, got {}", n)
+            }
+        }
+    }
+}
+
+impl<const BASE: u32> Balanced<BASE> {
+    const HALF: i64 = {
+        //The default symbols run out of lowercase letters past -28
+        assert!(BASE % 2 == 1 && BASE >= 3 && BASE <= 57);
+        (BASE / 2) as i64
+    };
+
+    fn zero() -> Self {
+        Balanced { digits: Vec::new() }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    fn signum(&self) -> i8 {
+        self.digits.last().map_or(0, |d| d.signum())
+    }
+
+    //SNAFU style symbols ('=', '-', '0', '1', '2') in the middle, then letters
+    //for digits further from zero: lowercase for negative, uppercase for positive
+    fn default_symbols() -> Vec<char> {
+        (-Self::HALF..=Self::HALF)
+            .map(|d| match d {
+                -2 => '=',
+                -1 => '-',
+                0..=9 => char::from_digit(d as u32, 10).unwrap(),
+                d if d > 0 => (b'A' + (d - 10) as u8) as char,
+                d => (b'a' + (-d - 3) as u8) as char,
+            })
+            .collect()
+    }
+
+    //Carry each position into the next until every digit is in range
+    fn from_wide(mut wide: Vec<i64>) -> Self {
+        let base = BASE as i64;
+        let mut i = 0;
+        while i < wide.len() {
+            let carry = (wide[i] + Self::HALF).div_euclid(base);
+            wide[i] -= carry * base;
+            if carry != 0 {
+                if i + 1 == wide.len() {
+                    wide.push(0);
+                }
+                wide[i + 1] += carry;
+            }
+            i += 1;
+        }
+        while wide.last() == Some(&0) {
+            wide.pop();
+        }
+        Balanced {
+            digits: wide.into_iter().map(|d| d as i8).collect(),
+        }
+    }
+
+    fn from_symbols(s: &str, symbols: &[char]) -> Result<Self, ParseBalancedError> {
+        if symbols.len() != BASE as usize {
+            return Err(ParseBalancedError::InvalidSymbols(symbols.len()));
+        }
+        if s.is_empty() {
+            return Err(ParseBalancedError::Empty);
+        }
+        let wide = s
+            .chars()
+            .rev()
+            .map(|c| {
+                symbols
+                    .iter()
+                    .position(|&symbol| symbol == c)
+                    .map(|i| i as i64 - Self::HALF)
+                    .ok_or(ParseBalancedError::InvalidDigit(c))
+            })
+            .collect::<Result<Vec<i64>, ParseBalancedError>>()?;
+        Ok(Self::from_wide(wide))
+    }
+
+    fn to_symbols(&self, symbols: &[char]) -> String {
+        assert_eq!(symbols.len(), BASE as usize);
+        if self.is_zero() {
+            return symbols[Self::HALF as usize].to_string();
+        }
+        self.digits
+            .iter()
+            .rev()
+            .map(|&d| symbols[(d as i64 + Self::HALF) as usize])
+            .collect()
+    }
+
+    fn to_i128(&self) -> Option<i128> {
+        self.digits.iter().rev().try_fold(0i128, |acc, &d| {
+            acc.checked_mul(BASE as i128)?.checked_add(d as i128)
+        })
+    }
+}
+
+impl<const BASE: u32> From<i64> for Balanced<BASE> {
+    fn from(value: i64) -> Self {
+        //Split into digits first so i64::MIN does not overflow on negation
+        let base = BASE as i64;
+        let mut wide = Vec::new();
+        let mut value = value;
+        while value != 0 {
+            wide.push(value % base);
+            value /= base;
+        }
+        Self::from_wide(wide)
+    }
+}
+
+impl<const BASE: u32> FromStr for Balanced<BASE> {
+    type Err = ParseBalancedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_symbols(s, &Self::default_symbols())
+    }
+}
+
+impl<const BASE: u32> Display for Balanced<BASE> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_symbols(&Self::default_symbols()))
+    }
+}
+
+impl<const BASE: u32> Add for Balanced<BASE> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let len = self.digits.len().max(other.digits.len());
+        let wide = (0..len)
+            .map(|i| {
+                let a = self.digits.get(i).copied().unwrap_or(0);
+                let b = other.digits.get(i).copied().unwrap_or(0);
+                a as i64 + b as i64
+            })
+            .collect();
+        Self::from_wide(wide)
+    }
+}
+
+impl<const BASE: u32> Neg for Balanced<BASE> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Balanced {
+            digits: self.digits.into_iter().map(|d| -d).collect(),
+        }
     }
 }
 
-fn i64_from_snafu(s: &str) -> i64 {
-    s.chars().rev().enumerate().fold(0, |acc, (i, c)| {
-        acc + snafu_char_to_i64(c) * 5_i64.pow(i as u32)
-    })
+impl<const BASE: u32> Sub for Balanced<BASE> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
 }
 
-fn snafu_from_i64(num: i64) -> String {
-    let mut num = num;
-    let mut s = String::new();
+impl<const BASE: u32> Mul for Balanced<BASE> {
+    type Output = Self;
 
-    if num == 0 {
-        return "0".to_string();
+    fn mul(self, other: Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Self::zero();
+        }
+        let mut wide = vec![0i64; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            for (j, &b) in other.digits.iter().enumerate() {
+                wide[i + j] += a as i64 * b as i64;
+            }
+        }
+        Self::from_wide(wide)
     }
+}
 
-    while num != 0 {
-        let rem = (num + 2) % 5 - 2;
-        num = (num - rem) / 5;
+impl<const BASE: u32> Ord for Balanced<BASE> {
+    //A longer number always has the larger magnitude, and for equal lengths
+    //the first differing digit from the top decides
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.signum().cmp(&other.signum()).then_with(|| {
+            let by_len = self.digits.len().cmp(&other.digits.len());
+            let by_len = if self.signum() < 0 {
+                by_len.reverse()
+            } else {
+                by_len
+            };
+            by_len.then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+        })
+    }
+}
 
-        let c = match rem {
-            2 => '2',
-            1 => '1',
-            0 => '0',
-            -1 => '-',
-            -2 => '=',
-            _ => panic!("Invalid rem: {}", rem),
-        };
-        s.push(c);
+impl<const BASE: u32> PartialOrd for Balanced<BASE> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
-    s.chars().rev().collect()
 }
 
-fn part1() {
-    let input = include_str!("../input/25");
+impl<const BASE: u32> Sum for Balanced<BASE> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, x| acc + x)
+    }
+}
 
-    let sum = input
-        .lines()
-        .fold(0, |acc, line| acc + i64_from_snafu(line));
+fn snafu_sum(input: &str) -> Result<Snafu, ParseBalancedError> {
+    input.lines().map(|line| line.parse::<Snafu>()).sum()
+}
 
-    println!("sum: {}", sum);
-    println!("snafu sum: {}", snafu_from_i64(sum));
+pub fn part1(input: &str) -> Answer {
+    snafu_sum(input).map(|sum| sum.to_string()).into()
 }
 
-pub fn run() {
-    println!("Day 25");
-    part1();
+//The last day only has one puzzle
+pub fn part2(_input: &str) -> Answer {
+    Answer::Unsolved("day 25 has no second part".to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    //Small xorshift generator so the property tests are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> i64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as i64
+        }
+    }
+
+    fn snafu(s: &str) -> Snafu {
+        s.parse().unwrap()
+    }
+
     #[test]
     fn test_from_snafu() {
-        assert!(i64_from_snafu("0") == 0);
-        assert!(i64_from_snafu("1") == 1);
-        assert!(i64_from_snafu("2") == 2);
-        assert!(i64_from_snafu("-") == -1);
-        assert!(i64_from_snafu("1=-0-2") == 1747);
+        assert_eq!(snafu("0").to_i128(), Some(0));
+        assert_eq!(snafu("1").to_i128(), Some(1));
+        assert_eq!(snafu("2").to_i128(), Some(2));
+        assert_eq!(snafu("-").to_i128(), Some(-1));
+        assert_eq!(snafu("1=-0-2").to_i128(), Some(1747));
     }
 
     #[test]
     fn test_to_snafu() {
-        assert!(snafu_from_i64(0) == "0");
-        assert!(snafu_from_i64(1) == "1");
-        assert!(snafu_from_i64(2) == "2");
-        assert!(snafu_from_i64(-1) == "-");
-        assert!(snafu_from_i64(10) == "20");
-        assert!(snafu_from_i64(20) == "1-0");
-        assert!(snafu_from_i64(25) == "100");
-        assert!(snafu_from_i64(1747) == "1=-0-2");
+        assert_eq!(Snafu::from(0).to_string(), "0");
+        assert_eq!(Snafu::from(1).to_string(), "1");
+        assert_eq!(Snafu::from(2).to_string(), "2");
+        assert_eq!(Snafu::from(-1).to_string(), "-");
+        assert_eq!(Snafu::from(10).to_string(), "20");
+        assert_eq!(Snafu::from(20).to_string(), "1-0");
+        assert_eq!(Snafu::from(25).to_string(), "100");
+        assert_eq!(Snafu::from(1747).to_string(), "1=-0-2");
+    }
+
+    #[test]
+    fn test_part1() {
+        let input = "1=-0-2\n12111\n2=0=\n21\n2=01\n111\n20012\n112\n1=-1=\n1-12\n12\n1=\n122";
+        assert_eq!(snafu_sum(input).unwrap().to_string(), "2=-1=0");
+        assert_eq!(
+            snafu_sum("1=\n2x\n"),
+            Err(ParseBalancedError::InvalidDigit('x'))
+        );
+        assert_eq!(part1("1=\n\n").to_string(), "unsolved (empty number)");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<Snafu>(), Err(ParseBalancedError::Empty));
+        assert_eq!(
+            "1=3".parse::<Snafu>(),
+            Err(ParseBalancedError::InvalidDigit('3'))
+        );
+        assert_eq!(
+            Snafu::from_symbols("1", &['0', '1']),
+            Err(ParseBalancedError::InvalidSymbols(2))
+        );
+        //Leading zeros are dropped
+        assert_eq!(snafu("0012").to_string(), "12");
+    }
+
+    #[test]
+    fn test_custom_symbols() {
+        let ternary = ['T', '0', '1'];
+        let x = Balanced::<3>::from_symbols("1T0", &ternary).unwrap();
+        assert_eq!(x.to_i128(), Some(6));
+        assert_eq!(x.to_symbols(&ternary), "1T0");
+        assert_eq!(x.to_string(), "1-0");
+
+        let x = Balanced::<7>::from(-3 * 49 + 2 * 7 + 3);
+        assert_eq!(x.to_string(), "a23");
+        assert_eq!("a23".parse::<Balanced<7>>(), Ok(x));
+    }
+
+    #[test]
+    fn test_no_overflow() {
+        let big = Snafu::from(i64::MAX);
+        let sum: Snafu = (0..5).map(|_| big.clone()).sum();
+        assert_eq!(sum.to_i128(), Some(5 * i64::MAX as i128));
+        assert_eq!(
+            (big.clone() * big.clone()).to_i128(),
+            Some(i64::MAX as i128 * i64::MAX as i128)
+        );
+
+        let huge = (0..10).fold(Snafu::from(1), |acc, _| acc * big.clone());
+        assert_eq!(huge.to_i128(), None);
+        assert_eq!(
+            (huge.clone() - huge.clone() + Snafu::from(7)).to_i128(),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for x in -5000..5000 {
+            let snafu = Snafu::from(x);
+            assert_eq!(snafu.to_i128(), Some(x as i128));
+            assert_eq!(snafu.to_string().parse::<Snafu>(), Ok(snafu.clone()));
+
+            let ternary = Balanced::<3>::from(x);
+            assert_eq!(ternary.to_i128(), Some(x as i128));
+        }
+        for x in [i64::MIN, i64::MAX] {
+            assert_eq!(Snafu::from(x).to_i128(), Some(x as i128));
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_matches_integers() {
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        for _ in 0..2000 {
+            let a = rng.next() >> 16;
+            let b = rng.next() >> 16;
+            let (x, y) = (Snafu::from(a), Snafu::from(b));
+            let (a, b) = (a as i128, b as i128);
+
+            assert_eq!((x.clone() + y.clone()).to_i128(), Some(a + b));
+            assert_eq!((x.clone() - y.clone()).to_i128(), Some(a - b));
+            assert_eq!((x.clone() * y.clone()).to_i128(), Some(a * b));
+            assert_eq!((-x.clone()).to_i128(), Some(-a));
+            assert_eq!(x.cmp(&y), a.cmp(&b));
+        }
+    }
+
+    #[test]
+    fn test_widest_base_symbols() {
+        let symbols = Balanced::<57>::default_symbols();
+        assert!(symbols
+            .iter()
+            .all(|c| c.is_ascii_alphanumeric() || *c == '=' || *c == '-'));
+        assert_eq!(symbols.first(), Some(&'z'));
+        assert_eq!(symbols.last(), Some(&'S'));
+        let x = Balanced::<57>::from(-28 * 57 * 57 + 28);
+        assert_eq!(x.to_string(), "z0S");
+        assert_eq!("z0S".parse::<Balanced<57>>(), Ok(x));
     }
 }
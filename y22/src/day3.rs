@@ -1,146 +1,334 @@
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+use crate::answer::Answer;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RucksackError {
+    InvalidItem { line: usize, item: char },
+    OddLength { line: usize, len: usize },
+    //Compartments should share exactly one item
+    SharedItems { line: usize, count: u32 },
+    //A group should carry exactly one common item
+    Badges { group: usize, count: u32 },
+    IncompleteGroup { group: usize, size: usize },
+    GroupSize(usize),
+}
+
+impl Display for RucksackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RucksackError::InvalidItem { line, item } => {
+                write!(f, "invalid item {:?} on line {}", item, line)
+            }
+            RucksackError::OddLength { line, len } => {
+                write!(
+                    f,
+                    "rucksack on line {} has an odd number of items ({})",
+                    line, len
+                )
+            }
+            RucksackError::SharedItems { line, count } => write!(
+                f,
+                "compartments on line {} share {} items, expected 1",
+                line, count
+            ),
+            RucksackError::Badges { group, count } => {
+                write!(f, "group {} has {} common items, expected 1", group, count)
+            }
+            RucksackError::IncompleteGroup { group, size } => {
+                write!(f, "group {} only has {} rucksacks", group, size)
+            }
+            RucksackError::GroupSize(size) => write!(f, "invalid group size: {}", size),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct Priority(u32);
 
 impl Priority {
-    fn new(c: char) -> Self {
-        let priority = match c {
-            'a'..='z' => c as u32 - 'a' as u32 + 1,
-            'A'..='Z' => c as u32 - 'A' as u32 + 1 + 26,
-            _ => panic!("Invalid character: {}", c),
-        };
-        Priority(priority)
+    fn new(c: char) -> Option<Self> {
+        match c {
+            'a'..='z' => Some(Priority(c as u32 - 'a' as u32 + 1)),
+            'A'..='Z' => Some(Priority(c as u32 - 'A' as u32 + 1 + 26)),
+            _ => None,
+        }
     }
 }
 
-impl Into<u32> for Priority {
-    fn into(self) -> u32 {
-        self.0
+impl From<Priority> for u32 {
+    fn from(priority: Priority) -> u32 {
+        priority.0
     }
 }
 
-fn get_missplaced_item(s: &str) -> Option<char> {
-    let mut array: [bool; 53] = [false; 53];
+//Set of items where bit n is the item with priority n
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    const ALL: ItemSet = ItemSet(!0);
 
-    //iterate over the first half of the string and count the number of each letter
-    for c in s.chars().take(s.len() / 2) {
-        let priority = Priority::new(c);
-        array[priority.0 as usize] = true;
+    fn from_items(items: &str, line: usize) -> Result<ItemSet, RucksackError> {
+        items.chars().try_fold(ItemSet(0), |set, item| {
+            let priority = Priority::new(item).ok_or(RucksackError::InvalidItem { line, item })?;
+            Ok(ItemSet(set.0 | 1 << priority.0))
+        })
     }
-    for c in s.chars().skip(s.len() / 2) {
-        let priority = Priority::new(c);
-        if array[priority.0 as usize] {
-            return Some(c);
-        }
+
+    fn intersect(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
     }
-    None
-}
 
-fn find_badge_item_priority(s1: &str, s2: &str, s3: &str) -> Option<u32> {
-    let mut array: [u8; 53] = [0; 53];
+    fn union(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
 
-    println!("{} {} {}", s1, s2, s3);
-    for c in s1.chars() {
-        let priority = Priority::new(c);
-        array[priority.0 as usize] = 1;
+    fn len(self) -> u32 {
+        self.0.count_ones()
     }
 
-    for c in s2.chars() {
-        let priority = Priority::new(c);
-        if array[priority.0 as usize] == 1 {
-            array[priority.0 as usize] = 2;
-        }
+    //The priority of the only item in the set
+    fn single(self) -> Option<Priority> {
+        (self.len() == 1).then(|| Priority(self.0.trailing_zeros()))
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rucksack {
+    line: usize,
+    compartments: [ItemSet; 2],
+}
 
-    for c in s3.chars() {
-        let priority = Priority::new(c);
-        if array[priority.0 as usize] == 2 {
-            return Some(priority.0);
+impl Rucksack {
+    fn parse(items: &str, line: usize) -> Result<Rucksack, RucksackError> {
+        //Every item is checked first, after that they are all one byte long
+        ItemSet::from_items(items, line)?;
+        let len = items.len();
+        if !len.is_multiple_of(2) {
+            return Err(RucksackError::OddLength { line, len });
         }
+        let (left, right) = items.split_at(items.len() / 2);
+        Ok(Rucksack {
+            line,
+            compartments: [
+                ItemSet::from_items(left, line)?,
+                ItemSet::from_items(right, line)?,
+            ],
+        })
+    }
+
+    fn items(&self) -> ItemSet {
+        self.compartments[0].union(self.compartments[1])
     }
 
-    None
+    fn misplaced(&self) -> Result<Priority, RucksackError> {
+        let shared = self.compartments[0].intersect(self.compartments[1]);
+        shared.single().ok_or(RucksackError::SharedItems {
+            line: self.line,
+            count: shared.len(),
+        })
+    }
+}
+
+//The item every rucksack of the group carries
+fn badge(group: &[Rucksack], index: usize) -> Result<Priority, RucksackError> {
+    let common = group.iter().fold(ItemSet::ALL, |common, rucksack| {
+        common.intersect(rucksack.items())
+    });
+    common.single().ok_or(RucksackError::Badges {
+        group: index + 1,
+        count: common.len(),
+    })
 }
 
-pub fn run() {
-    println!("Day 3");
-    let file = std::fs::read_to_string("input/3").unwrap();
-    let lines: Vec<&str> = file.split("\n").collect();
+fn parse_input(input: &str) -> Result<Vec<Rucksack>, RucksackError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| Rucksack::parse(line.trim_end(), i + 1))
+        .collect()
+}
 
-    let mut sum: u32 = 0;
-    let mut badge_sum: u32 = 0;
+fn misplaced_total(rucksacks: &[Rucksack]) -> Result<u32, RucksackError> {
+    rucksacks
+        .iter()
+        .map(|rucksack| rucksack.misplaced().map(u32::from))
+        .sum()
+}
 
-    for line in &lines {
-        println!("{}", line);
-        if let Some(c) = get_missplaced_item(line) {
-            sum += Priority::new(c).0;
-            println!("{}: {}", c, Priority::new(c).0);
-        }
+fn badge_total(rucksacks: &[Rucksack], group_size: usize) -> Result<u32, RucksackError> {
+    if group_size == 0 {
+        return Err(RucksackError::GroupSize(group_size));
     }
-    println!("Sum: {}", sum);
-
-    let mut count = 0;
-    //iterate 3 and 3 lines at a time
-    for i in (0..lines.len() - 1).step_by(3) {
-        let s1 = lines[i];
-        let s2 = lines[i + 1];
-        let s3 = lines[i + 2];
-        if let Some(priority) = find_badge_item_priority(s1, s2, s3) {
-            badge_sum += priority;
-            println!("{}: {}", i, priority);
-        }
+    let leftover = rucksacks.len() % group_size;
+    if leftover != 0 {
+        return Err(RucksackError::IncompleteGroup {
+            group: rucksacks.len() / group_size + 1,
+            size: leftover,
+        });
+    }
+    rucksacks
+        .chunks(group_size)
+        .enumerate()
+        .map(|(index, group)| badge(group, index).map(u32::from))
+        .sum()
+}
 
-        count += 1;
+//Both totals fail on their own, part 1 doesn't care about the groups and
+//part 2 doesn't care about the compartments
+#[derive(Debug, PartialEq, Eq)]
+struct Totals {
+    misplaced: Result<u32, RucksackError>,
+    badges: Result<u32, RucksackError>,
+}
+
+fn priorities(input: &str, group_size: usize) -> Totals {
+    match parse_input(input) {
+        Ok(rucksacks) => Totals {
+            misplaced: misplaced_total(&rucksacks),
+            badges: badge_total(&rucksacks, group_size),
+        },
+        Err(e) => Totals {
+            misplaced: Err(e.clone()),
+            badges: Err(e),
+        },
     }
-    println!("Badge sum: {}", badge_sum);
-    println!("Count: {}", count);
+}
+
+pub fn part1(input: &str) -> Answer {
+    priorities(input, 3).misplaced.into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    priorities(input, 3).badges.into()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    const EXAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw
+";
+
+    fn misplaced(items: &str) -> u32 {
+        Rucksack::parse(items, 1)
+            .unwrap()
+            .misplaced()
+            .unwrap()
+            .into()
+    }
+
     #[test]
     fn test() {
-        assert_eq!(1u32, Priority::new('a').into());
-        assert_eq!(16u32, Priority::new('p').into());
+        assert_eq!(Some(Priority(1)), Priority::new('a'));
+        assert_eq!(16u32, Priority::new('p').unwrap().into());
+        assert_eq!(None, Priority::new('1'));
     }
     #[test]
     fn test_capital() {
-        assert_eq!(38u32, Priority::new('L').into());
+        assert_eq!(38u32, Priority::new('L').unwrap().into());
     }
 
     #[test]
     fn test_missplaced() {
-        let mut s = "vJrwpWtwJgWrhcsFMMfFFhFp";
-        assert_eq!(Some('p'), get_missplaced_item(s));
-
-        s = "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL";
-        assert_eq!(Some('L'), get_missplaced_item(s));
-
-        s = "PmmdzqPrVvPwwTWBwg";
-        assert_eq!(Some('P'), get_missplaced_item(s));
-
-        s = "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn";
-        assert_eq!(Some('v'), get_missplaced_item(s));
-
-        s = "ttgJtRGJQctTZtZT";
-        assert_eq!(Some('t'), get_missplaced_item(s));
+        assert_eq!(16, misplaced("vJrwpWtwJgWrhcsFMMfFFhFp"));
+        assert_eq!(38, misplaced("jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL"));
+        assert_eq!(42, misplaced("PmmdzqPrVvPwwTWBwg"));
+        assert_eq!(22, misplaced("wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn"));
+        assert_eq!(20, misplaced("ttgJtRGJQctTZtZT"));
+        assert_eq!(19, misplaced("CrZsJsPPZsGzwwsLwLmpwMDw"));
+    }
 
-        s = "CrZsJsPPZsGzwwsLwLmpwMDw";
-        assert_eq!(Some('s'), get_missplaced_item(s));
+    #[test]
+    fn test_parts() {
+        assert_eq!(
+            priorities(EXAMPLE, 3),
+            Totals {
+                misplaced: Ok(157),
+                badges: Ok(70)
+            }
+        );
+        assert_eq!(part1(EXAMPLE), Answer::from(157));
+        assert_eq!(part2(EXAMPLE), Answer::from(70));
+        assert_eq!(part2(EXAMPLE.trim_end()), Answer::from(70));
     }
 
     #[test]
     fn test_group_badges() {
-        let s1 = "vJrwpWtwJgWrhcsFMMfFFhFp";
-        let s2 = "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL";
-        let s3 = "PmmdzqPrVvPwwTWBwg";
+        let rucksacks = parse_input(EXAMPLE).unwrap();
+        assert_eq!(badge(&rucksacks[..3], 0), Ok(Priority(18)));
+        assert_eq!(badge(&rucksacks[3..], 1), Ok(Priority(52)));
+    }
 
-        assert_eq!(Some(18), find_badge_item_priority(s1, s2, s3));
+    #[test]
+    fn test_group_sizes() {
+        let input = "abca\nadbd\naeef\n";
+        assert_eq!(priorities(input, 3).badges.unwrap(), 1);
+        assert_eq!(
+            priorities(input, 1).badges,
+            Err(RucksackError::Badges { group: 1, count: 3 })
+        );
+        assert_eq!(
+            priorities(input, 2).badges,
+            Err(RucksackError::IncompleteGroup { group: 2, size: 1 })
+        );
+        assert_eq!(
+            priorities("abca\nadbd\n", 2).badges,
+            Err(RucksackError::Badges { group: 1, count: 2 })
+        );
+        assert_eq!(
+            priorities(EXAMPLE, 4).badges,
+            Err(RucksackError::IncompleteGroup { group: 2, size: 2 })
+        );
+        assert_eq!(
+            priorities(EXAMPLE, 0).badges,
+            Err(RucksackError::GroupSize(0))
+        );
+    }
 
-        let s1 = "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn";
-        let s2 = "ttgJtRGJQctTZtZT";
-        let s3 = "CrZsJsPPZsGzwwsLwLmpwMDw";
+    #[test]
+    fn test_parts_fail_separately() {
+        //One rucksack isn't a full group, but it still has a misplaced item
+        assert_eq!(part1("abca\n"), Answer::from(1));
+        assert_eq!(
+            part2("abca\n").to_string(),
+            "unsolved (group 1 only has 1 rucksacks)"
+        );
+        //A badge doesn't need its rucksacks' compartments to share an item
+        assert!(part1("abcd\naefa\nagha\n")
+            .to_string()
+            .starts_with("unsolved"));
+        assert_eq!(part2("abcd\naefa\nagha\n"), Answer::from(1));
+    }
 
-        assert_eq!(Some(52), find_badge_item_priority(s1, s2, s3));
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            priorities("abca\nab1a\n", 1).badges,
+            Err(RucksackError::InvalidItem { line: 2, item: '1' })
+        );
+        assert_eq!(
+            priorities("aé\n", 1).badges,
+            Err(RucksackError::InvalidItem {
+                line: 1, item: 'é'
+            })
+        );
+        assert_eq!(
+            priorities("abc\n", 1).badges,
+            Err(RucksackError::OddLength { line: 1, len: 3 })
+        );
+        assert_eq!(
+            priorities("abcd\n", 1).misplaced,
+            Err(RucksackError::SharedItems { line: 1, count: 0 })
+        );
+        assert_eq!(
+            part1("abab\n").to_string(),
+            "unsolved (compartments on line 1 share 2 items, expected 1)"
+        );
     }
 }
@@ -1,103 +1,366 @@
-fn fully_overlapping_ranges(a: (u32, u32), b: (u32, u32)) -> bool {
-    let (a1, a2) = a;
-    let (b1, b2) = b;
+use crate::answer::Answer;
+use std::{fmt::Display, str::FromStr};
 
-    if a1 <= b1 && a2 >= b2 || b1 <= a1 && b2 >= a2 {
-        return true;
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    MissingComma(String),
+    InvalidRange(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingComma(line) => write!(f, "missing comma in pair: {}", line),
+            ParseError::InvalidRange(range) => write!(f, "invalid range: {}", range),
+        }
+    }
+}
+
+//Sections start..=end assigned to one elf
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Assignment {
+    start: u32,
+    end: u32,
+}
+
+impl Assignment {
+    fn new(start: u32, end: u32) -> Assignment {
+        Assignment { start, end }
+    }
+
+    //0-u32::MAX has one more section than a u32 holds
+    fn len(&self) -> u64 {
+        u64::from(self.end - self.start) + 1
+    }
+
+    fn contains(&self, other: &Assignment) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    //Number of sections both elves were assigned
+    fn overlap(&self, other: &Assignment) -> u64 {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        if start <= end {
+            Assignment::new(start, end).len()
+        } else {
+            0
+        }
     }
-    false
 }
 
-fn overlapping_ranges(a: (u32, u32), b: (u32, u32)) -> bool {
-    let (a1, a2) = a;
-    let (b1, b2) = b;
+impl FromStr for Assignment {
+    type Err = ParseError;
 
-    if a1 <= b1 && a2 >= b1 || b1 <= a1 && b2 >= a1 {
-        return true;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::InvalidRange(s.to_string());
+        let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+        let start = start.parse().map_err(|_| invalid())?;
+        let end = end.parse().map_err(|_| invalid())?;
+        if start > end {
+            return Err(invalid());
+        }
+        Ok(Assignment::new(start, end))
     }
+}
 
-    false
+impl Display for Assignment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
 }
 
-pub fn run() {
-    println!("Day 4");
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pair(Assignment, Assignment);
 
-    let sum = include_str!("../input/4")
-        .lines()
-        .map(|line| {
-            println!("{}", line);
-            let mut parts = line.split(',');
-            let first = parts.next().unwrap().split('-').collect::<Vec<_>>();
-            let second = parts.next().unwrap().split('-').collect::<Vec<_>>();
+impl Pair {
+    fn fully_overlapping(&self) -> bool {
+        self.0.contains(&self.1) || self.1.contains(&self.0)
+    }
+
+    fn overlapping(&self) -> bool {
+        self.overlap() > 0
+    }
 
-            // println!("{:?} {:?}", first, second);
-            let first = (
-                first[0].parse::<u32>().unwrap(),
-                first[1].parse::<u32>().unwrap(),
-            );
-            let second = (
-                second[0].parse::<u32>().unwrap(),
-                second[1].parse::<u32>().unwrap(),
-            );
+    fn overlap(&self) -> u64 {
+        self.0.overlap(&self.1)
+    }
+}
 
-            if fully_overlapping_ranges(first, second) {
-                println!("{:?} {:?}", first, second);
-                1
-            } else {
-                0
+impl FromStr for Pair {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (first, second) = s
+            .split_once(',')
+            .ok_or(ParseError::MissingComma(s.to_string()))?;
+        Ok(Pair(first.parse()?, second.parse()?))
+    }
+}
+
+fn parse_input(input: &str) -> Result<Vec<Pair>, ParseError> {
+    input.lines().map(|line| line.parse()).collect()
+}
+
+fn assignments(pairs: &[Pair]) -> Vec<Assignment> {
+    pairs.iter().flat_map(|pair| [pair.0, pair.1]).collect()
+}
+
+//Assignments merged into sorted, disjoint runs of sections
+fn merged(assignments: &[Assignment]) -> Vec<Assignment> {
+    let mut sorted = assignments.to_vec();
+    sorted.sort();
+    let mut runs: Vec<Assignment> = Vec::new();
+    for assignment in sorted {
+        match runs.last_mut() {
+            Some(run) if u64::from(assignment.start) <= u64::from(run.end) + 1 => {
+                run.end = run.end.max(assignment.end)
             }
-        })
-        .collect::<Vec<_>>()
+            _ => runs.push(assignment),
+        }
+    }
+    runs
+}
+
+//Number of sections assigned to at least one elf
+fn union_len(assignments: &[Assignment]) -> u64 {
+    merged(assignments).iter().map(Assignment::len).sum()
+}
+
+//Sections between the first and last assigned section nobody was assigned.
+//Merged runs are at least one section apart, so the gaps can't overflow.
+fn uncovered(assignments: &[Assignment]) -> Vec<Assignment> {
+    merged(assignments)
+        .windows(2)
+        .map(|runs| Assignment::new(runs[0].end + 1, runs[1].start - 1))
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Clique {
+    size: usize,
+    //First section every assignment in the clique covers
+    section: u32,
+}
+
+//Ranges on a line overlap pairwise exactly when they share a section, so the
+//largest clique is the most assignments covering one section
+fn largest_clique(assignments: &[Assignment]) -> Option<Clique> {
+    //Ends sort before starts on the same section, an assignment ending at
+    //section n is closed at n + 1, which may be past u32::MAX
+    let mut events: Vec<(u64, i32)> = assignments
         .iter()
-        .sum::<i32>();
-
-    println!("Count: {}", sum);
-
-    let sum = include_str!("../input/4")
-        .lines()
-        .map(|line| {
-            println!("{}", line);
-            let mut parts = line.split(',');
-            let first = parts.next().unwrap().split('-').collect::<Vec<_>>();
-            let second = parts.next().unwrap().split('-').collect::<Vec<_>>();
-
-            // println!("{:?} {:?}", first, second);
-            let first = (
-                first[0].parse::<u32>().unwrap(),
-                first[1].parse::<u32>().unwrap(),
-            );
-            let second = (
-                second[0].parse::<u32>().unwrap(),
-                second[1].parse::<u32>().unwrap(),
-            );
-
-            if overlapping_ranges(first, second) {
-                println!("{:?} {:?}", first, second);
-                1
-            } else {
-                0
-            }
-        })
-        .collect::<Vec<_>>()
+        .flat_map(|a| [(u64::from(a.start), 1), (u64::from(a.end) + 1, -1)])
+        .collect();
+    events.sort();
+
+    let mut best: Option<Clique> = None;
+    let mut open = 0;
+    for (section, change) in events {
+        open += change;
+        if change > 0 && best.as_ref().is_none_or(|best| open as usize > best.size) {
+            //Only starts open a clique, so the section is a u32
+            best = Some(Clique {
+                size: open as usize,
+                section: section as u32,
+            });
+        }
+    }
+    best
+}
+
+fn count_pairs(input: &str, predicate: fn(&Pair) -> bool) -> Result<usize, ParseError> {
+    Ok(parse_input(input)?
+        .iter()
+        .filter(|pair| predicate(pair))
+        .count())
+}
+
+pub fn part1(input: &str) -> Answer {
+    count_pairs(input, Pair::fully_overlapping).into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    count_pairs(input, Pair::overlapping).into()
+}
+
+pub fn export(input: &str, format: &str) -> Result<Option<String>, String> {
+    if format != "stats" {
+        return Ok(None);
+    }
+    let pairs = parse_input(input).map_err(|e| e.to_string())?;
+    let assignments = assignments(&pairs);
+    let gaps = uncovered(&assignments)
         .iter()
-        .sum::<i32>();
-    println!("Count: {}", sum);
+        .map(|gap| gap.to_string())
+        .collect::<Vec<_>>();
+    let gaps = if gaps.is_empty() {
+        "none".to_string()
+    } else {
+        gaps.join(", ")
+    };
+    let mut lines = vec![
+        format!(
+            "Overlapping sections: {}",
+            pairs.iter().map(Pair::overlap).sum::<u64>()
+        ),
+        format!("Covered sections: {}", union_len(&assignments)),
+        format!("Uncovered sections: {}", gaps),
+    ];
+    if let Some(clique) = largest_clique(&assignments) {
+        lines.push(format!(
+            "Largest clique: {} assignments sharing section {}",
+            clique.size, clique.section
+        ));
+    }
+    Ok(Some(lines.join("\n")))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8\n";
+
+    fn pair(s: &str) -> Pair {
+        s.parse().unwrap()
+    }
+
     #[test]
     fn test() {
-        assert!(fully_overlapping_ranges((6, 6), (4, 6)));
-        assert!(!fully_overlapping_ranges((2, 4), (6, 8)));
+        assert!(pair("6-6,4-6").fully_overlapping());
+        assert!(!pair("2-4,6-8").fully_overlapping());
     }
 
     #[test]
     fn test_overlapping() {
-        assert!(!overlapping_ranges((2, 3), (4, 5)));
-        assert!(overlapping_ranges((2, 4), (4, 5)));
-        assert!(overlapping_ranges((6, 6), (4, 6)));
+        assert!(!pair("2-3,4-5").overlapping());
+        assert!(pair("2-4,4-5").overlapping());
+        assert!(pair("6-6,4-6").overlapping());
+    }
+
+    #[test]
+    fn test_parts() {
+        assert_eq!(part1(EXAMPLE), Answer::from(2));
+        assert_eq!(part2(EXAMPLE), Answer::from(4));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "2-4 6-8".parse::<Pair>(),
+            Err(ParseError::MissingComma("2-4 6-8".to_string()))
+        );
+        assert_eq!(
+            "2-4,8-6".parse::<Pair>(),
+            Err(ParseError::InvalidRange("8-6".to_string()))
+        );
+        assert_eq!(
+            part1("2-4,6-x\n").to_string(),
+            "unsolved (invalid range: 6-x)"
+        );
+    }
+
+    #[test]
+    fn test_overlap_len() {
+        let overlaps = parse_input(EXAMPLE)
+            .unwrap()
+            .iter()
+            .map(Pair::overlap)
+            .collect::<Vec<_>>();
+        assert_eq!(overlaps, vec![0, 0, 1, 5, 1, 3]);
+    }
+
+    #[test]
+    fn test_union_and_uncovered() {
+        let assignments = assignments(&parse_input(EXAMPLE).unwrap());
+        assert_eq!(union_len(&assignments), 8);
+        assert!(uncovered(&assignments).is_empty());
+
+        let assignments = [
+            Assignment::new(10, 12),
+            Assignment::new(1, 2),
+            Assignment::new(3, 4),
+            Assignment::new(7, 7),
+        ];
+        assert_eq!(union_len(&assignments), 8);
+        assert_eq!(
+            uncovered(&assignments),
+            vec![Assignment::new(5, 6), Assignment::new(8, 9)]
+        );
+        assert_eq!(union_len(&[]), 0);
+    }
+
+    #[test]
+    fn test_largest_clique() {
+        let assignments = assignments(&parse_input(EXAMPLE).unwrap());
+        assert_eq!(
+            largest_clique(&assignments),
+            Some(Clique {
+                size: 8,
+                section: 6
+            })
+        );
+
+        //Touching ends share a section, the next start after an end doesn't
+        let assignments = [
+            Assignment::new(1, 3),
+            Assignment::new(3, 5),
+            Assignment::new(4, 6),
+            Assignment::new(6, 6),
+        ];
+        assert_eq!(
+            largest_clique(&assignments),
+            Some(Clique {
+                size: 2,
+                section: 3
+            })
+        );
+        assert_eq!(largest_clique(&[]), None);
+    }
+
+    #[test]
+    fn test_clique_matches_brute_force() {
+        let assignments = assignments(&parse_input(include_str!("../input/4")).unwrap());
+        let max = assignments.iter().map(|a| a.end).max().unwrap();
+        let brute_force = (1..=max)
+            .map(|section| {
+                assignments
+                    .iter()
+                    .filter(|a| a.start <= section && section <= a.end)
+                    .count()
+            })
+            .max()
+            .unwrap();
+        assert_eq!(largest_clique(&assignments).unwrap().size, brute_force);
+    }
+
+    #[test]
+    fn test_last_section() {
+        let max = u32::MAX;
+        let assignments = [
+            Assignment::new(0, max),
+            Assignment::new(max, max),
+            Assignment::new(5, max - 2),
+        ];
+        assert_eq!(union_len(&assignments), u64::from(max) + 1);
+        assert_eq!(assignments[0].overlap(&assignments[0]), u64::from(max) + 1);
+        assert_eq!(
+            largest_clique(&assignments),
+            Some(Clique {
+                size: 2,
+                section: 5
+            })
+        );
+
+        let assignments = [Assignment::new(max, max), Assignment::new(1, max - 2)];
+        assert_eq!(
+            uncovered(&assignments),
+            vec![Assignment::new(max - 1, max - 1)]
+        );
+        assert_eq!(
+            merged(&[Assignment::new(max - 1, max), Assignment::new(max, max)]),
+            vec![Assignment::new(max - 1, max)]
+        );
     }
 }
@@ -1,110 +1,437 @@
-use std::str::Lines;
+use crate::answer::Answer;
+use std::{fmt::Display, str::FromStr};
 
-fn do_move(stacks: &mut Vec<Vec<char>>, num: usize, from: usize, to: usize) {
-    let mut temp: Vec<char> = Vec::new();
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    MissingFooter,
+    InvalidColumn(String),
+    UnlabeledCrate { line: usize, column: usize },
+    InvalidMove(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingFooter => write!(f, "stack drawing has no line of stack numbers"),
+            ParseError::InvalidColumn(label) => write!(f, "invalid stack number: {}", label),
+            ParseError::UnlabeledCrate { line, column } => write!(
+                f,
+                "crate at line {} column {} is not above a stack number",
+                line, column
+            ),
+            ParseError::InvalidMove(line) => write!(f, "invalid move: {}", line),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CraneError {
+    NoSuchStack(usize),
+    NotEnoughCrates {
+        stack: usize,
+        needed: usize,
+        available: usize,
+    },
+}
+
+impl Display for CraneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CraneError::NoSuchStack(stack) => write!(f, "there is no stack {}", stack),
+            CraneError::NotEnoughCrates {
+                stack,
+                needed,
+                available,
+            } => write!(
+                f,
+                "stack {} holds {} crates but {} should be moved",
+                stack, available, needed
+            ),
+        }
+    }
+}
 
-    for _ in 0..num {
-        temp.push(stacks[from - 1].pop().unwrap());
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    count: usize,
+    from: usize,
+    to: usize,
+}
+
+impl FromStr for Move {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError::InvalidMove(s.to_string());
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            ["move", count, "from", from, "to", to] => Ok(Move {
+                count: count.parse().map_err(|_| invalid())?,
+                from: from.parse().map_err(|_| invalid())?,
+                to: to.parse().map_err(|_| invalid())?,
+            }),
+            _ => Err(invalid()),
+        }
     }
-    println!("Temp: {:?}", temp);
-    for _ in 0..num {
-        stacks[to - 1].push(temp.pop().unwrap());
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from, self.to)
     }
 }
 
-fn parse_stack(lines: Lines) -> Vec<Vec<char>> {
-    let mut stacks: Vec<Vec<char>> = Vec::new();
+//Stacks are numbered from 1 like in the drawing, crates are listed bottom first
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stacks(Vec<Vec<String>>);
 
-    let first = lines.clone().next().unwrap();
+impl Stacks {
+    fn stack(&self, number: usize) -> Result<&Vec<String>, CraneError> {
+        number
+            .checked_sub(1)
+            .and_then(|i| self.0.get(i))
+            .ok_or(CraneError::NoSuchStack(number))
+    }
 
-    for _ in 0..(first.len() / 4 + 1) {
-        stacks.push(Vec::new());
+    fn stack_mut(&mut self, number: usize) -> Result<&mut Vec<String>, CraneError> {
+        number
+            .checked_sub(1)
+            .and_then(|i| self.0.get_mut(i))
+            .ok_or(CraneError::NoSuchStack(number))
     }
 
-    let _ = lines.filter(|l| l.starts_with('[')).for_each(|s| {
-        //split line into vec of 3chars remove one whitespace between
-        let mut chars = s.chars().collect::<Vec<char>>();
+    //Take the top count crates off a stack, keeping their order
+    fn take(&mut self, number: usize, count: usize) -> Result<Vec<String>, CraneError> {
+        let stack = self.stack_mut(number)?;
+        if stack.len() < count {
+            return Err(CraneError::NotEnoughCrates {
+                stack: number,
+                needed: count,
+                available: stack.len(),
+            });
+        }
+        Ok(stack.split_off(stack.len() - count))
+    }
 
-        //take out the second character then skip the next 3
-        let mut pos = 0;
-        while chars.len() > 3 {
-            let c = chars[1];
-            if c.is_alphabetic() {
-                stacks[pos].push(c);
+    fn top_crates(&self) -> String {
+        self.0
+            .iter()
+            .filter_map(|stack| stack.last())
+            .cloned()
+            .collect()
+    }
+}
+
+//Spans of bracketed crate labels in one line of the drawing, as (start, end, label)
+fn crate_spans(line: &str) -> Vec<(usize, usize, &str)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c, start) {
+            ('[', None) => start = Some(i),
+            (']', Some(s)) => {
+                spans.push((s, i, &line[s + 1..i]));
+                start = None;
             }
+            _ => {}
+        }
+    }
+    spans
+}
+
+impl FromStr for Stacks {
+    type Err = ParseError;
 
-            chars = chars.split_off(4);
-            pos += 1;
+    //Each crate belongs to the stack whose number sits under it, so stacks can
+    //have any number of columns and labels any width
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s.lines().collect::<Vec<_>>();
+        let (footer, crates) = lines.split_last().ok_or(ParseError::MissingFooter)?;
+        if footer.trim().is_empty() || footer.contains('[') {
+            return Err(ParseError::MissingFooter);
         }
-        let c = chars[1];
-        if c.is_alphabetic() {
-            stacks[pos].push(c);
+
+        let mut columns = Vec::new();
+        let mut offset = 0;
+        for label in footer.split_whitespace() {
+            let start = offset + footer[offset..].find(label).unwrap();
+            offset = start + label.len();
+            let number = label
+                .parse::<usize>()
+                .map_err(|_| ParseError::InvalidColumn(label.to_string()))?;
+            if number != columns.len() + 1 {
+                return Err(ParseError::InvalidColumn(label.to_string()));
+            }
+            columns.push((start, offset - 1));
         }
-    });
 
-    //revers each stack
-    for stack in stacks.iter_mut() {
-        stack.reverse();
+        let mut stacks = vec![Vec::new(); columns.len()];
+        for (line_number, line) in crates.iter().enumerate().rev() {
+            for (start, end, label) in crate_spans(line) {
+                let stack = columns
+                    .iter()
+                    .position(|&(a, b)| a <= end && start <= b)
+                    .ok_or(ParseError::UnlabeledCrate {
+                        line: line_number + 1,
+                        column: start + 1,
+                    })?;
+                stacks[stack].push(label.trim().to_string());
+            }
+        }
+        Ok(Stacks(stacks))
     }
+}
+
+impl Display for Stacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label_width = self
+            .0
+            .iter()
+            .flatten()
+            .map(|label| label.len())
+            .chain(std::iter::once(self.0.len().to_string().len()))
+            .max()
+            .unwrap_or(1);
+        let height = self.0.iter().map(|stack| stack.len()).max().unwrap_or(0);
 
-    println!("Stacks: {:?}", stacks);
-    stacks
+        for level in (0..height).rev() {
+            let line = self
+                .0
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(label) => format!("[{:^w$}]", label, w = label_width),
+                    None => " ".repeat(label_width + 2),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        let footer = (1..=self.0.len())
+            .map(|number| format!(" {:^w$} ", number, w = label_width))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(f, "{}", footer.trim_end())
+    }
 }
 
-fn part1() {
-    let mut stacks = parse_stack(include_str!("../input/5").lines());
-    let _sum = include_str!("../input/5")
-        .lines()
-        .filter(|s| s.starts_with("move"))
-        .for_each(|s| {
-            let mut words = s.split_whitespace();
-            let _ = words.next();
-            let num = words.next().unwrap().parse::<usize>().unwrap();
-            let _ = words.next();
-            let from = words.next().unwrap().parse::<usize>().unwrap();
-            let _ = words.next();
-            let to = words.next().unwrap().parse::<usize>().unwrap();
-
-            println!("Move {} from {} to {}", num, from, to);
-            for _ in 0..num {
-                do_move(&mut stacks, 1, from, to);
-            }
-        });
+trait Crane {
+    fn name(&self) -> String;
+
+    //Most crates lifted in one go, a move of more crates is split into lifts
+    fn capacity(&self) -> usize;
+
+    fn apply(&self, stacks: &mut Stacks, m: &Move) -> Result<(), CraneError> {
+        stacks.stack(m.to)?;
+        let available = stacks.stack(m.from)?.len();
+        if available < m.count {
+            return Err(CraneError::NotEnoughCrates {
+                stack: m.from,
+                needed: m.count,
+                available,
+            });
+        }
+
+        let mut remaining = m.count;
+        while remaining > 0 {
+            let lift = remaining.min(self.capacity());
+            let crates = stacks.take(m.from, lift)?;
+            stacks.stack_mut(m.to)?.extend(crates);
+            remaining -= lift;
+        }
+        Ok(())
+    }
+}
+
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_string()
+    }
+
+    fn capacity(&self) -> usize {
+        1
+    }
+}
+
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_string()
+    }
+
+    fn capacity(&self) -> usize {
+        usize::MAX
+    }
+}
+
+struct LimitedCrane(usize);
 
-    print!("Code: ");
-    stacks.iter().for_each(|s| {
-        print!("{}", s.last().unwrap());
-    });
-    println!();
+impl Crane for LimitedCrane {
+    fn name(&self) -> String {
+        format!("crane lifting at most {} crates", self.0)
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.max(1)
+    }
 }
 
-fn part2() {
-    let mut stacks = parse_stack(include_str!("../input/5").lines());
-    let _sum = include_str!("../input/5")
+fn parse_input(input: &str) -> Result<(Stacks, Vec<Move>), ParseError> {
+    let input = input.replace("\r\n", "\n");
+    let (drawing, moves) = input.split_once("\n\n").ok_or(ParseError::MissingFooter)?;
+    let stacks = drawing.parse()?;
+    let moves = moves
         .lines()
-        .filter(|s| s.starts_with("move"))
-        .for_each(|s| {
-            let mut words = s.split_whitespace();
-            let _ = words.next();
-            let num = words.next().unwrap().parse::<usize>().unwrap();
-            let _ = words.next();
-            let from = words.next().unwrap().parse::<usize>().unwrap();
-            let _ = words.next();
-            let to = words.next().unwrap().parse::<usize>().unwrap();
-
-            println!("Move {} from {} to {}", num, from, to);
-            do_move(&mut stacks, num as usize, from, to);
-        });
-
-    print!("Code: ");
-    stacks.iter().for_each(|s| {
-        print!("{}", s.last().unwrap());
-    });
-    println!();
-}
-
-pub fn run() {
-    println!("Day 5");
-    part1();
-    part2();
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse())
+        .collect::<Result<_, _>>()?;
+    Ok((stacks, moves))
+}
+
+//Runs all moves, on_step sees every move together with the stacks after it
+fn simulate<F>(
+    crane: &dyn Crane,
+    mut stacks: Stacks,
+    moves: &[Move],
+    mut on_step: F,
+) -> Result<Stacks, CraneError>
+where
+    F: FnMut(&Move, &Stacks),
+{
+    for m in moves {
+        crane.apply(&mut stacks, m)?;
+        on_step(m, &stacks);
+    }
+    Ok(stacks)
+}
+
+fn top_crates(input: &str, crane: &dyn Crane) -> Result<String, String> {
+    let (stacks, moves) = parse_input(input).map_err(|e| e.to_string())?;
+    let stacks = simulate(crane, stacks, &moves, |_, _| {}).map_err(|e| e.to_string())?;
+    Ok(stacks.top_crates())
+}
+
+pub fn part1(input: &str) -> Answer {
+    top_crates(input, &CrateMover9000).into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    top_crates(input, &CrateMover9001).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let (stacks, moves) = parse_input(include_str!("../input/test5")).unwrap();
+        assert_eq!(
+            stacks.0,
+            vec![vec!["Z", "N"], vec!["M", "C", "D"], vec!["P"]]
+        );
+        assert_eq!(moves.len(), 4);
+        assert_eq!(
+            moves[1],
+            Move {
+                count: 3,
+                from: 1,
+                to: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_cranes() {
+        let input = include_str!("../input/test5");
+        assert_eq!(top_crates(input, &CrateMover9000).unwrap(), "CMZ");
+        assert_eq!(top_crates(input, &CrateMover9001).unwrap(), "MCD");
+        assert_eq!(top_crates(input, &LimitedCrane(1)).unwrap(), "CMZ");
+        assert_eq!(top_crates(input, &LimitedCrane(3)).unwrap(), "MCD");
+    }
+
+    #[test]
+    fn test_limited_crane() {
+        let stacks: Stacks = "[E]\n[D]\n[C]\n[B]\n[A]\n 1   2".parse().unwrap();
+        let m = Move {
+            count: 5,
+            from: 1,
+            to: 2,
+        };
+        let after = simulate(&LimitedCrane(2), stacks, &[m], |_, _| {}).unwrap();
+        assert_eq!(after.0[1], vec!["D", "E", "B", "C", "A"]);
+    }
+
+    #[test]
+    fn test_render_steps() {
+        let (stacks, moves) = parse_input(include_str!("../input/test5")).unwrap();
+        assert_eq!(
+            stacks.to_string(),
+            "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3\n"
+        );
+
+        let mut frames = Vec::new();
+        simulate(&CrateMover9000, stacks, &moves, |m, stacks| {
+            frames.push(format!("{}\n{}", m, stacks))
+        })
+        .unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(
+            frames[0],
+            "move 1 from 2 to 1\n[D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3\n"
+        );
+        assert_eq!(
+            frames[3],
+            "move 1 from 1 to 2\n        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3\n"
+        );
+    }
+
+    #[test]
+    fn test_wide_drawing() {
+        let drawing = "\
+[AB]            [XY]
+[CD] [E ]       [Z ] [Q1]
+  1    2    3    4    5    6    7    8    9   10
+";
+        let stacks: Stacks = drawing.parse().unwrap();
+        assert_eq!(stacks.0.len(), 10);
+        assert_eq!(stacks.0[0], vec!["CD", "AB"]);
+        assert_eq!(stacks.0[1], vec!["E"]);
+        assert_eq!(stacks.0[3], vec!["Z", "XY"]);
+        assert_eq!(stacks.0[4], vec!["Q1"]);
+        assert_eq!(stacks.top_crates(), "ABEXYQ1");
+
+        let rendered: Stacks = stacks.to_string().parse().unwrap();
+        assert_eq!(rendered, stacks);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (stacks, _) = parse_input(include_str!("../input/test5")).unwrap();
+        let m = "move 2 from 3 to 1".parse::<Move>().unwrap();
+        assert_eq!(
+            simulate(&CrateMover9001, stacks.clone(), &[m], |_, _| {}),
+            Err(CraneError::NotEnoughCrates {
+                stack: 3,
+                needed: 2,
+                available: 1
+            })
+        );
+        let m = "move 1 from 1 to 4".parse::<Move>().unwrap();
+        assert_eq!(
+            CrateMover9000.apply(&mut stacks.clone(), &m),
+            Err(CraneError::NoSuchStack(4))
+        );
+        assert_eq!(
+            "move one from 1 to 2".parse::<Move>(),
+            Err(ParseError::InvalidMove("move one from 1 to 2".to_string()))
+        );
+        assert_eq!(
+            "[A]\n  [B]\n 1".parse::<Stacks>(),
+            Err(ParseError::UnlabeledCrate { line: 2, column: 3 })
+        );
+        assert_eq!("[A]".parse::<Stacks>(), Err(ParseError::MissingFooter));
+    }
 }
@@ -1,57 +1,168 @@
-use std::collections::VecDeque;
+use crate::answer::Answer;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io::{self, BufReader, Read},
+};
 
-fn has_duplicates(queue: &VecDeque<char>) -> bool {
-    for i in 0..queue.len() {
-        for j in 0..queue.len() {
-            if i != j && queue[i] == queue[j] {
-                return true;
-            }
+//Tracks the current run of symbols without repeats. Remembering where each
+//symbol was last seen means every symbol is only looked at once.
+struct Detector<T> {
+    window: usize,
+    //Number of symbols consumed so far
+    position: usize,
+    //First position of the current run without repeats
+    run_start: usize,
+    last_seen: HashMap<T, usize>,
+}
+
+impl<T: Eq + Hash> Detector<T> {
+    fn new(window: usize) -> Self {
+        Detector {
+            window,
+            position: 0,
+            run_start: 0,
+            last_seen: HashMap::new(),
+        }
+    }
+
+    //True if the last window symbols, ending with this one, are all different
+    fn push(&mut self, symbol: T) -> bool {
+        if let Some(last) = self.last_seen.insert(symbol, self.position) {
+            self.run_start = self.run_start.max(last + 1);
         }
+        self.position += 1;
+        self.window > 0 && self.position - self.run_start >= self.window
     }
-    return false;
 }
 
-fn start_of_packet(packet: &str, distinct_chars: usize) -> usize {
-    let mut queue: VecDeque<char> = packet.chars().take(distinct_chars).collect();
+//Yields the position right after every window of distinct symbols, so the
+//first item is the answer to the puzzle
+struct Markers<I: Iterator> {
+    iter: I,
+    detector: Detector<I::Item>,
+}
 
-    if !has_duplicates(&queue) {
-        return distinct_chars;
-    }
+impl<I> Iterator for Markers<I>
+where
+    I: Iterator,
+    I::Item: Eq + Hash,
+{
+    type Item = usize;
 
-    for i in distinct_chars..packet.len() {
-        let _ = queue.pop_front();
-        queue.push_back(packet.chars().nth(i).unwrap());
-        if !has_duplicates(&queue) {
-            return i + 1;
+    fn next(&mut self) -> Option<usize> {
+        for symbol in self.iter.by_ref() {
+            if self.detector.push(symbol) {
+                return Some(self.detector.position);
+            }
         }
+        None
     }
+}
 
-    return 0;
+fn markers<I>(symbols: I, window: usize) -> Markers<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: Eq + Hash,
+{
+    Markers {
+        iter: symbols.into_iter(),
+        detector: Detector::new(window),
+    }
 }
 
-fn part1() {
-    for line in include_str!("../input/6").lines() {
-        let start = start_of_packet(&line, 4);
+fn start_of_packet<I>(symbols: I, window: usize) -> Option<usize>
+where
+    I: IntoIterator,
+    I::Item: Eq + Hash,
+{
+    markers(symbols, window).next()
+}
 
-        println!("Packet start: {}", start);
+//Reads the stream once and finds the first marker for every window size,
+//stopping as soon as all of them are found. Line breaks at the end are not
+//part of the signal, so they are held back until more symbols follow them.
+fn first_markers<R: Read>(reader: R, windows: &[usize]) -> io::Result<Vec<Option<usize>>> {
+    let mut detectors = windows
+        .iter()
+        .map(|&window| Detector::new(window))
+        .collect::<Vec<_>>();
+    let mut found = vec![None; windows.len()];
+    let mut line_breaks = Vec::new();
+    for byte in BufReader::new(reader).bytes() {
+        let byte = byte?;
+        if byte == b'\n' || byte == b'\r' {
+            line_breaks.push(byte);
+            continue;
+        }
+        for byte in line_breaks.drain(..).chain([byte]) {
+            for (detector, found) in detectors.iter_mut().zip(found.iter_mut()) {
+                if found.is_none() && detector.push(byte) {
+                    *found = Some(detector.position);
+                }
+            }
+        }
+        if found.iter().all(Option::is_some) {
+            break;
+        }
     }
+    Ok(found)
 }
-fn part2() {
-    for line in include_str!("../input/6").lines() {
-        let start = start_of_packet(&line, 14);
 
-        println!("Packet start: {}", start);
-    }
+//Both parts in one pass over a stream, without holding it in memory
+pub fn stream(reader: &mut dyn Read) -> io::Result<(Answer, Answer)> {
+    let found = first_markers(reader, &[4, 14])?;
+    Ok((found[0].into(), found[1].into()))
 }
-pub fn run() {
-    println!("Day 6");
-    part1();
-    part2();
+
+pub fn part1(input: &str) -> Answer {
+    start_of_packet(input.trim_end_matches(['\r', '\n']).bytes(), 4).into()
+}
+
+pub fn part2(input: &str) -> Answer {
+    start_of_packet(input.trim_end_matches(['\r', '\n']).bytes(), 14).into()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, Instant};
+
+    //The previous implementation, kept to compare results and speed against
+    fn start_of_packet_quadratic(packet: &str, distinct_chars: usize) -> usize {
+        use std::collections::VecDeque;
+
+        fn has_duplicates(queue: &VecDeque<char>) -> bool {
+            for i in 0..queue.len() {
+                for j in 0..queue.len() {
+                    if i != j && queue[i] == queue[j] {
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+
+        let mut queue: VecDeque<char> = packet.chars().take(distinct_chars).collect();
+        if !has_duplicates(&queue) {
+            return distinct_chars;
+        }
+        for i in distinct_chars..packet.len() {
+            let _ = queue.pop_front();
+            queue.push_back(packet.chars().nth(i).unwrap());
+            if !has_duplicates(&queue) {
+                return i + 1;
+            }
+        }
+        0
+    }
+
+    //A long run of the same letter, the only marker is right at the end
+    fn late_marker(len: usize, window: usize) -> String {
+        let mut s = "a".repeat(len - window + 1);
+        s.extend(('b'..='z').take(window - 1));
+        s
+    }
 
     #[test]
     fn test_part1() {
@@ -59,10 +170,10 @@ mod tests {
         let s2 = "nppdvjthqldpwncqszvftbrmjlhg";
         let s3 = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
         let s4 = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        assert_eq!(start_of_packet(s1, 4), 5);
-        assert_eq!(start_of_packet(s2, 4), 6);
-        assert_eq!(start_of_packet(s3, 4), 10);
-        assert_eq!(start_of_packet(s4, 4), 11);
+        assert_eq!(start_of_packet(s1.chars(), 4), Some(5));
+        assert_eq!(start_of_packet(s2.bytes(), 4), Some(6));
+        assert_eq!(start_of_packet(s3.bytes(), 4), Some(10));
+        assert_eq!(start_of_packet(s4.bytes(), 4), Some(11));
     }
     #[test]
     fn test_part2() {
@@ -71,10 +182,104 @@ mod tests {
         let s3 = "nppdvjthqldpwncqszvftbrmjlhg";
         let s4 = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
         let s5 = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        assert_eq!(start_of_packet(s1, 14), 19);
-        assert_eq!(start_of_packet(s2, 14), 23);
-        assert_eq!(start_of_packet(s3, 14), 23);
-        assert_eq!(start_of_packet(s4, 14), 29);
-        assert_eq!(start_of_packet(s5, 14), 26);
+        assert_eq!(start_of_packet(s1.bytes(), 14), Some(19));
+        assert_eq!(start_of_packet(s2.bytes(), 14), Some(23));
+        assert_eq!(start_of_packet(s3.bytes(), 14), Some(23));
+        assert_eq!(start_of_packet(s4.bytes(), 14), Some(29));
+        assert_eq!(start_of_packet(s5.bytes(), 14), Some(26));
+    }
+
+    #[test]
+    fn test_no_marker() {
+        assert_eq!(start_of_packet("abcabcabc".bytes(), 4), None);
+        assert_eq!(start_of_packet("abc".bytes(), 4), None);
+        assert_eq!(start_of_packet("abcd".bytes(), 0), None);
+        assert_eq!(start_of_packet("".bytes(), 1), None);
+    }
+
+    #[test]
+    fn test_all_markers() {
+        let found = markers("aabcdbefa".bytes(), 3).collect::<Vec<_>>();
+        assert_eq!(found, vec![4, 5, 6, 7, 8, 9]);
+        let found = markers("abcaab".bytes(), 2).collect::<Vec<_>>();
+        assert_eq!(found, vec![2, 3, 4, 6]);
+        let found = markers([1, 2, 1, 2, 3], 3).collect::<Vec<_>>();
+        assert_eq!(found, vec![5]);
+    }
+
+    #[test]
+    fn test_first_markers() {
+        let stream = "mjqjpqmgbljsphdztnvjfqwrcgsmlb\n";
+        let found = first_markers(stream.as_bytes(), &[4, 14]).unwrap();
+        assert_eq!(found, vec![Some(7), Some(19)]);
+        let found = first_markers("aa\naa".as_bytes(), &[2, 1, 5]).unwrap();
+        assert_eq!(found, vec![Some(3), Some(1), None]);
+        let found = first_markers("a\r\nb\n".as_bytes(), &[2]).unwrap();
+        assert_eq!(found, vec![Some(2)]);
+        //The trailing line break would complete the window
+        let found = first_markers("aab\r\n".as_bytes(), &[3]).unwrap();
+        assert_eq!(found, vec![None]);
+    }
+
+    #[test]
+    fn test_stream() {
+        let mut reader = "mjqjpqmgbljsphdztnvjfqwrcgsmlb\n".as_bytes();
+        let (packet, message) = stream(&mut reader).unwrap();
+        assert_eq!(packet, Answer::from(7));
+        assert_eq!(message, Answer::from(19));
+        let mut reader = "  abcd\r\n".as_bytes();
+        assert_eq!(stream(&mut reader).unwrap().0, part1("  abcd\r\n"));
+        let mut reader = "abc\n".as_bytes();
+        assert_eq!(
+            stream(&mut reader).unwrap().0,
+            Answer::Unsolved("no solution".to_string())
+        );
+    }
+
+    #[test]
+    fn test_matches_quadratic() {
+        let input = include_str!("../input/6").trim();
+        for window in 1..=20 {
+            let expected = start_of_packet_quadratic(input, window);
+            let found = start_of_packet(input.bytes(), window).unwrap_or(0);
+            assert_eq!(found, expected, "window {}", window);
+        }
+        for len in [32, 100, 1000] {
+            let s = late_marker(len, 14);
+            assert_eq!(start_of_packet(s.bytes(), 14), Some(len));
+            assert_eq!(start_of_packet_quadratic(&s, 14), len);
+        }
+    }
+
+    //cargo test --release bench_markers -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_markers() {
+        let mut quadratic_too_slow = false;
+        for shift in 14..=22 {
+            let len = 1 << shift;
+            let input = late_marker(len, 14);
+
+            let start = Instant::now();
+            assert_eq!(start_of_packet(input.bytes(), 14), Some(len));
+            let streaming = start.elapsed();
+
+            let quadratic = if quadratic_too_slow {
+                None
+            } else {
+                let start = Instant::now();
+                assert_eq!(start_of_packet_quadratic(&input, 14), len);
+                let elapsed = start.elapsed();
+                quadratic_too_slow = elapsed > Duration::from_secs(5);
+                Some(elapsed)
+            };
+
+            println!(
+                "{:>8} KiB  streaming {:>10.3?}  quadratic {}",
+                len >> 10,
+                streaming,
+                quadratic.map_or("skipped".to_string(), |d| format!("{:.3?}", d))
+            );
+        }
     }
 }
@@ -1,295 +1,310 @@
-use std::cell::RefCell;
-use std::fmt::Debug;
-use std::rc::Rc;
-
-trait Node {
-    fn get_name(&self) -> &str;
-    fn get_parent(&self) -> Option<Link>;
-    fn get_size(&self) -> u64;
-}
+use crate::answer::Answer;
+use std::fmt::Display;
 
-trait NodeTrait: Node + Debug {}
+type DirId = usize;
 
-type Link = Rc<RefCell<Dir>>;
+const ROOT: DirId = 0;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct Dir {
     name: String,
-    children: Vec<Link>,
+    parent: Option<DirId>,
+    children: Vec<DirId>,
     files: Vec<File>,
-    parent: Option<Link>,
 }
 
-impl Dir {
-    fn new(name: String, parent: Link) -> Rc<RefCell<Self>> {
-        Rc::new(RefCell::new(Dir {
-            name,
-            children: Vec::new(),
-            files: Vec::new(),
-            parent: Some(parent),
-        }))
-    }
-    fn add_child(&mut self, child: Link) {
-        //check that child does not already exist
-        for c in self.children.iter() {
-            if c.borrow().get_name() == child.borrow().get_name() {
-                return;
-            }
-        }
-        self.children.push(child);
-    }
+#[derive(Debug)]
+struct File {
+    name: String,
+    size: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    UnknownCommand(String),
+    MissingCdTarget,
+    CdAboveRoot,
+    LsWithArguments(String),
+    OutputWithoutLs(String),
+    MalformedEntry(String),
+    //cd into a directory no ls has listed
+    UnknownDir(String),
+    InvalidName(String),
+}
 
-    fn add_file(&mut self, file: File) {
-        //check that file does not already exist
-        for f in self.files.iter() {
-            if f.get_name() == file.get_name() {
-                return;
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnknownCommand(line) => write!(f, "unknown command: {}", line),
+            ParseError::MissingCdTarget => write!(f, "cd without a target directory"),
+            ParseError::CdAboveRoot => write!(f, "cd .. from the root directory"),
+            ParseError::LsWithArguments(line) => write!(f, "ls does not take arguments: {}", line),
+            ParseError::OutputWithoutLs(line) => {
+                write!(f, "output without a preceding ls: {}", line)
             }
+            ParseError::MalformedEntry(line) => write!(f, "malformed ls entry: {}", line),
+            ParseError::UnknownDir(name) => write!(f, "cd into unlisted directory: {}", name),
+            ParseError::InvalidName(name) => write!(f, "invalid name: {}", name),
         }
-        self.files.push(file);
     }
 }
 
-impl Node for Dir {
-    fn get_name(&self) -> &str {
-        &self.name
-    }
-
-    fn get_parent(&self) -> Option<Link> {
-        self.parent.clone()
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SizeFilter {
+    AtMost(u64),
+    AtLeast(u64),
+}
 
-    fn get_size(&self) -> u64 {
-        let mut size = 0;
-        for child in self.children.iter() {
-            size += child.borrow().get_size();
-        }
-        for file in self.files.iter() {
-            size += file.get_size();
+impl SizeFilter {
+    fn matches(&self, size: u64) -> bool {
+        match self {
+            SizeFilter::AtMost(limit) => size <= *limit,
+            SizeFilter::AtLeast(limit) => size >= *limit,
         }
-        size
     }
 }
 
 #[derive(Debug)]
-struct File {
-    name: String,
-    size: u64,
-    parent: Option<Link>,
+struct FileSystem {
+    dirs: Vec<Dir>,
+    sizes: Vec<u64>,
 }
 
-impl File {
-    fn new(name: String, size: u64) -> Self {
-        File {
-            name,
-            size,
-            parent: None,
+impl FileSystem {
+    fn new() -> Self {
+        FileSystem {
+            dirs: vec![Dir {
+                name: "/".to_string(),
+                parent: None,
+                children: Vec::new(),
+                files: Vec::new(),
+            }],
+            sizes: vec![0],
         }
     }
 
-    fn get_size(&self) -> u64 {
-        self.size
+    fn child(&self, dir: DirId, name: &str) -> Option<DirId> {
+        self.dirs[dir]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.dirs[c].name == name)
     }
 
-    fn get_parent(&mut self) -> &mut Option<Link> {
-        &mut self.parent
+    fn add_dir(&mut self, parent: DirId, name: &str) -> DirId {
+        if let Some(existing) = self.child(parent, name) {
+            return existing;
+        }
+        let id = self.dirs.len();
+        self.dirs.push(Dir {
+            name: name.to_string(),
+            parent: Some(parent),
+            children: Vec::new(),
+            files: Vec::new(),
+        });
+        self.sizes.push(0);
+        self.dirs[parent].children.push(id);
+        id
     }
 
-    fn get_name(&self) -> &str {
-        &self.name
+    fn add_file(&mut self, dir: DirId, name: &str, size: u64) {
+        if self.dirs[dir].files.iter().any(|f| f.name == name) {
+            return;
+        }
+        self.dirs[dir].files.push(File {
+            name: name.to_string(),
+            size,
+        });
     }
-}
 
-impl Node for File {
-    fn get_name(&self) -> &str {
-        &self.name
+    //Children are always created after their parent, so walking the arena
+    //backwards sees every subdirectory before the directory containing it
+    fn update_sizes(&mut self) {
+        for id in (0..self.dirs.len()).rev() {
+            let files: u64 = self.dirs[id].files.iter().map(|f| f.size).sum();
+            let children: u64 = self.dirs[id].children.iter().map(|&c| self.sizes[c]).sum();
+            self.sizes[id] = files + children;
+        }
     }
 
-    fn get_parent(&self) -> Option<Link> {
-        self.parent.clone()
+    fn size(&self, dir: DirId) -> u64 {
+        self.sizes[dir]
     }
 
-    fn get_size(&self) -> u64 {
-        self.size
+    fn lookup(&self, path: &str) -> Option<DirId> {
+        let rest = path.strip_prefix('/')?;
+        rest.split('/')
+            .filter(|part| !part.is_empty())
+            .try_fold(ROOT, |dir, part| self.child(dir, part))
     }
-}
-
-fn breadth_first_map<F>(dir: Rc<RefCell<Dir>>, mut f: F)
-where
-    F: FnMut(Rc<RefCell<Dir>>),
-{
-    let mut queue = Vec::new();
-
-    queue.push(dir.clone());
 
-    while let Some(node) = queue.pop() {
-        f(node.clone());
-        for child in node.borrow().children.iter() {
-            queue.push(child.clone());
+    fn path(&self, dir: DirId) -> String {
+        let mut parts = Vec::new();
+        let mut current = dir;
+        while let Some(parent) = self.dirs[current].parent {
+            parts.push(self.dirs[current].name.as_str());
+            current = parent;
         }
+        parts.reverse();
+        format!("/{}", parts.join("/"))
     }
-}
 
-fn parse_input(input: &str) -> Dir {
-    let root = Dir::new(
-        "/".to_string(),
-        Rc::new(RefCell::new(Dir {
-            name: "/".to_string(),
-            children: Vec::new(),
-            files: Vec::new(),
-            parent: None,
-        })),
-    );
-    let mut current_dir = root.clone();
-
-    let mut ls_command = false;
-    for line in input.lines() {
-        if ls_command {
-            match line {
-                line if line.starts_with("dir") => {
-                    let dir = line.split(" ").skip(1).next().unwrap();
-                    println!("dir: {}", dir);
-                    continue;
-                }
-                line if line.split(" ").count() == 2 => {
-                    let size = line.split(" ").next().unwrap();
-                    let name = line.split(" ").skip(1).next().unwrap();
-                    println!("file: {} {}", size, name);
-
-                    let file = File::new(name.to_string(), size.parse().unwrap());
-                    current_dir.borrow_mut().add_file(file);
-                    continue;
-                }
-                _ => {
-                    ls_command = false;
-                    println!("");
-                    println!("{}", line);
-                }
-            }
+    //Directories in depth first order, parents before their children
+    fn walk(&self) -> Vec<DirId> {
+        let mut order = Vec::new();
+        let mut stack = vec![ROOT];
+        while let Some(dir) = stack.pop() {
+            order.push(dir);
+            let mut children = self.dirs[dir].children.clone();
+            children.sort_by(|a, b| self.dirs[*b].name.cmp(&self.dirs[*a].name));
+            stack.extend(children);
         }
-        match line {
-            line if line.starts_with("$ cd") => {
-                let mut path = line.split(" ").skip(2);
-                let dir = path.next().unwrap();
-                println!("cd: {}", dir);
-
-                match dir {
-                    "/" => {
-                        current_dir = root.clone();
-                    }
-                    ".." => {
-                        let parent = current_dir.borrow().get_parent().clone();
-                        if let Some(parent) = parent {
-                            current_dir = parent;
-                        }
-                    }
-                    dir => {
-                        let mut child_dir: Option<Link> = None;
-                        for child in current_dir.borrow().children.iter() {
-                            if child.borrow().get_name() == dir {
-                                child_dir = Some(child.clone());
-                                break;
-                            }
-                        }
-                        if let Some(child_dir) = child_dir {
-                            current_dir = child_dir;
-                        } else {
-                            let new_dir = Dir::new(dir.to_string(), current_dir.clone());
-
-                            current_dir.borrow_mut().add_child(new_dir.clone());
-
-                            // .add_child(new_dir.clone());
-                            current_dir = new_dir.clone();
-                        }
-                    }
-                }
-            }
-            line if line.starts_with("$ ls") => {
-                ls_command = true;
-                println!("ls");
-            }
+        order
+    }
+
+    fn du(&self) -> Vec<(String, u64)> {
+        self.walk()
+            .into_iter()
+            .map(|dir| (self.path(dir), self.size(dir)))
+            .collect()
+    }
 
-            line => {
-                panic!("Invalid input: {}", line);
+    fn find(&self, filter: SizeFilter) -> Vec<DirId> {
+        self.walk()
+            .into_iter()
+            .filter(|&dir| filter.matches(self.size(dir)))
+            .collect()
+    }
+
+    fn fmt_dir(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        dir: DirId,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{}- {} (dir)", indent, self.dirs[dir].name)?;
+
+        //Entries are listed alphabetically with directories and files interleaved
+        let mut entries: Vec<(&str, Option<DirId>, u64)> = self.dirs[dir]
+            .children
+            .iter()
+            .map(|&c| (self.dirs[c].name.as_str(), Some(c), 0))
+            .chain(
+                self.dirs[dir]
+                    .files
+                    .iter()
+                    .map(|file| (file.name.as_str(), None, file.size)),
+            )
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (name, child, size) in entries {
+            match child {
+                Some(child) => self.fmt_dir(f, child, depth + 1)?,
+                None => writeln!(f, "{}  - {} (file, size={})", indent, name, size)?,
             }
         }
+        Ok(())
     }
-    root.take()
 }
 
-fn part1() {
-    println!("Day 7");
-
-    //get input from file
-    let input = include_str!("../input/7");
-
-    let dirs = parse_input(input);
-    let size = dirs.get_size();
-    println!("Root size: {}", size);
+impl Display for FileSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_dir(f, ROOT, 0)
+    }
+}
 
-    //visit all dirs
-    let mut capped_size = 0;
+//A single path component, so lookups by path find every entry
+fn check_name(name: &str) -> Result<&str, ParseError> {
+    if name.contains('/') || name == "." || name == ".." {
+        return Err(ParseError::InvalidName(name.to_string()));
+    }
+    Ok(name)
+}
 
-    let dirs = Rc::new(RefCell::new(dirs));
+fn parse_input(input: &str) -> Result<FileSystem, ParseError> {
+    let mut fs = FileSystem::new();
+    let mut current_dir = ROOT;
+    let mut in_ls = false;
+
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        let mut words = line.split_whitespace();
+
+        if let Some(command) = line.strip_prefix("$ ") {
+            in_ls = false;
+            let mut args = command.split_whitespace();
+            match args.next() {
+                Some("cd") => {
+                    let target = args.next().ok_or(ParseError::MissingCdTarget)?;
+                    if args.next().is_some() {
+                        return Err(ParseError::UnknownCommand(line.to_string()));
+                    }
+                    current_dir = match target {
+                        "/" => ROOT,
+                        ".." => fs.dirs[current_dir].parent.ok_or(ParseError::CdAboveRoot)?,
+                        dir => fs
+                            .child(current_dir, check_name(dir)?)
+                            .ok_or_else(|| ParseError::UnknownDir(dir.to_string()))?,
+                    };
+                }
+                Some("ls") => {
+                    if args.next().is_some() {
+                        return Err(ParseError::LsWithArguments(line.to_string()));
+                    }
+                    in_ls = true;
+                }
+                _ => return Err(ParseError::UnknownCommand(line.to_string())),
+            }
+            continue;
+        }
 
-    breadth_first_map(dirs, |c: Link| {
-        let size = c.borrow().get_size();
+        if !in_ls {
+            return Err(ParseError::OutputWithoutLs(line.to_string()));
+        }
 
-        if size <= 100000 {
-            println!("dir: {:?} size: {}", c.borrow().get_name(), size);
-            capped_size += size;
+        match (words.next(), words.next(), words.next()) {
+            (Some("dir"), Some(name), None) => {
+                fs.add_dir(current_dir, check_name(name)?);
+            }
+            (Some(size), Some(name), None) => {
+                let size = size
+                    .parse()
+                    .or(Err(ParseError::MalformedEntry(line.to_string())))?;
+                fs.add_file(current_dir, check_name(name)?, size);
+            }
+            _ => return Err(ParseError::MalformedEntry(line.to_string())),
         }
-    });
+    }
 
-    println!("Capped size: {}", capped_size);
+    fs.update_sizes();
+    Ok(fs)
 }
 
-fn part2() {
-    println!("Day 7 part 2");
-
-    //get input from file
-    let input = include_str!("../input/7");
-
-    let dirs = parse_input(input);
-    let root_size = dirs.get_size();
-
-    let filesystem_size = 70000000;
-    let space_left = filesystem_size - root_size;
-    let needed_space = 30000000;
-    let min_space_to_free = needed_space - space_left;
-
-    println!("Filesystem size: {}", filesystem_size);
-    println!("Root size: {}", root_size);
-    println!("Space left: {}", space_left);
-    println!("Needed space: {}", needed_space);
-    println!("");
-    println!("Min space to free: {}", min_space_to_free);
-
-    let mut dir_to_delete: Option<(String, u64)> = None;
-    let dirs = Rc::new(RefCell::new(dirs));
-    breadth_first_map(dirs, |c: Link| {
-        let size = c.borrow().get_size();
+fn capped_size(fs: &FileSystem, cap: u64) -> u64 {
+    fs.find(SizeFilter::AtMost(cap))
+        .into_iter()
+        .map(|dir| fs.size(dir))
+        .sum()
+}
 
-        if size >= min_space_to_free {
-            let name = c.borrow().get_name().to_string();
-            println!("Big enough dir: {:?} size: {}", name, size);
+fn dir_to_delete(fs: &FileSystem, filesystem_size: u64, needed_space: u64) -> Option<DirId> {
+    //Also correct when the tree is already larger than the disk
+    let min_space_to_free = (fs.size(ROOT) + needed_space).saturating_sub(filesystem_size);
 
-            if dir_to_delete.is_none() || size < dir_to_delete.clone().unwrap().1 {
-                dir_to_delete = Some((name, size));
-            }
-        }
-    });
+    fs.find(SizeFilter::AtLeast(min_space_to_free))
+        .into_iter()
+        .min_by_key(|&dir| fs.size(dir))
+}
 
-    if let Some((name, size)) = dir_to_delete {
-        println!("Dir to delete: {:?} size: {}", name, size);
-    } else {
-        println!("No dir to delete");
-    }
+pub fn part1(input: &str) -> Answer {
+    parse_input(input).map(|fs| capped_size(&fs, 100000)).into()
 }
 
-pub fn run() {
-    part1();
-    part2();
+pub fn part2(input: &str) -> Answer {
+    parse_input(input)
+        .map(|fs| dir_to_delete(&fs, 70000000, 30000000).map(|dir| fs.size(dir)))
+        .into()
 }
 
 #[cfg(test)]
@@ -297,38 +312,146 @@ mod tests {
     use super::*;
     #[test]
     fn test_cd() {
-        let input = "$ cd /\n$ cd a";
-        let dirs = parse_input(input);
+        let input = "$ cd /\n$ ls\ndir a\n$ cd a";
+        let fs = parse_input(input).unwrap();
 
-        println!("dirs: {:?}", dirs);
-        assert_eq!(dirs.get_name(), "/");
-        assert_eq!(dirs.children.len(), 1);
-        assert_eq!(dirs.children[0].borrow().get_name(), "a");
+        assert_eq!(fs.path(ROOT), "/");
+        assert_eq!(fs.dirs[ROOT].children.len(), 1);
+        assert_eq!(fs.lookup("/a"), Some(1));
     }
 
     #[test]
     fn test_capped_size() {
         let input = include_str!("../input/test7");
 
-        let dirs = parse_input(input);
-        let size = dirs.get_size();
-        println!("Root size: {}", size);
+        let fs = parse_input(input).unwrap();
+        assert_eq!(fs.size(ROOT), 48381165);
+        assert_eq!(capped_size(&fs, 100000), 95437);
+    }
 
-        //visit all dirs
-        let mut capped_size = 0;
+    #[test]
+    fn test_dir_to_delete() {
+        let fs = parse_input(include_str!("../input/test7")).unwrap();
 
-        let test = Rc::new(RefCell::new(dirs));
+        let dir = dir_to_delete(&fs, 70000000, 30000000).unwrap();
+        assert_eq!(fs.path(dir), "/d");
+        assert_eq!(fs.size(dir), 24933642);
+    }
 
-        breadth_first_map(test, |c: Link| {
-            let size = c.borrow().get_size();
+    #[test]
+    fn test_tree_larger_than_disk() {
+        let fs = parse_input(include_str!("../input/test7")).unwrap();
 
-            println!("dir: {:?} size: {}", c.borrow().get_name(), size);
-            if size <= 100000 {
-                println!("dir: {:?} size: {}", c.borrow().get_name(), size);
-                capped_size += size;
-            }
-        });
-        println!("Capped size: {}", capped_size);
-        assert_eq!(capped_size, 95437);
+        let dir = dir_to_delete(&fs, 40000000, 0).unwrap();
+        assert_eq!(fs.path(dir), "/d");
+        assert_eq!(dir_to_delete(&fs, 10000000, 0), Some(ROOT));
+        assert_eq!(dir_to_delete(&fs, 1000, 2000), None);
+    }
+
+    #[test]
+    fn test_lookup_and_du() {
+        let fs = parse_input(include_str!("../input/test7")).unwrap();
+
+        let e = fs.lookup("/a/e").unwrap();
+        assert_eq!(fs.size(e), 584);
+        assert_eq!(fs.path(e), "/a/e");
+        assert_eq!(fs.lookup("/a/x"), None);
+        assert_eq!(fs.lookup("a"), None);
+
+        assert_eq!(
+            fs.du(),
+            vec![
+                ("/".to_string(), 48381165),
+                ("/a".to_string(), 94853),
+                ("/a/e".to_string(), 584),
+                ("/d".to_string(), 24933642),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let fs = parse_input(include_str!("../input/test7")).unwrap();
+
+        let small: Vec<String> = fs
+            .find(SizeFilter::AtMost(100000))
+            .into_iter()
+            .map(|dir| fs.path(dir))
+            .collect();
+        assert_eq!(small, vec!["/a", "/a/e"]);
+
+        let big: Vec<String> = fs
+            .find(SizeFilter::AtLeast(1000000))
+            .into_iter()
+            .map(|dir| fs.path(dir))
+            .collect();
+        assert_eq!(big, vec!["/", "/d"]);
+    }
+
+    #[test]
+    fn test_tree() {
+        let fs = parse_input(include_str!("../input/test7")).unwrap();
+
+        let expected = "\
+- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+";
+        assert_eq!(fs.to_string(), expected);
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        assert_eq!(
+            parse_input("$ cd /\n$ cd ..").unwrap_err(),
+            ParseError::CdAboveRoot
+        );
+        assert_eq!(
+            parse_input("$ cd /\n123 a").unwrap_err(),
+            ParseError::OutputWithoutLs("123 a".to_string())
+        );
+        assert_eq!(
+            parse_input("$ cd /\n$ ls\nabc a").unwrap_err(),
+            ParseError::MalformedEntry("abc a".to_string())
+        );
+        assert_eq!(
+            parse_input("$ cd").unwrap_err(),
+            ParseError::MissingCdTarget
+        );
+        assert_eq!(
+            parse_input("$ ls -la").unwrap_err(),
+            ParseError::LsWithArguments("$ ls -la".to_string())
+        );
+        assert_eq!(
+            parse_input("$ rm -rf /").unwrap_err(),
+            ParseError::UnknownCommand("$ rm -rf /".to_string())
+        );
+        assert_eq!(
+            parse_input("$ cd /\n$ cd a").unwrap_err(),
+            ParseError::UnknownDir("a".to_string())
+        );
+        assert_eq!(
+            parse_input("$ cd /\n$ ls\ndir a\n$ cd a/b").unwrap_err(),
+            ParseError::InvalidName("a/b".to_string())
+        );
+        assert_eq!(
+            parse_input("$ ls\n12 x/y").unwrap_err(),
+            ParseError::InvalidName("x/y".to_string())
+        );
+        assert_eq!(
+            part1("$ cd /\n$ cd a").to_string(),
+            "unsolved (cd into unlisted directory: a)"
+        );
     }
 }
@@ -1,406 +1,366 @@
-use std::{
-    fmt::{Display, Formatter, Result},
-    iter::Rev,
-    ops::Range,
-};
-
-#[derive(Debug)]
-struct VisableTree {
-    height: i32,
-    top: bool,
-    bottom: bool,
-    left: bool,
-    right: bool,
+use crate::answer::Answer;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    InvalidHeight(char),
+    NotRectangular,
 }
 
-impl VisableTree {
-    fn new(height: i32) -> Self {
-        VisableTree {
-            height,
-            top: false,
-            bottom: false,
-            left: false,
-            right: false,
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidHeight(c) => write!(f, "invalid tree height: {:?}", c),
+            ParseError::NotRectangular => write!(f, "tree grid is not rectangular"),
         }
     }
 }
 
-#[derive(Debug)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
     Top,
     Bottom,
     Left,
     Right,
 }
 
-impl Direction {}
+impl Side {
+    const ALL: [Side; 4] = [Side::Top, Side::Bottom, Side::Left, Side::Right];
 
-struct Trees(Vec<Vec<VisableTree>>);
+    fn index(self) -> usize {
+        self as usize
+    }
+}
 
-#[derive(Debug, Clone)]
-enum RangeDirection {
-    Forward(Range<usize>),
-    Reverse(Rev<Range<usize>>),
+//What a single tree sees, distances are indexed by Side
+#[derive(Debug, PartialEq, Eq)]
+struct TreeView {
+    height: u8,
+    visible_from: Vec<Side>,
+    distances: [usize; 4],
 }
 
-impl Iterator for RangeDirection {
-    type Item = usize;
+impl TreeView {
+    fn distance(&self, side: Side) -> usize {
+        self.distances[side.index()]
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self {
-            RangeDirection::Forward(range) => range.next(),
-            RangeDirection::Reverse(range) => range.next(),
-        }
+    fn scenic_score(&self) -> usize {
+        self.distances.iter().product()
     }
 }
 
-impl Trees {
-    fn ranges(&self, dir: &Direction) -> (RangeDirection, RangeDirection) {
-        let rows = self.0.len();
-        let cols = self.0[0].len();
-        match dir {
-            Direction::Top => {
-                let first = RangeDirection::Forward(0..cols);
-                let second = RangeDirection::Forward(0..rows);
-                (first, second)
-            }
-            Direction::Bottom => {
-                let first = RangeDirection::Forward(0..cols);
-                let second = RangeDirection::Reverse((0..rows).rev());
-                (first, second)
-            }
-            Direction::Left => {
-                let first = RangeDirection::Forward(0..rows);
-                let second = RangeDirection::Forward(0..cols);
-                (first, second)
-            }
-            Direction::Right => {
-                let first = RangeDirection::Forward(0..rows);
-                let second = RangeDirection::Reverse((0..cols).rev());
-                (first, second)
+struct Forest {
+    width: usize,
+    height: usize,
+    heights: Vec<u8>,
+    visible: Vec<[bool; 4]>,
+    distances: Vec<[usize; 4]>,
+}
+
+impl Forest {
+    fn new(width: usize, heights: Vec<u8>) -> Self {
+        let height = heights.len().checked_div(width).unwrap_or(0);
+        let mut forest = Forest {
+            width,
+            height,
+            visible: vec![[false; 4]; heights.len()],
+            distances: vec![[0; 4]; heights.len()],
+            heights,
+        };
+        for side in Side::ALL {
+            for line in 0..forest.lines(side) {
+                let cells = forest.line(side, line);
+                forest.scan(side, &cells);
             }
         }
+        forest
     }
 
-    fn check_visibility(&mut self) {
-        let dirs = vec![
-            Direction::Top,
-            Direction::Bottom,
-            Direction::Left,
-            Direction::Right,
-        ];
+    fn lines(&self, side: Side) -> usize {
+        match side {
+            Side::Top | Side::Bottom => self.width,
+            Side::Left | Side::Right => self.height,
+        }
+    }
 
-        for dir in dirs {
-            let (first, second) = self.ranges(&dir);
-
-            for a in first {
-                let mut tallest_tree: i32 = -1;
-                for b in second.clone() {
-                    let height = match dir {
-                        Direction::Top => self.0[b][a].height,
-                        Direction::Bottom => self.0[b][a].height,
-                        Direction::Left => self.0[a][b].height,
-                        Direction::Right => self.0[a][b].height,
-                    };
-                    if height > tallest_tree {
-                        match dir {
-                            Direction::Top => self.0[b][a].top = true,
-                            Direction::Bottom => self.0[b][a].bottom = true,
-                            Direction::Left => self.0[a][b].left = true,
-                            Direction::Right => self.0[a][b].right = true,
-                        }
-                        tallest_tree = height;
-                    }
-                }
-            }
+    //Cell indices of one row or column, starting at the edge on the given side
+    fn line(&self, side: Side, line: usize) -> Vec<usize> {
+        let (w, h) = (self.width, self.height);
+        match side {
+            Side::Top => (0..h).map(|row| row * w + line).collect(),
+            Side::Bottom => (0..h).rev().map(|row| row * w + line).collect(),
+            Side::Left => (0..w).map(|col| line * w + col).collect(),
+            Side::Right => (0..w).rev().map(|col| line * w + col).collect(),
         }
     }
 
-    fn visible_from_dir(&self, dir: &Direction) {
-        println!("{:?}", dir);
-        for row in self.0.iter() {
-            for tree in row.iter() {
-                match dir {
-                    Direction::Top => {
-                        if tree.top {
-                            print!("X");
-                        } else {
-                            print!(" ");
-                        }
-                    }
-                    Direction::Bottom => {
-                        if tree.bottom {
-                            print!("X");
-                        } else {
-                            print!(" ");
-                        }
-                    }
-                    Direction::Left => {
-                        if tree.left {
-                            print!("X");
-                        } else {
-                            print!(" ");
-                        }
-                    }
-                    Direction::Right => {
-                        if tree.right {
-                            print!("X");
-                        } else {
-                            print!(" ");
-                        }
-                    }
-                }
+    //Walk inwards from the edge keeping a stack of trees with non-increasing
+    //heights, anything shorter than the current tree can never block a later one
+    fn scan(&mut self, side: Side, cells: &[usize]) {
+        let mut stack: Vec<usize> = Vec::new();
+        for (pos, &cell) in cells.iter().enumerate() {
+            let height = self.heights[cell];
+            while stack
+                .last()
+                .is_some_and(|&top| self.heights[cells[top]] < height)
+            {
+                stack.pop();
             }
-            println!();
+            let (visible, distance) = match stack.last() {
+                None => (true, pos),
+                Some(&blocker) => (false, pos - blocker),
+            };
+            self.visible[cell][side.index()] = visible;
+            self.distances[cell][side.index()] = distance;
+            stack.push(pos);
         }
-        println!("");
     }
 
-    fn iterate_2d_matrix(
-        &self,
-        start_row: usize,
-        start_col: usize,
-    ) -> (
-        impl Iterator<Item = &VisableTree>,
-        impl Iterator<Item = &VisableTree>,
-        impl Iterator<Item = &VisableTree>,
-        impl Iterator<Item = &VisableTree>,
-    ) {
-        let num_rows = self.0.len();
-        let num_cols = if num_rows > 0 { self.0[0].len() } else { 0 };
-
-        let up_iter = (0..start_row).rev().map(move |r| &self.0[r][start_col]);
-        let down_iter = (start_row + 1..num_rows).map(move |r| &self.0[r][start_col]);
-        let left_iter = (0..start_col).rev().map(move |c| &self.0[start_row][c]);
-        let right_iter = (start_col + 1..num_cols).map(move |c| &self.0[start_row][c]);
-
-        (up_iter, down_iter, left_iter, right_iter)
+    fn cell(&self, row: usize, col: usize) -> Option<usize> {
+        (row < self.height && col < self.width).then(|| row * self.width + col)
     }
 
-    fn calculate_max_scenic_score(&mut self) -> i32 {
-        self.0
-            .iter()
-            .enumerate()
-            .map(|(row_index, row)| {
-                row.iter()
-                    .enumerate()
-                    .map(|(col_index, this_tree)| {
-                        let (up_iter, down_iter, left_iter, right_iter) =
-                            self.iterate_2d_matrix(row_index, col_index);
-
-                        let mut up_trees = 0;
-                        for tree in up_iter {
-                            up_trees += 1;
-                            if tree.height >= this_tree.height {
-                                break;
-                            }
-                        }
-
-                        let mut down_trees = 0;
-                        for tree in down_iter {
-                            down_trees += 1;
-                            if tree.height >= this_tree.height {
-                                break;
-                            }
-                        }
-
-                        let mut left_trees = 0;
-                        for tree in left_iter {
-                            left_trees += 1;
-                            if tree.height >= this_tree.height {
-                                break;
-                            }
-                        }
-
-                        let mut right_trees = 0;
-                        for tree in right_iter {
-                            right_trees += 1;
-                            if tree.height >= this_tree.height {
-                                break;
-                            }
-                        }
-
-                        let score = up_trees * down_trees * left_trees * right_trees;
-
-                        score
-                    })
-                    .max()
-                    .unwrap()
-                    .clone()
-            })
+    fn view(&self, row: usize, col: usize) -> Option<TreeView> {
+        let cell = self.cell(row, col)?;
+        Some(TreeView {
+            height: self.heights[cell],
+            visible_from: Side::ALL
+                .into_iter()
+                .filter(|side| self.visible[cell][side.index()])
+                .collect(),
+            distances: self.distances[cell],
+        })
+    }
+
+    fn is_visible(&self, cell: usize) -> bool {
+        self.visible[cell].iter().any(|&v| v)
+    }
+
+    fn scenic_score(&self, cell: usize) -> usize {
+        self.distances[cell].iter().product()
+    }
+
+    fn visible_count(&self) -> usize {
+        (0..self.heights.len())
+            .filter(|&cell| self.is_visible(cell))
+            .count()
+    }
+
+    fn max_scenic_score(&self) -> usize {
+        (0..self.heights.len())
+            .map(|cell| self.scenic_score(cell))
             .max()
-            .unwrap()
-            .clone()
+            .unwrap_or(0)
     }
-}
 
-impl Display for Trees {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        for row in self.0.iter() {
-            for tree in row.iter() {
-                if tree.top || tree.bottom || tree.left || tree.right {
-                    write!(f, "X")?;
-                } else {
-                    write!(f, " ")?;
-                }
+    fn render<F>(&self, cell_char: F) -> String
+    where
+        F: Fn(usize) -> char,
+    {
+        let mut s = String::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                s.push(cell_char(row * self.width + col));
             }
-            writeln!(f)?;
+            s.push('\n');
         }
-        Ok(())
+        s
     }
-}
 
-fn parse_input(input: &str) -> Trees {
-    Trees(
-        input
-            .lines()
-            .map(|line| {
-                line.chars()
-                    .map(|num| VisableTree::new(num.to_digit(10).unwrap() as i32))
-                    .collect::<Vec<VisableTree>>()
-            })
-            .collect::<Vec<Vec<VisableTree>>>(),
-    )
-}
+    //Trees visible from one side, or from any side when no side is given
+    fn render_visibility(&self, side: Option<Side>) -> String {
+        self.render(|cell| {
+            let visible = match side {
+                Some(side) => self.visible[cell][side.index()],
+                None => self.is_visible(cell),
+            };
+            if visible {
+                'X'
+            } else {
+                ' '
+            }
+        })
+    }
 
-fn part1() {
-    let input = include_str!("../input/8");
-    let mut trees = parse_input(input);
-    trees.check_visibility();
-
-    let count = trees
-        .0
-        .iter()
-        .map(|row| {
-            row.iter()
-                .filter(|tree| tree.top || tree.bottom || tree.left || tree.right)
-                .count()
+    //Scenic scores scaled to a ramp of characters, a space is a score of zero
+    fn heatmap(&self) -> String {
+        const RAMP: &[u8] = b" .:-=+*#%@";
+        let max = self.max_scenic_score();
+        self.render(|cell| {
+            let score = self.scenic_score(cell);
+            if score == 0 {
+                return ' ';
+            }
+            let level = (score * (RAMP.len() - 1)).div_ceil(max);
+            RAMP[level] as char
         })
-        .sum::<usize>();
+    }
+}
 
-    trees.visible_from_dir(&Direction::Top);
-    trees.visible_from_dir(&Direction::Bottom);
-    trees.visible_from_dir(&Direction::Left);
-    trees.visible_from_dir(&Direction::Right);
+impl Display for Forest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render_visibility(None))
+    }
+}
 
-    println!("{}", trees);
-    println!("Count: {}", count);
+fn parse_input(input: &str) -> Result<Forest, ParseError> {
+    let width = input.lines().next().map_or(0, |line| line.chars().count());
+    let mut heights = Vec::new();
+    for line in input.lines() {
+        if line.chars().count() != width {
+            return Err(ParseError::NotRectangular);
+        }
+        for c in line.chars() {
+            let height = c.to_digit(10).ok_or(ParseError::InvalidHeight(c))?;
+            heights.push(height as u8);
+        }
+    }
+    Ok(Forest::new(width, heights))
 }
 
-fn part2() {
-    let input = include_str!("../input/8");
-    let mut trees = parse_input(input);
-    let max = trees.calculate_max_scenic_score();
+pub fn part1(input: &str) -> Answer {
+    parse_input(input)
+        .map(|forest| forest.visible_count())
+        .into()
+}
 
-    println!("Max scenic score: {}", max);
+pub fn part2(input: &str) -> Answer {
+    parse_input(input)
+        .map(|forest| forest.max_scenic_score())
+        .into()
 }
 
-pub fn run() {
-    part1();
-    part2();
+pub fn export(input: &str, format: &str) -> Result<Option<String>, String> {
+    let forest = parse_input(input).map_err(|e| e.to_string())?;
+    Ok(match format {
+        "visibility" => Some(forest.to_string()),
+        "heatmap" => Some(forest.heatmap()),
+        _ => None,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    //Walks every direction from every tree, used to check the stack version
+    fn naive(forest: &Forest, row: usize, col: usize) -> ([bool; 4], [usize; 4]) {
+        let height = forest.heights[forest.cell(row, col).unwrap()];
+        let lines: [Vec<(usize, usize)>; 4] = [
+            (0..row).rev().map(|r| (r, col)).collect(),
+            (row + 1..forest.height).map(|r| (r, col)).collect(),
+            (0..col).rev().map(|c| (row, c)).collect(),
+            (col + 1..forest.width).map(|c| (row, c)).collect(),
+        ];
+        let mut visible = [true; 4];
+        let mut distances = [0; 4];
+        for (i, line) in lines.iter().enumerate() {
+            for &(r, c) in line {
+                distances[i] += 1;
+                if forest.heights[forest.cell(r, c).unwrap()] >= height {
+                    visible[i] = false;
+                    break;
+                }
+            }
+        }
+        (visible, distances)
+    }
+
+    fn assert_matches_naive(forest: &Forest) {
+        for row in 0..forest.height {
+            for col in 0..forest.width {
+                let cell = forest.cell(row, col).unwrap();
+                assert_eq!(
+                    naive(forest, row, col),
+                    (forest.visible[cell], forest.distances[cell]),
+                    "tree at {},{}",
+                    row,
+                    col
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_parse() {
         let input = include_str!("../input/test8");
-        let mut trees = parse_input(input);
-        trees.check_visibility();
-
-        let count = trees
-            .0
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .filter(|tree| tree.top || tree.bottom || tree.left || tree.right)
-                    .count()
-            })
-            .sum::<usize>();
-
-        trees.visible_from_dir(&Direction::Top);
-        trees.visible_from_dir(&Direction::Bottom);
-        trees.visible_from_dir(&Direction::Left);
-        trees.visible_from_dir(&Direction::Right);
-
-        println!("{}", trees);
-        println!("count: {}", count);
-        assert!(count == 21);
+        let forest = parse_input(input).unwrap();
+
+        assert_eq!(forest.visible_count(), 21);
+        assert_eq!(forest.to_string(), "XXXXX\nXXX X\nXX XX\nX X X\nXXXXX\n");
     }
 
     #[test]
     fn test_other_input() {
         let input = include_str!("../input/test8_2");
-        let mut trees = parse_input(input);
-        trees.check_visibility();
-
-        let count = trees
-            .0
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .filter(|tree| tree.top || tree.bottom || tree.left || tree.right)
-                    .count()
-            })
-            .sum::<usize>();
-
-        trees.visible_from_dir(&Direction::Top);
-        trees.visible_from_dir(&Direction::Bottom);
-        trees.visible_from_dir(&Direction::Left);
-        trees.visible_from_dir(&Direction::Right);
-
-        assert!(count == 65);
+        let forest = parse_input(input).unwrap();
+
+        assert_eq!(forest.visible_count(), 65);
+        assert_matches_naive(&forest);
     }
 
     #[test]
-    fn test_iterator() {
-        let input = include_str!("../input/test8");
-
-        let trees = parse_input(input);
-
-        let (up_iter, down_iter, left_iter, right_iter) = trees.iterate_2d_matrix(3, 3);
-
-        let up_iter = up_iter.collect::<Vec<&VisableTree>>();
-
-        assert!(up_iter.len() == 3);
-
-        let down_iter = down_iter.collect::<Vec<&VisableTree>>();
-
-        assert!(down_iter.len() == 1);
-
-        let left_iter = left_iter.collect::<Vec<&VisableTree>>();
-
-        assert!(left_iter.len() == 3);
-
-        let right_iter = right_iter.collect::<Vec<&VisableTree>>();
-
-        assert!(right_iter.len() == 1);
-
-        for tree in up_iter.iter() {
-            println!("up: {:?}", tree);
-        }
-
-        assert!(up_iter[0].height == 3);
-        assert!(up_iter[1].height == 1);
-        assert!(up_iter[2].height == 7);
+    fn test_matches_naive_real() {
+        let forest = parse_input(include_str!("../input/8")).unwrap();
+        assert_matches_naive(&forest);
+    }
 
-        assert!(down_iter[0].height == 9);
+    #[test]
+    fn test_view() {
+        let input = include_str!("../input/test8");
+        let forest = parse_input(input).unwrap();
+
+        let view = forest.view(1, 2).unwrap();
+        assert_eq!(view.height, 5);
+        assert_eq!(view.visible_from, vec![Side::Top, Side::Right]);
+        assert_eq!(view.distance(Side::Top), 1);
+        assert_eq!(view.distance(Side::Left), 1);
+        assert_eq!(view.distance(Side::Right), 2);
+        assert_eq!(view.distance(Side::Bottom), 2);
+        assert_eq!(view.scenic_score(), 4);
+
+        let view = forest.view(3, 2).unwrap();
+        assert_eq!(view.distances, [2, 1, 2, 2]);
+        assert_eq!(view.scenic_score(), 8);
+
+        assert_eq!(forest.view(5, 0), None);
+    }
 
-        assert!(left_iter[0].height == 5);
-        assert!(left_iter[1].height == 3);
-        assert!(left_iter[2].height == 3);
+    #[test]
+    fn test_render_side() {
+        let input = include_str!("../input/test8");
+        let forest = parse_input(input).unwrap();
 
-        assert!(right_iter[0].height == 9);
+        assert_eq!(
+            forest.render_visibility(Some(Side::Left)),
+            "X  X \nXX   \nX    \nX X X\nXX X \n"
+        );
     }
 
     #[test]
     fn test_scenic_score() {
         let input = include_str!("../input/test8");
+        let forest = parse_input(input).unwrap();
 
-        let mut trees = parse_input(input);
-        let max = trees.calculate_max_scenic_score();
+        assert_eq!(forest.max_scenic_score(), 8);
+        assert_eq!(forest.heatmap(), "     \n :+: \n #:- \n :@= \n     \n");
+    }
 
-        assert_eq!(max, 8);
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_input("12\n3").err(), Some(ParseError::NotRectangular));
+        assert_eq!(
+            parse_input("12\n3x").err(),
+            Some(ParseError::InvalidHeight('x'))
+        );
+        assert_eq!(
+            part1("1\n23").to_string(),
+            "unsolved (tree grid is not rectangular)"
+        );
+        assert_eq!(
+            part2("1a").to_string(),
+            "unsolved (invalid tree height: 'a')"
+        );
     }
 }
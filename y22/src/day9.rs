@@ -1,3 +1,4 @@
+use crate::answer::Answer;
 use std::collections::HashSet;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -14,20 +15,6 @@ enum Direction {
 }
 
 impl Direction {
-    fn opposite(&self) -> Self {
-        match self {
-            Direction::UpLeft => Direction::DownRight,
-            Direction::Up => Direction::Down,
-            Direction::UpRight => Direction::DownLeft,
-            Direction::Left => Direction::Right,
-            Direction::On => Direction::On,
-            Direction::Right => Direction::Left,
-            Direction::DownLeft => Direction::UpRight,
-            Direction::Down => Direction::Up,
-            Direction::DownRight => Direction::UpLeft,
-        }
-    }
-
     fn to_vec(&self) -> (i32, i32) {
         match self {
             Direction::UpLeft => (-1, 1),
@@ -41,26 +28,6 @@ impl Direction {
             Direction::DownRight => (1, -1),
         }
     }
-    fn from_vec(vec: (i32, i32)) -> Option<Self> {
-        match vec {
-            (-1, 1) => Some(Direction::UpLeft),
-            (0, 1) => Some(Direction::Up),
-            (1, 1) => Some(Direction::UpRight),
-            (-1, 0) => Some(Direction::Left),
-            (0, 0) => Some(Direction::On),
-            (1, 0) => Some(Direction::Right),
-            (-1, -1) => Some(Direction::DownLeft),
-            (0, -1) => Some(Direction::Down),
-            (1, -1) => Some(Direction::DownRight),
-            _ => None,
-        }
-    }
-    fn plus(&self, other_dir: &Direction) -> Option<Direction> {
-        let (x1, y1) = self.to_vec();
-        let (x2, y2) = other_dir.to_vec();
-        let (x, y) = (x1 + x2, y1 + y2);
-        Direction::from_vec((x, y))
-    }
 }
 
 impl From<&str> for Direction {
@@ -75,7 +42,7 @@ impl From<&str> for Direction {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 struct Move {
     direction: Direction,
 }
@@ -97,78 +64,122 @@ impl Pos {
     }
 }
 
-struct RopeSegment {
-    head_relative: Direction,
-    tail: Pos,
-    tail_visited: HashSet<Pos>,
+struct Rope {
+    knots: Vec<Pos>,
+    visited: Vec<HashSet<Pos>>,
 }
 
-impl RopeSegment {
-    fn new() -> Self {
-        let mut rope = RopeSegment {
-            head_relative: Direction::On,
-            tail: Pos::default(),
-            tail_visited: HashSet::new(),
-        };
-        rope.tail_visited.insert(Pos::default());
-        rope
+impl Rope {
+    fn new(knots: usize) -> Result<Self, String> {
+        if knots == 0 {
+            return Err("a rope needs at least one knot".to_string());
+        }
+        Ok(Rope {
+            knots: vec![Pos::default(); knots],
+            visited: vec![HashSet::from([Pos::default()]); knots],
+        })
+    }
+
+    fn tail(&self) -> Pos {
+        *self.knots.last().unwrap()
     }
-    fn move_segment(&mut self, m: &Move) -> Move {
-        let mut tail_move = Move::new(Direction::On);
-
-        let new_head_relative = self.head_relative.plus(&m.direction);
-
-        if let Some(new_head_relative) = new_head_relative {
-            //Only move the head and the tail stays in the same pos
-            self.head_relative = new_head_relative;
-        } else {
-            let new_relative_head_vec = (
-                self.head_relative.to_vec().0 + m.direction.to_vec().0,
-                self.head_relative.to_vec().1 + m.direction.to_vec().1,
-            );
-
-            let normalized_new_relative_head =
-                Direction::from_vec((new_relative_head_vec.0 / 2, new_relative_head_vec.1 / 2))
-                    .unwrap();
-
-            match (&self.head_relative, m.direction) {
-                (head, move_dir) if *head == move_dir => {
-                    tail_move = Move::new(move_dir);
-                }
-                (head, move_dir) if *head == move_dir.opposite() => {
-                    self.head_relative = Direction::On;
-                }
-                (_, _) => match (new_relative_head_vec.0, new_relative_head_vec.1) {
-                    (_, 0) | (0, _) => {
-                        self.head_relative = normalized_new_relative_head;
-                        tail_move = Move::new(normalized_new_relative_head.clone());
-                    }
-                    (-2, y) | (2, y) => {
-                        self.head_relative = normalized_new_relative_head;
-                        tail_move = Move::new(
-                            normalized_new_relative_head
-                                .plus(&Direction::from_vec((0, y)).unwrap())
-                                .unwrap(),
-                        );
-                    }
-                    (x, -2) | (x, 2) => {
-                        self.head_relative = normalized_new_relative_head;
-                        tail_move = Move::new(
-                            normalized_new_relative_head
-                                .plus(&Direction::from_vec((x, 0)).unwrap())
-                                .unwrap(),
-                        );
-                    }
-                    _ => {
-                        println!("Invalid move");
-                    }
-                },
+
+    //Move the head one step, every other knot follows the knot in front of it
+    fn step(&mut self, m: &Move) {
+        self.knots[0].move_pos(m);
+        for i in 1..self.knots.len() {
+            let (hx, hy) = self.knots[i - 1].0;
+            let (tx, ty) = self.knots[i].0;
+            let (dx, dy) = (hx - tx, hy - ty);
+            if dx.abs() <= 1 && dy.abs() <= 1 {
+                break;
             }
+            self.knots[i] = Pos((tx + dx.signum(), ty + dy.signum()));
+            self.visited[i].insert(self.knots[i]);
         }
-        self.tail.move_pos(&tail_move);
-        self.tail_visited.insert(self.tail);
-        tail_move
+        self.visited[0].insert(self.knots[0]);
     }
+
+    fn apply(&mut self, moves: &[Move]) {
+        for m in moves {
+            self.step(m);
+        }
+    }
+
+    fn visited(&self, knot: usize) -> &HashSet<Pos> {
+        &self.visited[knot]
+    }
+
+    //Smallest frame containing the start and every cell any knot has been on
+    fn bounds(&self) -> ((i32, i32), (i32, i32)) {
+        self.visited
+            .iter()
+            .flatten()
+            .fold(((0, 0), (0, 0)), |(min, max), p| {
+                let (x, y) = p.0;
+                ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+            })
+    }
+
+    fn label(&self, knot: usize) -> char {
+        match knot {
+            0 => 'H',
+            k if k == self.knots.len() - 1 && self.knots.len() == 2 => 'T',
+            //Past the 36 digits and letters every knot looks the same
+            k => char::from_digit(k as u32, 36).unwrap_or('*'),
+        }
+    }
+
+    fn render_with<F>(min: (i32, i32), max: (i32, i32), cell: F) -> String
+    where
+        F: Fn(Pos) -> Option<char>,
+    {
+        let mut s = String::new();
+        for y in (min.1..=max.1).rev() {
+            for x in min.0..=max.0 {
+                let pos = Pos((x, y));
+                let c = match cell(pos) {
+                    Some(c) => c,
+                    None if pos == Pos::default() => 's',
+                    None => '.',
+                };
+                s.push(c);
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    //Knots in front cover the ones behind them, like in the puzzle diagrams
+    fn render(&self, min: (i32, i32), max: (i32, i32)) -> String {
+        Self::render_with(min, max, |pos| {
+            self.knots
+                .iter()
+                .position(|&k| k == pos)
+                .map(|knot| self.label(knot))
+        })
+    }
+
+    fn render_trail(&self, knot: usize, min: (i32, i32), max: (i32, i32)) -> String {
+        Self::render_with(min, max, |pos| {
+            (pos != Pos::default() && self.visited[knot].contains(&pos)).then_some('#')
+        })
+    }
+}
+
+//One frame of the rope for every step of the head, in a frame fitting all of them
+fn frames(knots: usize, moves: &[Move]) -> Result<Vec<String>, String> {
+    let mut rope = Rope::new(knots)?;
+    rope.apply(moves);
+    let (min, max) = rope.bounds();
+
+    let mut rope = Rope::new(knots)?;
+    let mut frames = vec![rope.render(min, max)];
+    for m in moves {
+        rope.step(m);
+        frames.push(rope.render(min, max));
+    }
+    Ok(frames)
 }
 
 fn parse_input(input: &str) -> Vec<Move> {
@@ -185,55 +196,18 @@ fn parse_input(input: &str) -> Vec<Move> {
     result
 }
 
-pub fn part1() {
-    println!("Part 1");
-
-    let input = include_str!("../input/9");
-    let moves = parse_input(input);
-    let mut rope = RopeSegment::new();
-
-    for m in moves {
-        let _ = rope.move_segment(&m);
-    }
-
-    let num_visited_nodes = rope.tail_visited.iter().count();
-
-    println!("Number of visited nodes: {}", num_visited_nodes);
+fn tail_visited(input: &str, knots: usize) -> Result<usize, String> {
+    let mut rope = Rope::new(knots)?;
+    rope.apply(&parse_input(input));
+    Ok(rope.visited(knots - 1).len())
 }
 
-fn part2() {
-    let mut rope = [
-        RopeSegment::new(),
-        RopeSegment::new(),
-        RopeSegment::new(),
-        RopeSegment::new(),
-        RopeSegment::new(),
-        RopeSegment::new(),
-        RopeSegment::new(),
-        RopeSegment::new(),
-        RopeSegment::new(),
-    ];
-
-    let input = include_str!("../input/9");
-    let moves = parse_input(input);
-
-    for m in moves {
-        let mut next_segment_move = m;
-        rope.iter_mut().for_each(|r| {
-            if next_segment_move.direction != Direction::On {
-                next_segment_move = r.move_segment(&next_segment_move);
-            }
-        });
-    }
-    let visited_tail_nodes = rope[8].tail_visited.iter().count();
-    println!("Part 2");
-    println!("Number of visited nodes: {}", visited_tail_nodes);
+pub fn part1(input: &str) -> Answer {
+    tail_visited(input, 2).into()
 }
 
-pub fn run() {
-    println!("Day 8");
-    part1();
-    part2();
+pub fn part2(input: &str) -> Answer {
+    tail_visited(input, 10).into()
 }
 
 #[cfg(test)]
@@ -269,255 +243,170 @@ mod tests {
     #[test]
     fn test_input_move_rope_segment() {
         let input = include_str!("../input/test9");
-        let moves = parse_input(input);
-        let mut rope = RopeSegment::new();
-
-        for m in moves {
-            rope.move_segment(&m);
-        }
-
-        assert_eq!(rope.tail, Pos((1, 2)));
+        let mut rope = Rope::new(2).unwrap();
+        rope.apply(&parse_input(input));
 
-        let num_visited_nodes = rope.tail_visited.iter().count();
-        assert_eq!(num_visited_nodes, 13);
+        assert_eq!(rope.tail(), Pos((1, 2)));
+        assert_eq!(rope.visited(1).len(), 13);
     }
 
     #[test]
     fn test_move_rope_segment() {
-        let mut rope = RopeSegment {
-            head_relative: Direction::On,
-            tail: Pos::default(),
-            tail_visited: HashSet::new(),
-        };
-
-        rope.move_segment(&Move::new(Direction::Right));
-        rope.move_segment(&Move::new(Direction::Right));
-        rope.move_segment(&Move::new(Direction::Right));
-        rope.move_segment(&Move::new(Direction::Right));
-        assert_eq!(rope.tail, Pos((3, 0)));
+        let mut rope = Rope::new(2).unwrap();
+        rope.apply(&[Move::new(Direction::Right); 4]);
+        assert_eq!(rope.tail(), Pos((3, 0)));
     }
 
     #[test]
     fn test_move_rope() {
-        let mut rope = [
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-        ];
-
         let input = include_str!("../input/test9");
-        let moves = parse_input(input);
-
-        for m in moves {
-            let mut next_segment_move = m;
-
-            println!("\n\nHead Move: {:?}", &next_segment_move.direction);
-
-            rope.iter_mut().enumerate().for_each(|(i, r)| {
-                if next_segment_move.direction == Direction::On {
-                } else {
-                    println!(
-                        "Segment: {} Previous tail moved: {:?}",
-                        i, &next_segment_move.direction
-                    );
-                    next_segment_move = r.move_segment(&next_segment_move);
-                    println!("Segment: {} New tail: {:?}", i, r.tail);
-                }
-            });
-        }
-
-        for r in rope.iter() {
-            println!("Rope: {:?}", r.tail);
-        }
-
-        assert_eq!(rope[0].tail, Pos((1, 2)));
-        assert_eq!(rope[1].tail, Pos((2, 2)));
-        assert_eq!(rope[2].tail, Pos((3, 2)));
-        assert_eq!(rope[3].tail, Pos((2, 2)));
-
-        let visited_tail_nodes = rope[9].tail_visited.iter().count();
-        assert_eq!(visited_tail_nodes, 1);
+        let mut rope = Rope::new(10).unwrap();
+        rope.apply(&parse_input(input));
+
+        assert_eq!(rope.knots[1], Pos((1, 2)));
+        assert_eq!(rope.knots[2], Pos((2, 2)));
+        assert_eq!(rope.knots[3], Pos((3, 2)));
+        assert_eq!(rope.knots[4], Pos((2, 2)));
+        assert_eq!(rope.visited(9).len(), 1);
     }
+
     #[test]
     fn test_move_rope2() {
-        let mut rope = [
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-        ];
-
-        let input = include_str!("../input/test9_2");
-        let moves = parse_input(input);
-
-        for m in moves {
-            // Create a 30x30 grid for visualizing positions from -15 to -15
-            let mut grid = [[0; 30]; 30];
-            let mut next_segment_move = m;
-
-            println!("\n\nHead Move: {:?}", &next_segment_move.direction);
-
-            rope.iter_mut().enumerate().for_each(|(i, r)| {
-                if next_segment_move.direction == Direction::On {
-                    grid[(r.tail.0 .0 + 15) as usize][(r.tail.0 .1 + 15) as usize] = i + 1;
-                } else {
-                    println!(
-                        "Segment: {} Previous tail moved: {:?}",
-                        i, &next_segment_move.direction
-                    );
-                    next_segment_move = r.move_segment(&next_segment_move);
-                    println!("Segment: {} New tail: {:?}", i, r.tail);
-                    println!("X: {}, Y: {}", r.tail.0 .0, r.tail.0 .1);
-                    grid[(r.tail.0 .0 + 15) as usize][(r.tail.0 .1 + 15) as usize] = i + 1;
-                }
-            });
-
-            grid[15][15] = 10;
-
-            for i in (0..30).rev() {
-                for j in 0..30 {
-                    if grid[j][i] == 0 {
-                        if i == 0 {
-                            print!("-");
-                            continue;
-                        }
-                        if j == 0 {
-                            print!("|");
-                            continue;
-                        }
-                        print!(".");
-                    } else if grid[j][i] == 10 {
-                        print!("s");
-                    } else {
-                        print!("{}", grid[j][i]);
-                    }
-                }
-                println!();
-            }
-        }
-
-        for r in rope.iter() {
-            println!("Rope: {:?}", r.tail);
-        }
-
-        let mut grid = [[0; 30]; 30];
-
-        rope[8].tail_visited.iter().for_each(|p| {
-            grid[(p.0 .0 + 15) as usize][(p.0 .1 + 15) as usize] = 1;
-        });
-
-        for i in (0..30).rev() {
-            for j in 0..30 {
-                if grid[j][i] == 0 {
-                    print!(".");
-                } else {
-                    print!("#");
-                }
-            }
-            println!();
-        }
-
-        let visited_tail_nodes = rope[8].tail_visited.iter().count();
-        assert_eq!(visited_tail_nodes, 36);
+        assert_eq!(tail_visited(include_str!("../input/test9_2"), 10), Ok(36));
     }
 
     #[test]
     fn test_move_rope3() {
-        let mut rope = [
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-            RopeSegment::new(),
-        ];
-
-        let input = include_str!("../input/test9_3");
-        let moves = parse_input(input);
+        assert_eq!(tail_visited(include_str!("../input/test9_3"), 10), Ok(8));
+    }
 
-        for m in moves {
-            // Create a 30x30 grid for visualizing positions from -15 to -15
-            let mut grid = [[0; 30]; 30];
-            let mut next_segment_move = m;
-
-            println!("\n\nHead Move: {:?}", &next_segment_move.direction);
-
-            rope.iter_mut().enumerate().for_each(|(i, r)| {
-                if next_segment_move.direction == Direction::On {
-                    grid[(r.tail.0 .0 + 15) as usize][(r.tail.0 .1 + 15) as usize] = i + 1;
-                } else {
-                    println!(
-                        "Segment: {} Previous tail moved: {:?}",
-                        i, &next_segment_move.direction
-                    );
-                    next_segment_move = r.move_segment(&next_segment_move);
-                    println!("Segment: {} New tail: {:?}", i, r.tail);
-                    println!("X: {}, Y: {}", r.tail.0 .0, r.tail.0 .1);
-                    grid[(r.tail.0 .0 + 15) as usize][(r.tail.0 .1 + 15) as usize] = i + 1;
-                }
-            });
-
-            grid[15][15] = 10;
-
-            for i in (0..30).rev() {
-                for j in 0..30 {
-                    if grid[j][i] == 0 {
-                        if i == 0 {
-                            print!("-");
-                            continue;
-                        }
-                        if j == 0 {
-                            print!("|");
-                            continue;
-                        }
-                        print!(".");
-                    } else if grid[j][i] == 10 {
-                        print!("s");
-                    } else {
-                        print!("{}", grid[j][i]);
-                    }
-                }
-                println!();
-            }
-        }
+    #[test]
+    fn test_render() {
+        let moves = parse_input(include_str!("../input/test9"));
+        let mut rope = Rope::new(2).unwrap();
 
-        for r in rope.iter() {
-            println!("Rope: {:?}", r.tail);
-        }
+        rope.apply(&moves[..4]);
+        assert_eq!(
+            rope.render((0, 0), (5, 4)),
+            "......\n......\n......\n......\ns..TH.\n"
+        );
 
-        let mut grid = [[0; 30]; 30];
+        rope.apply(&moves[4..]);
+        assert_eq!(
+            rope.render_trail(1, (0, 0), (5, 4)),
+            "..##..\n...##.\n.####.\n....#.\ns###..\n"
+        );
+    }
 
-        rope[8].tail_visited.iter().for_each(|p| {
-            grid[(p.0 .0 + 15) as usize][(p.0 .1 + 15) as usize] = 1;
-        });
+    #[test]
+    fn test_render_ten_knots() {
+        let moves = parse_input(include_str!("../input/test9_2"));
+        let mut rope = Rope::new(10).unwrap();
+
+        rope.apply(&moves[..5]);
+        let expected = "\
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+...........54321H.........
+..........................
+..........................
+..........................
+..........................
+..........................
+";
+        assert_eq!(rope.render((-11, -5), (14, 15)), expected);
+
+        rope.apply(&moves[5..]);
+        let expected = "\
+H.........................
+1.........................
+2.........................
+3.........................
+4.........................
+5.........................
+6.........................
+7.........................
+8.........................
+9.........................
+..........................
+..........................
+..........................
+..........................
+..........................
+...........s..............
+..........................
+..........................
+..........................
+..........................
+..........................
+";
+        assert_eq!(rope.render((-11, -5), (14, 15)), expected);
+
+        let expected = "\
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+..........................
+#.........................
+#.............###.........
+#............#...#........
+.#..........#.....#.......
+..#..........#.....#......
+...#........#.......#.....
+....#......s.........#....
+.....#..............#.....
+......#............#......
+.......#..........#.......
+........#........#........
+.........########.........
+";
+        assert_eq!(rope.render_trail(9, (-11, -5), (14, 15)), expected);
+    }
 
-        for i in (0..30).rev() {
-            for j in 0..30 {
-                if grid[j][i] == 0 {
-                    print!(".");
-                } else {
-                    print!("#");
-                }
-            }
-            println!();
-        }
+    #[test]
+    fn test_frames() {
+        let moves = parse_input("R 2\nU 1");
+        let frames = frames(2, &moves).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0], "...\nH..\n");
+        assert_eq!(frames[2], "...\nsTH\n");
+        assert_eq!(frames[3], "..H\nsT.\n");
+    }
 
-        let visited_tail_nodes = rope[8].tail_visited.iter().count();
-        assert_eq!(visited_tail_nodes, 8);
+    #[test]
+    fn test_invalid_knots() {
+        assert!(Rope::new(0).is_err());
+        assert_eq!(
+            tail_visited("R 1", 0),
+            Err("a rope needs at least one knot".to_string())
+        );
+        assert!(frames(0, &[]).is_err());
+    }
+
+    #[test]
+    fn test_labels_past_36_knots() {
+        let rope = Rope::new(40).unwrap();
+        assert_eq!(rope.label(0), 'H');
+        assert_eq!(rope.label(35), 'z');
+        assert_eq!(rope.label(36), '*');
+        assert_eq!(rope.render((0, 0), (0, 0)), "H\n");
     }
 }
@@ -1,6 +1,5 @@
 #![allow(dead_code)]
-#![feature(slice_first_last_chunk)]
-#![feature(linked_list_remove)]
+mod answer;
 mod day1;
 mod day10;
 mod day11;
@@ -26,30 +25,231 @@ mod day6;
 mod day7;
 mod day8;
 mod day9;
+
+use answer::Answer;
+use std::io::Read;
+
+//Settings from the command line for days that can be tuned
+#[derive(Default)]
+struct Options {
+    //Worker threads for days that split their search, 0 picks one per core
+    threads: usize,
+    //Rock shapes and chamber for day 17, the puzzle's when not given
+    rocks: Option<String>,
+    chamber: day17::Config,
+}
+
+impl Options {
+    fn threads(&self) -> usize {
+        match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+
+    fn rocks(&self) -> &str {
+        self.rocks.as_deref().unwrap_or(day17::ROCKS)
+    }
+}
+
+type Part = fn(&str, &Options) -> Answer;
+//Both parts read straight from stdin instead of from one string
+type Stream = fn(&mut dyn Read) -> std::io::Result<(Answer, Answer)>;
+//Extra output such as renders or 3D models, Ok(None) for an unknown format
+type Export = fn(&str, &str, &Options) -> Result<Option<String>, String>;
+
+struct Day {
+    input: &'static str,
+    part1: Part,
+    part2: Part,
+    export: Option<Export>,
+    stream: Option<Stream>,
+}
+
+macro_rules! day {
+    ($day:ident, $input:literal) => {
+        day!($day, $input, None, None)
+    };
+    ($day:ident, $input:literal, export) => {
+        day!(
+            $day,
+            $input,
+            Some(|input, format, _| $day::export(input, format)),
+            None
+        )
+    };
+    ($day:ident, $input:literal, stream) => {
+        day!($day, $input, None, Some($day::stream))
+    };
+    ($day:ident, $input:literal, $export:expr, $stream:expr) => {
+        day!(
+            $day,
+            $input,
+            |input, _| $day::part1(input),
+            |input, _| $day::part2(input),
+            $export,
+            $stream
+        )
+    };
+    ($day:ident, $input:literal, $part1:expr, $part2:expr, $export:expr, $stream:expr) => {
+        Day {
+            input: include_str!(concat!("../input/", $input)),
+            part1: $part1,
+            part2: $part2,
+            export: $export,
+            stream: $stream,
+        }
+    };
+}
+
+const DAYS: [Day; 25] = [
+    day!(day1, "1"),
+    day!(day2, "2"),
+    day!(day3, "3"),
+    day!(day4, "4", export),
+    day!(day5, "5"),
+    day!(day6, "6", stream),
+    day!(day7, "7"),
+    day!(day8, "8", export),
+    day!(day9, "9"),
+    day!(day10, "10"),
+    day!(day11, "11"),
+    day!(day12, "12"),
+    day!(day13, "13"),
+    day!(day14, "14", export),
+    day!(day15, "15"),
+    day!(day16, "16", export),
+    day!(
+        day17,
+        "17",
+        |input, options| day17::part1_with(input, options.rocks(), options.chamber),
+        |input, options| day17::part2_with(input, options.rocks(), options.chamber),
+        None,
+        None
+    ),
+    day!(day18, "18", export),
+    day!(
+        day19,
+        "19",
+        |input, options| day19::part1_with(input, options.threads()),
+        |input, options| day19::part2_with(input, options.threads()),
+        Some(|input, format, options| day19::export(input, format, options.threads())),
+        None
+    ),
+    day!(day20, "20"),
+    day!(day21, "21"),
+    day!(day22, "22"),
+    day!(day23, "23"),
+    day!(day24, "24"),
+    day!(day25, "25"),
+];
+
+const USAGE: &str = "usage: aoc22 [DAY|all] [--stdin] [--export FORMAT] [--threads N]
+             [--rocks FILE] [--chamber WIDTH,GAP,OFFSET]";
+
+fn run(number: usize, input: &str, export: Option<&str>, options: &Options) -> Result<(), String> {
+    let day = &DAYS[number - 1];
+    if let Some(format) = export {
+        let export = day
+            .export
+            .ok_or(format!("day {} has nothing to export", number))?;
+        let output = export(input, format, options)?
+            .ok_or(format!("day {} can't export {}", number, format))?;
+        println!("{}", output);
+        return Ok(());
+    }
+    print_answers(
+        number,
+        (day.part1)(input, options),
+        (day.part2)(input, options),
+    );
+    Ok(())
+}
+
+fn print_answers(number: usize, part1: Answer, part2: Answer) {
+    println!("Day {}", number);
+    println!("Part 1: {}", part1);
+    println!("Part 2: {}", part2);
+}
+
 fn main() {
-    // day1::run();
-    // day2::run();
-    // day3::run();
-    // day4::run();
-    // day5::run();
-    // day6::run();
-    // day7::run();
-    // day8::run();
-    // day9::run();
-    // day10::run();
-    // day11::run();
-    // day12::run();
-    // day13::run();
-    // day14::run();
-    // day15::run();
-    // day16::run();
-    // day17::run();
-    // day18::run();
-    // day19::run();
-    // day20::run();
-    // day21::run();
-    // day22::run();
-    // day23::run();
-    // day24::run();
-    day25::run();
+    let mut days = vec![25];
+    let mut stdin = false;
+    let mut export = None;
+    let mut options = Options::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "all" => days = (1..=DAYS.len()).collect(),
+            "--stdin" => stdin = true,
+            "--export" => export = args.next(),
+            "--threads" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => options.threads = n,
+                None => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            "--rocks" => match args.next().map(std::fs::read_to_string) {
+                Some(Ok(shapes)) => options.rocks = Some(shapes),
+                Some(Err(e)) => {
+                    eprintln!("Error reading rocks: {}", e);
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            "--chamber" => match args.next().map(|config| config.parse()) {
+                Some(Ok(config)) => options.chamber = config,
+                Some(Err(e)) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(2);
+                }
+                None => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            _ => match arg.parse::<usize>() {
+                Ok(day) if (1..=DAYS.len()).contains(&day) => days = vec![day],
+                _ => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+        }
+    }
+
+    //A single day that can stream doesn't need all of stdin in memory
+    if let (&[day], true, None) = (days.as_slice(), stdin, &export) {
+        if let Some(stream) = DAYS[day - 1].stream {
+            match stream(&mut std::io::stdin().lock()) {
+                Ok((part1, part2)) => print_answers(day, part1, part2),
+                Err(e) => {
+                    eprintln!("Error reading stdin: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+    }
+
+    let mut input = String::new();
+    if stdin {
+        if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+            eprintln!("Error reading stdin: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    for day in days {
+        let input = if stdin { &input } else { DAYS[day - 1].input };
+        if let Err(e) = run(day, input, export.as_deref(), &options) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use dot::{Dot, Edge, Highlight, Node};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
@@ -159,47 +160,119 @@ impl FromStr for PartRating {
     }
 }
 
+//Result of the first expression in the rule that matches the part
+fn step<'a>(rating: &PartRating, rule: &'a Rule) -> &'a RuleResult {
+    let fields = [rating.0, rating.1, rating.2, rating.3];
+    rule.expressions
+        .iter()
+        .find(|expression| match &expression.condition {
+            None => true,
+            Some(Condition::LessThan(num)) => fields[expression.field] < *num,
+            Some(Condition::GreaterThan(num)) => fields[expression.field] > *num,
+        })
+        .map_or(&RuleResult::Rejected, |expression| &expression.result)
+}
+
 fn solve(rating: &PartRating, rule: &Rule, rules: &HashMap<String, Rule>) -> RuleResult {
-    for expression in rule.expressions.iter() {
-        let field = match expression.field {
-            0 => rating.0,
-            1 => rating.1,
-            2 => rating.2,
-            3 => rating.3,
-            _ => panic!("Invalid field"),
-        };
+    match step(rating, rule) {
+        RuleResult::Name(name) => solve(rating, &rules[name], rules),
+        result => result.clone(),
+    }
+}
 
-        if match &expression.condition {
-            None => true,
-            Some(Condition::LessThan(num)) if field < *num => true,
-            Some(Condition::GreaterThan(num)) if field > *num => true,
-            _ => false,
-        } {
-            if let RuleResult::Name(name) = &expression.result {
-                let rule = rules.get(name).unwrap();
-                return solve(rating, rule, rules);
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::LessThan(num) => write!(f, "<{}", num),
+            Condition::GreaterThan(num) => write!(f, ">{}", num),
+        }
+    }
+}
+
+impl RuleResult {
+    fn node(&self) -> &str {
+        match self {
+            RuleResult::Name(name) => name,
+            RuleResult::Accepted => "A",
+            RuleResult::Rejected => "R",
+        }
+    }
+}
+
+struct Workflows(HashMap<String, Rule>);
+
+impl Workflows {
+    fn parse(input: &str) -> Workflows {
+        Workflows(
+            input
+                .split("\n\n")
+                .next()
+                .expect("No rules")
+                .lines()
+                .map(|line| {
+                    let line = line.parse::<Rule>().unwrap();
+                    (line.name.clone(), line)
+                })
+                .collect(),
+        )
+    }
+
+    //Workflows a part passes through from "in" until it is accepted or rejected
+    fn trace(&self, rating: &PartRating) -> Vec<String> {
+        let mut trace = vec!["in".to_string()];
+        let mut rule = &self.0["in"];
+        loop {
+            let result = step(rating, rule);
+            trace.push(result.node().to_string());
+            match result {
+                RuleResult::Name(name) => rule = &self.0[name],
+                _ => return trace,
             }
-            return expression.result.clone();
         }
     }
+}
+
+impl Dot for Workflows {
+    fn graph_name(&self) -> String {
+        "workflows".to_string()
+    }
+
+    fn nodes(&self) -> Vec<Node> {
+        let mut names: Vec<&String> = self.0.keys().collect();
+        names.sort();
+        let mut nodes: Vec<Node> = names.into_iter().map(|name| Node::new(name)).collect();
+        nodes.push(Node::with_label("A", "accepted"));
+        nodes.push(Node::with_label("R", "rejected"));
+        nodes
+    }
 
-    RuleResult::Rejected
+    fn edges(&self) -> Vec<Edge> {
+        let mut names: Vec<&String> = self.0.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .flat_map(|name| {
+                self.0[name]
+                    .expressions
+                    .iter()
+                    .map(move |expression| match &expression.condition {
+                        Some(condition) => {
+                            let field = ['x', 'm', 'a', 's'][expression.field];
+                            let label = format!("{}{}", field, condition);
+                            Edge::with_label(name, expression.result.node(), &label)
+                        }
+                        None => Edge::new(name, expression.result.node()),
+                    })
+            })
+            .collect()
+    }
 }
 
 fn part1(input: &str) -> u32 {
-    let mut input = input.split("\n\n");
-
-    let rules = input
-        .next()
-        .expect("No rules")
-        .lines()
-        .map(|line| {
-            let line = line.parse::<Rule>().unwrap();
-            (line.name.clone(), line)
-        })
-        .collect::<HashMap<_, _>>();
+    let rules = Workflows::parse(input).0;
     let ratings = input
-        .next()
+        .split("\n\n")
+        .nth(1)
         .expect("No ratings")
         .lines()
         .map(|line| line.parse::<PartRating>().unwrap())
@@ -306,17 +379,7 @@ fn combinations(rule: &Rule, rules: &HashMap<String, Rule>, ranges: Vec<Range>)
 }
 
 fn part2(input: &str) -> u64 {
-    let mut input = input.split("\n\n");
-
-    let rules = input
-        .next()
-        .expect("No rules")
-        .lines()
-        .map(|line| {
-            let line = line.parse::<Rule>().unwrap();
-            (line.name.clone(), line)
-        })
-        .collect::<HashMap<_, _>>();
+    let rules = Workflows::parse(input).0;
 
     let ranges = vec![
         Range { min: 1, max: 4000 },
@@ -329,8 +392,25 @@ fn part2(input: &str) -> u64 {
 }
 
 fn main() {
-    println!("AoC 2023 - Day 1");
     let input = include_str!("input");
+
+    //Print the workflows with the path of the first part for rendering with dot
+    if std::env::args().any(|arg| arg == "--dot") {
+        let workflows = Workflows::parse(input);
+        let rating = input
+            .split("\n\n")
+            .nth(1)
+            .and_then(|ratings| ratings.lines().next())
+            .map(|line| line.parse::<PartRating>().unwrap());
+        let highlight = match rating {
+            Some(rating) => Highlight::new().path(&workflows.trace(&rating)),
+            None => Highlight::new(),
+        };
+        println!("{}", workflows.to_dot(&highlight));
+        return;
+    }
+
+    println!("AoC 2023 - Day 1");
     println!("Part 1: {}", part1(input));
     println!("Part 2: {}", part2(input));
 }
@@ -351,6 +431,23 @@ mod tests {
         assert_eq!(part2(input), 167409079868000);
     }
 
+    #[test]
+    fn dot_test() {
+        let workflows = Workflows::parse(include_str!("test"));
+        let rating = "{x=787,m=2655,a=1222,s=2876}"
+            .parse::<PartRating>()
+            .unwrap();
+        let trace = workflows.trace(&rating);
+        assert_eq!(trace, vec!["in", "qqz", "qs", "lnx", "A"]);
+
+        let dot = workflows.to_dot(&Highlight::new().path(&trace));
+        assert!(dot.contains("\"in\" -> \"px\" [label=\"s<1351\"];"));
+        assert!(dot.contains("\"in\" -> \"qqz\" [color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"qqz\" -> \"qs\" [label=\"s>2770\", color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"lnx\" -> \"A\" [color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"R\" [label=\"rejected\"];"));
+    }
+
     #[test]
     fn combinations_test() {
         let input = include_str!("test");
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use dot::{Dot, Edge, Highlight};
+use std::collections::{HashMap, HashSet};
 #[derive(Debug)]
 enum Instruction {
     Left,
@@ -63,6 +64,73 @@ impl From<&str> for Path {
     }
 }
 
+#[derive(Debug)]
+struct Network(HashMap<Node, Path>);
+
+impl Network {
+    fn parse(input: &str) -> (Instructions, Network) {
+        let mut lines = input.lines();
+        let instructions = Instructions::new(lines.next().unwrap());
+
+        let mut map = HashMap::new();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut words = line.split("=");
+            let node = words.next().unwrap().trim();
+            let path = Path::from(words.next().unwrap().trim());
+            map.insert(node.to_string(), path);
+        }
+        (instructions, Network(map))
+    }
+
+    //Nodes visited following the instructions from start until an end node.
+    //None when the walk gets back to a node at the same instruction without
+    //reaching the end, or runs into a node that isn't in the network
+    fn route(&self, instructions: &mut Instructions, start: &str, end: &str) -> Option<Vec<Node>> {
+        let mut route = vec![start.to_string()];
+        let mut seen = HashSet::new();
+        let mut current = start;
+        while current != end {
+            if !seen.insert((current, instructions.index)) {
+                return None;
+            }
+            let path = self.0.get(current)?;
+            current = match instructions.next()? {
+                Instruction::Left => &path.0,
+                Instruction::Right => &path.1,
+            };
+            route.push(current.to_string());
+        }
+        Some(route)
+    }
+}
+
+impl Dot for Network {
+    fn graph_name(&self) -> String {
+        "network".to_string()
+    }
+
+    fn nodes(&self) -> Vec<dot::Node> {
+        let mut nodes: Vec<&Node> = self.0.keys().collect();
+        nodes.sort();
+        nodes.into_iter().map(|node| dot::Node::new(node)).collect()
+    }
+
+    fn edges(&self) -> Vec<Edge> {
+        let mut nodes: Vec<&Node> = self.0.keys().collect();
+        nodes.sort();
+        nodes
+            .into_iter()
+            .flat_map(|node| {
+                let path = &self.0[node];
+                [
+                    Edge::with_label(node, &path.0, "L"),
+                    Edge::with_label(node, &path.1, "R"),
+                ]
+            })
+            .collect()
+    }
+}
+
 fn is_start_node(node: &str) -> bool {
     node.ends_with("A")
 }
@@ -91,65 +159,22 @@ fn lcm_array(a: &[u64]) -> u64 {
 }
 
 fn part1(input: &str) -> u32 {
-    let mut lines = input.lines();
-    let mut instructions = Instructions::new(lines.next().unwrap());
-
-    let mut map = HashMap::new();
-    while let Some(line) = lines.next() {
-        if line.is_empty() {
-            continue;
-        }
-        let mut words = line.split("=");
-        let node = words.next().unwrap().trim();
-
-        let path = Path::from(words.next().unwrap().trim());
-
-        map.insert(node, path);
-    }
-
-    let mut current_node = "AAA";
-    let mut steps = 0;
-
-    while let Some(instruction) = instructions.next() {
-        if current_node == "ZZZ" {
-            break;
-        }
-        let path = map.get(&current_node).unwrap();
-        match instruction {
-            Instruction::Left => {
-                current_node = &path.0;
-            }
-            Instruction::Right => {
-                current_node = &path.1;
-            }
-        }
-        steps += 1;
-    }
-    steps
+    let (mut instructions, network) = Network::parse(input);
+    let route = network
+        .route(&mut instructions, "AAA", "ZZZ")
+        .expect("ZZZ can't be reached");
+    route.len() as u32 - 1
 }
 
 fn part2(input: &str) -> u64 {
-    let mut lines = input.lines();
-    let mut instructions = Instructions::new(lines.next().unwrap());
-
-    let mut map = HashMap::new();
+    let (mut instructions, network) = Network::parse(input);
+    let map = &network.0;
 
-    let mut current_nodes = Vec::new();
-    while let Some(line) = lines.next() {
-        if line.is_empty() {
-            continue;
-        }
-        let mut words = line.split("=");
-        let node = words.next().unwrap().trim();
-
-        let path = Path::from(words.next().unwrap().trim());
-
-        map.insert(node, path);
-
-        if is_start_node(&node) {
-            current_nodes.push(node);
-        }
-    }
+    let mut current_nodes: Vec<&str> = map
+        .keys()
+        .map(|node| node.as_str())
+        .filter(|node| is_start_node(node))
+        .collect();
 
     let mut steps = 0;
     let mut cycles = vec![(0, 0); current_nodes.len()];
@@ -180,7 +205,7 @@ fn part2(input: &str) -> u64 {
                     cycles[i].0 = steps - cycles[i].1;
                 }
             }
-            let path = map.get(node).unwrap();
+            let path = map.get(*node).unwrap();
             match instruction {
                 Instruction::Left => {
                     *node = &path.0;
@@ -201,8 +226,19 @@ fn part2(input: &str) -> u64 {
 }
 
 fn main() {
-    println!("AoC 2023 - Day 1");
     let input = include_str!("input");
+
+    //Print the network with the route from AAA to ZZZ for rendering with dot
+    if std::env::args().any(|arg| arg == "--dot") {
+        let (mut instructions, network) = Network::parse(input);
+        let route = network
+            .route(&mut instructions, "AAA", "ZZZ")
+            .unwrap_or_default();
+        println!("{}", network.to_dot(&Highlight::new().path(&route)));
+        return;
+    }
+
+    println!("AoC 2023 - Day 1");
     println!("Part 1: {}", part1(input));
     println!("Part 2: {}", part2(input));
 }
@@ -220,6 +256,29 @@ mod tests {
         assert_eq!(part1(input), 6);
     }
 
+    #[test]
+    fn dot_test() {
+        let (mut instructions, network) = Network::parse(include_str!("test2"));
+        let route = network.route(&mut instructions, "AAA", "ZZZ").unwrap();
+        assert_eq!(route, vec!["AAA", "BBB", "AAA", "BBB", "AAA", "BBB", "ZZZ"]);
+
+        let dot = network.to_dot(&Highlight::new().path(&route));
+        assert!(dot.starts_with("digraph \"network\" {\n"));
+        assert!(dot.contains("\"AAA\" -> \"BBB\" [label=\"L\", color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"BBB\" -> \"ZZZ\" [label=\"R\", color=\"red\", penwidth=2];"));
+        assert!(dot.contains("\"ZZZ\" -> \"ZZZ\" [label=\"L\"];"));
+    }
+
+    #[test]
+    fn unreachable_test() {
+        let input = "LR\n\nAAA = (BBB, BBB)\nBBB = (AAA, AAA)\nZZZ = (ZZZ, ZZZ)\n";
+        let (mut instructions, network) = Network::parse(input);
+        assert_eq!(network.route(&mut instructions, "AAA", "ZZZ"), None);
+
+        let (mut instructions, network) = Network::parse("L\n\nAAA = (XXX, XXX)\n");
+        assert_eq!(network.route(&mut instructions, "AAA", "ZZZ"), None);
+    }
+
     #[test]
     fn part2_test() {
         let input = include_str!("test3");
@@ -1,4 +1,5 @@
-use std::collections::VecDeque;
+use dot::{Dot, Edge, Highlight, Node};
+use std::collections::{BTreeSet, VecDeque};
 use std::str::FromStr;
 
 #[derive(Debug, Clone)]
@@ -23,6 +24,29 @@ impl FromStr for Rule {
     }
 }
 
+struct PageOrdering(Vec<Rule>);
+
+impl Dot for PageOrdering {
+    fn graph_name(&self) -> String {
+        "page_ordering".to_string()
+    }
+
+    fn nodes(&self) -> Vec<Node> {
+        let pages: BTreeSet<u32> = self.0.iter().flat_map(|rule| [rule.x, rule.y]).collect();
+        pages
+            .into_iter()
+            .map(|page| Node::new(&page.to_string()))
+            .collect()
+    }
+
+    fn edges(&self) -> Vec<Edge> {
+        self.0
+            .iter()
+            .map(|rule| Edge::new(&rule.x.to_string(), &rule.y.to_string()))
+            .collect()
+    }
+}
+
 fn validate_instruction(rules: Vec<Rule>, mut instruction: VecDeque<u32>) -> bool {
     if instruction.len() < 2 {
         return true;
@@ -107,8 +131,27 @@ fn part2(input: &str) -> u32 {
 }
 
 fn main() {
-    println!("AoC 2024 - Day 1");
     let input = include_str!("input");
+
+    //Print the rules with the first update highlighted for rendering with dot
+    if std::env::args().any(|arg| arg == "--dot") {
+        let mut sections = input.split("\n\n");
+        let rules = sections
+            .next()
+            .unwrap()
+            .lines()
+            .map(|l| l.parse::<Rule>().unwrap())
+            .collect::<Vec<Rule>>();
+        let update = sections
+            .next()
+            .and_then(|updates| updates.lines().next())
+            .map_or(vec![], |l| l.split(',').collect::<Vec<&str>>());
+        let highlight = Highlight::new().path(&update).visited(update.clone());
+        println!("{}", PageOrdering(rules).to_dot(&highlight));
+        return;
+    }
+
+    println!("AoC 2024 - Day 1");
     println!("Part 1: {}", part1(input));
     println!("Part 2: {}", part2(input));
 }
@@ -123,6 +166,27 @@ mod tests {
         assert_eq!(part1(input), 143);
     }
 
+    #[test]
+    fn dot_test() {
+        let rules = include_str!("test")
+            .split("\n\n")
+            .next()
+            .unwrap()
+            .lines()
+            .map(|l| l.parse::<Rule>().unwrap())
+            .collect::<Vec<Rule>>();
+
+        let update = ["75", "47", "61"];
+        let dot = PageOrdering(rules).to_dot(&Highlight::new().path(&update).visited(update));
+        assert!(dot.starts_with("digraph \"page_ordering\" {\n    \"13\";\n"));
+        assert!(dot.contains("\"47\" -> \"53\";"));
+        assert!(dot.contains("\"75\" -> \"47\" [color=\"red\", penwidth=2];"));
+        assert!(dot.contains(
+            "\"61\" [style=\"filled\", fillcolor=\"lightgrey\", color=\"red\", penwidth=2];"
+        ));
+        assert_eq!(dot.matches(" -> ").count(), 21);
+    }
+
     #[test]
     fn part2_test() {
         let input = include_str!("test");